@@ -0,0 +1,3733 @@
+use chrono::{Days, Local, Months, NaiveDate, NaiveDateTime};
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use directories::ProjectDirs;
+use ratatui::layout::{Position, Rect};
+use ratatui::style::Color;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::config::{
+    Config, EditorMode, KeyMap, PALETTE, Startup, Theme, UserSort, WritingPrompt, key_label,
+};
+use crate::editor::{Editor, EditorAction, Mode};
+use crate::error::AppError;
+use crate::export;
+use crate::input;
+use crate::prompts;
+use crate::search;
+use crate::settings::{
+    Draft, JournalSettings, PageRef, RecoveryFile, is_reserved_page, parse_tags,
+};
+use crate::stats::JournalStats;
+use crate::store::DecryptedStore;
+use crate::viewer::{ViewOptions, Viewer};
+
+pub const ADD_USER_ITEM: &str = "Add New User";
+pub const ADD_PAGE_ITEM: &str = "Add Page";
+pub const NEW_CHAPTER_ITEM: &str = "New Chapter";
+pub const ADD_ATTACHMENT_ITEM: &str = "Add Attachment";
+/// Badge for chapters without a page in them yet.
+pub const EMPTY_BADGE: &str = "empty";
+/// Badge for the sub-chapters listed above the pages of a topic.
+pub const SUB_CHAPTER_BADGE: &str = "chapter";
+/// Badge for `.clog` entries in the data directory that can't be a journal.
+pub const UNRECOGNIZED_BADGE: &str = "unrecognized";
+/// Badge for journals that are truncated or failed to load as damaged.
+pub const CORRUPT_BADGE: &str = "corrupt";
+/// Marks the copies of recently unlocked journals pinned above the list.
+pub const RECENT_BADGE: &str = "recent";
+/// Badges for the days of the chapter list written today and yesterday,
+/// which stand out from the rest.
+pub const TODAY_BADGE: &str = "Today";
+pub const YESTERDAY_BADGE: &str = "Yesterday";
+/// Format edit times are stored in, whatever `datetime_format` shows.
+pub const TIMESTAMP_FORMAT: &str = "%d/%m/%Y %H:%M";
+/// How long before auto-lock the footer starts counting down.
+const AUTO_LOCK_WARNING: Duration = Duration::from_secs(30);
+/// How long a count or a first `g` waits for the motion that uses it.
+const PENDING_TIMEOUT: Duration = Duration::from_secs(1);
+const MAX_COUNT: usize = 9999;
+/// Longest wait for input when nothing on screen is counting down.
+const IDLE_POLL: Duration = Duration::from_secs(60);
+/// How long a status line message stays in the footer.
+const STATUS_TTL: Duration = Duration::from_secs(4);
+/// Shortest wait, so a countdown landing on a whole second doesn't spin.
+const MIN_POLL: Duration = Duration::from_millis(10);
+/// Columns moved by one press of h/l in the viewer when lines aren't wrapped.
+const HSCROLL_STEP: usize = 8;
+/// How long after an edit in the inline editor its draft is written.
+const DRAFT_INTERVAL: Duration = Duration::from_secs(5);
+/// Edits that write the draft straight away, however quickly typed.
+const DRAFT_EDITS: usize = 50;
+/// Keys that work on every screen with a journal open but aren't in the
+/// footer, for the help overlay.
+const GLOBAL_KEYS: &[(&str, &str)] = &[
+    ("^P", "Go to page"),
+    ("^T", "Today's pages"),
+    ("^Q", "Quit, asking first if text would be lost"),
+];
+/// Motions shared by every list, for the help overlay.
+const LIST_KEYS: &[(&str, &str)] = &[
+    ("gg / G", "First / last row"),
+    ("5j / 5k", "Move by a count"),
+    ("Click / double-click", "Select / open"),
+];
+/// Two clicks on the same row within this time open it.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// Keys and the action they perform, as shown in the footer and the help.
+pub type KeyHint = (Cow<'static, str>, &'static str);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AppState {
+    /// The configured profiles, shown first when there is more than one.
+    SelectProfile,
+    /// An inline editor draft left by a session that didn't finish, offered
+    /// back after unlocking.
+    RecoverDraft(String, String),
+    /// Another session holds the journal's lock: open it read-only, or
+    /// force-unlock it. Carries what the lock says about that session.
+    JournalInUse(String, String, String),
+    SelectUser,
+    /// What the external editor returned lately, kept in case saving it
+    /// failed.
+    Recoveries,
+    /// The recovery with this file name, asked the password of its journal.
+    OpenRecovery(String),
+    /// A recovery opened on `App::recovery`, to copy or read.
+    ViewRecovery(String),
+    EnterNewUser,
+    EnterPassword(String),
+    SelectFolder(String, String),
+    SelectFile(String, String, String),
+    EditOrViewFile(String, String, String, String),
+    ViewPage(String, String, String, String),
+    InputPrompt(String, Box<AppState>),
+    AddPagePrompt(String, String, String),
+    /// Name of a topic chapter to create, inside the topic given last.
+    NewChapter(String, String, Option<String>),
+    EditJournalTitle(String, String),
+    EditJournalDescription(String, String, String),
+    SearchChapter(String, String, String),
+    ChapterResults(String, String, String, String),
+    SearchPrompt(String, String),
+    SearchResults(String, String, String),
+    Export(String, String),
+    Import(String, String),
+    /// Month view of the chapters with the day under the cursor.
+    Calendar(String, String, NaiveDate),
+    RenamePage(String, String, String, String),
+    /// Name for a copy of a page, asked for before it is made.
+    DuplicatePage(String, String, String, String),
+    /// Sharing a page as an encrypted `.clogpage`: the file to write, then,
+    /// once it is confirmed, the passphrase.
+    ExportPage(String, String, String, String, Option<PathBuf>),
+    /// Markdown file to write the chapter's pages to.
+    ExportChapter(String, String, String),
+    /// Adding a shared `.clogpage` to the chapter: the file to read, then
+    /// its passphrase.
+    ImportPage(String, String, String, Option<String>),
+    /// Tags asked for right after a new page is named.
+    NewPageTags(String, String, String, String),
+    EditTags(String, String, String, String),
+    /// Every tag in the journal with its page count.
+    SelectTag(String, String),
+    TagResults(String, String, String),
+    /// Every page of the journal, newest first.
+    Timeline(String, String),
+    /// Entry count, streak and word totals of the journal.
+    Stats(String, String),
+    /// Deleted pages, newest first, to restore or purge.
+    Trash(String, String),
+    /// Files attached to a page.
+    Attachments(String, String, String, String),
+    /// Path of a file to attach to the page, asked for on the input line.
+    AttachFile(String, String, String, String),
+    /// Directory to extract the attachment held in the hidden page to.
+    ExtractAttachment(String, String, String, String, String),
+    /// Pages filtered by a fuzzy match of their `folder/page` name against
+    /// the input line.
+    QuickOpen(String, String),
+    /// The inline editor is open on `App::editor`; saving performs the write.
+    InlineEdit(PageWrite),
+    /// Text to replace across the inline editor's page, then, once it is
+    /// known, what to replace it with.
+    ReplaceText(PageWrite, Option<String>),
+    /// Lock screen. Holds the screen to return to, with its password wiped,
+    /// and the selection it had.
+    Locked(Box<AppState>, usize),
+    /// Yes/no question: yes performs the effect, no returns to the state.
+    Confirm(String, Box<SideEffect>, Box<AppState>),
+}
+
+impl AppState {
+    /// Whether the input line holds a password: unlocking or creating a
+    /// journal, or the lock screen.
+    pub fn is_password_prompt(&self) -> bool {
+        match self {
+            AppState::InputPrompt(_, next) => matches!(
+                **next,
+                AppState::EnterPassword(_)
+                    | AppState::SelectFolder(_, _)
+                    | AppState::ExportPage(_, _, _, _, Some(_))
+                    | AppState::ImportPage(_, _, _, Some(_))
+                    | AppState::OpenRecovery(_)
+            ),
+            AppState::Locked(_, _) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether this is one of the screens for finding your way around an
+    /// open journal, from which the jump keys work.
+    pub fn is_navigation(&self) -> bool {
+        matches!(
+            self,
+            AppState::SelectFolder(_, _)
+                | AppState::SelectFile(_, _, _)
+                | AppState::ChapterResults(_, _, _, _)
+                | AppState::SearchResults(_, _, _)
+                | AppState::SelectTag(_, _)
+                | AppState::TagResults(_, _, _)
+                | AppState::Timeline(_, _)
+                | AppState::Stats(_, _)
+                | AppState::Trash(_, _)
+                | AppState::Attachments(_, _, _, _)
+                | AppState::Calendar(_, _, _)
+                | AppState::ViewPage(_, _, _, _)
+        )
+    }
+
+    /// The journal open in this state, if any.
+    pub fn user_path(&self) -> Option<&str> {
+        match self {
+            AppState::SelectFolder(user_path, _)
+            | AppState::SelectFile(user_path, _, _)
+            | AppState::EditOrViewFile(user_path, _, _, _)
+            | AppState::ViewPage(user_path, _, _, _)
+            | AppState::AddPagePrompt(user_path, _, _)
+            | AppState::NewChapter(user_path, _, _)
+            | AppState::EditJournalTitle(user_path, _)
+            | AppState::EditJournalDescription(user_path, _, _)
+            | AppState::SearchChapter(user_path, _, _)
+            | AppState::ChapterResults(user_path, _, _, _)
+            | AppState::SearchPrompt(user_path, _)
+            | AppState::SearchResults(user_path, _, _)
+            | AppState::Calendar(user_path, _, _)
+            | AppState::Export(user_path, _)
+            | AppState::Import(user_path, _) => Some(user_path),
+            AppState::RenamePage(user_path, _, _, _)
+            | AppState::DuplicatePage(user_path, _, _, _)
+            | AppState::ExportPage(user_path, _, _, _, _)
+            | AppState::ExportChapter(user_path, _, _)
+            | AppState::ImportPage(user_path, _, _, _)
+            | AppState::NewPageTags(user_path, _, _, _)
+            | AppState::EditTags(user_path, _, _, _)
+            | AppState::Attachments(user_path, _, _, _)
+            | AppState::AttachFile(user_path, _, _, _)
+            | AppState::ExtractAttachment(user_path, _, _, _, _) => Some(user_path),
+            AppState::SelectTag(user_path, _)
+            | AppState::TagResults(user_path, _, _)
+            | AppState::Timeline(user_path, _)
+            | AppState::Stats(user_path, _)
+            | AppState::Trash(user_path, _)
+            | AppState::RecoverDraft(user_path, _)
+            | AppState::JournalInUse(user_path, _, _)
+            | AppState::QuickOpen(user_path, _) => Some(user_path),
+            AppState::InlineEdit(write) | AppState::ReplaceText(write, _) => Some(&write.user_path),
+            AppState::InputPrompt(_, next)
+            | AppState::Confirm(_, _, next)
+            | AppState::Locked(next, _) => next.user_path(),
+            _ => None,
+        }
+    }
+
+    /// The password of the journal open in this state, if any.
+    pub fn password_mut(&mut self) -> Option<&mut String> {
+        match self {
+            AppState::SelectFolder(_, password)
+            | AppState::SelectFile(_, password, _)
+            | AppState::EditOrViewFile(_, password, _, _)
+            | AppState::ViewPage(_, password, _, _)
+            | AppState::AddPagePrompt(_, password, _)
+            | AppState::NewChapter(_, password, _)
+            | AppState::EditJournalTitle(_, password)
+            | AppState::EditJournalDescription(_, password, _)
+            | AppState::SearchChapter(_, password, _)
+            | AppState::ChapterResults(_, password, _, _)
+            | AppState::SearchPrompt(_, password)
+            | AppState::SearchResults(_, password, _)
+            | AppState::Calendar(_, password, _)
+            | AppState::Export(_, password)
+            | AppState::Import(_, password) => Some(password),
+            AppState::RenamePage(_, password, _, _)
+            | AppState::DuplicatePage(_, password, _, _)
+            | AppState::ExportPage(_, password, _, _, _)
+            | AppState::ExportChapter(_, password, _)
+            | AppState::ImportPage(_, password, _, _)
+            | AppState::NewPageTags(_, password, _, _)
+            | AppState::EditTags(_, password, _, _)
+            | AppState::Attachments(_, password, _, _)
+            | AppState::AttachFile(_, password, _, _)
+            | AppState::ExtractAttachment(_, password, _, _, _) => Some(password),
+            AppState::SelectTag(_, password)
+            | AppState::TagResults(_, password, _)
+            | AppState::Timeline(_, password)
+            | AppState::Stats(_, password)
+            | AppState::Trash(_, password)
+            | AppState::RecoverDraft(_, password)
+            | AppState::JournalInUse(_, password, _)
+            | AppState::QuickOpen(_, password) => Some(password),
+            AppState::InlineEdit(write) | AppState::ReplaceText(write, _) => {
+                Some(&mut write.password)
+            }
+            // The password the confirmed effect carries is the same one;
+            // `lock` and the lock screen deal with it.
+            AppState::InputPrompt(_, next) | AppState::Confirm(_, _, next) => next.password_mut(),
+            _ => None,
+        }
+    }
+}
+
+/// Work the driver has to perform after a state transition. `handle_event`
+/// never touches the terminal, the filesystem or the clog itself; it only
+/// describes what should happen next.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SideEffect {
+    LoadUsers,
+    Unlock(String, String),
+    CreateUser(String, String),
+    /// Remove the lock another session left on the journal, then unlock it.
+    ForceUnlock(String, String),
+    LoadRecoveries,
+    /// Open the recovery with the password of its journal.
+    OpenRecovery(String, String),
+    DiscardRecovery(String),
+    LoadFolders(String, String),
+    LoadFiles(String, String, String),
+    /// Reads a page of the page list for the preview pane.
+    LoadPreview(String, String, String, String),
+    OpenPage(String, String, String, String),
+    AddPage(String, String, String, String, Vec<String>),
+    /// Creates an empty topic chapter with the given name.
+    AddFolder(String, String, String, Option<String>),
+    /// Puts back the pages held by `App::undo`.
+    Undo(String, String),
+    /// Writes a new page over an existing one of the same name.
+    ReplacePage(String, String, String, String, Vec<String>),
+    SaveInlineEdit(PageWrite),
+    WritePage(PageWrite),
+    SaveJournalInfo(String, String, String, String),
+    SearchChapter(String, String, String, String),
+    SearchJournal(String, String, String),
+    DeletePages(String, String, String, Vec<String>),
+    RenamePage(String, String, String, String, String),
+    /// Copies the page's content into a new page of the given name.
+    DuplicatePage(String, String, String, String, String),
+    SaveTags(String, String, String, String, Vec<String>),
+    LoadTags(String, String),
+    LoadTagged(String, String, String),
+    LoadTimeline(String, String),
+    LoadQuickOpen(String, String),
+    LoadStats(String, String),
+    LoadTrash(String, String),
+    /// Puts the page at this index of the trash back where it was deleted.
+    RestoreTrashed(String, String, usize),
+    /// Drops the page at this index of the trash for good.
+    PurgeTrashed(String, String, usize),
+    LoadAttachments(String, String, String, String),
+    /// Check the size of the file at the path and ask first if it is large.
+    PrepareAttach(String, String, String, String, String),
+    Attach(String, String, String, String, PathBuf),
+    /// Write the attachment in the hidden page to the directory.
+    ExtractAttachment(String, String, String, String, String, String),
+    /// Erase the attachment in the hidden page.
+    RemoveAttachment(String, String, String, String, String),
+    LoadProfiles,
+    /// Saves the pages of a chapter in this order, then highlights the row.
+    ReorderPages(String, String, String, Vec<String>, usize),
+    LoadDraft(String, String),
+    /// Writes the inline editor's content as the journal's draft.
+    SaveDraft(PageWrite),
+    /// Deletes the journal's draft, then goes to the state.
+    DiscardDraft(String, Box<AppState>),
+    /// Use the data directory of the configured profile at this index.
+    SwitchProfile(usize),
+    /// Ask whether to restore a damaged journal from its backup, if it has
+    /// one.
+    PrepareRestore(String),
+    RestoreBackup(String),
+    /// Count what an export to the directory would write and ask first.
+    PrepareExport(String, String, String),
+    Export(String, String, PathBuf),
+    /// List the files an import from the directory would read and ask first.
+    PrepareImport(String, String, String),
+    Import(String, String, PathBuf),
+    /// Lock or unlock a page of the chapter against changes.
+    SetLocked(String, String, String, String, bool),
+    /// Colour a chapter on the chapter list, or take its colour away.
+    SetChapterColor(String, String, String, Option<String>),
+    /// Ask for the passphrase to share a page with at the confirmed path,
+    /// unless a file is already there.
+    SharePage(String, String, String, String, PathBuf),
+    /// Write a page of the chapter to a `.clogpage` under the passphrase.
+    ExportPage(String, String, String, String, PathBuf, String),
+    /// Put the chapter's pages on the clipboard as Markdown.
+    CopyChapter(String, String, String),
+    /// Count the chapter's pages and ask before exporting them to the path.
+    PrepareExportChapter(String, String, String, String),
+    /// Write the chapter's pages as Markdown to a new file at the path.
+    ExportChapter(String, String, String, PathBuf),
+    /// Add the page of a `.clogpage` to the chapter, given its passphrase.
+    ImportPage(String, String, String, String, String),
+    /// Puts the text on the system clipboard.
+    Copy(String),
+    LoadDictionary,
+    /// Re-open the journal of a locked screen; the state carries the
+    /// password that was entered.
+    Resume(Box<AppState>, usize),
+    Quit,
+}
+
+impl SideEffect {
+    /// The password of the journal the effect works on, if it carries one.
+    pub fn password_mut(&mut self) -> Option<&mut String> {
+        match self {
+            SideEffect::Unlock(_, password)
+            | SideEffect::CreateUser(_, password)
+            | SideEffect::ForceUnlock(_, password)
+            | SideEffect::LoadFolders(_, password)
+            | SideEffect::LoadFiles(_, password, _)
+            | SideEffect::LoadPreview(_, password, _, _)
+            | SideEffect::OpenPage(_, password, _, _)
+            | SideEffect::AddPage(_, password, _, _, _)
+            | SideEffect::AddFolder(_, password, _, _)
+            | SideEffect::Undo(_, password)
+            | SideEffect::ReplacePage(_, password, _, _, _)
+            | SideEffect::SaveJournalInfo(_, password, _, _)
+            | SideEffect::SearchChapter(_, password, _, _)
+            | SideEffect::SearchJournal(_, password, _)
+            | SideEffect::DeletePages(_, password, _, _)
+            | SideEffect::RenamePage(_, password, _, _, _)
+            | SideEffect::DuplicatePage(_, password, _, _, _)
+            | SideEffect::SaveTags(_, password, _, _, _)
+            | SideEffect::LoadTags(_, password)
+            | SideEffect::LoadTagged(_, password, _)
+            | SideEffect::LoadTimeline(_, password)
+            | SideEffect::LoadQuickOpen(_, password)
+            | SideEffect::LoadStats(_, password)
+            | SideEffect::LoadTrash(_, password)
+            | SideEffect::RestoreTrashed(_, password, _)
+            | SideEffect::PurgeTrashed(_, password, _)
+            | SideEffect::LoadAttachments(_, password, _, _)
+            | SideEffect::PrepareAttach(_, password, _, _, _)
+            | SideEffect::Attach(_, password, _, _, _)
+            | SideEffect::ExtractAttachment(_, password, _, _, _, _)
+            | SideEffect::RemoveAttachment(_, password, _, _, _)
+            | SideEffect::ReorderPages(_, password, _, _, _)
+            | SideEffect::LoadDraft(_, password)
+            | SideEffect::PrepareExport(_, password, _)
+            | SideEffect::Export(_, password, _)
+            | SideEffect::PrepareImport(_, password, _)
+            | SideEffect::Import(_, password, _)
+            | SideEffect::SetLocked(_, password, _, _, _)
+            | SideEffect::SetChapterColor(_, password, _, _)
+            | SideEffect::SharePage(_, password, _, _, _)
+            | SideEffect::ExportPage(_, password, _, _, _, _)
+            | SideEffect::CopyChapter(_, password, _)
+            | SideEffect::PrepareExportChapter(_, password, _, _)
+            | SideEffect::ExportChapter(_, password, _, _)
+            | SideEffect::ImportPage(_, password, _, _, _) => Some(password),
+            SideEffect::SaveInlineEdit(write)
+            | SideEffect::WritePage(write)
+            | SideEffect::SaveDraft(write) => Some(&mut write.password),
+            SideEffect::DiscardDraft(_, state) | SideEffect::Resume(state, _) => {
+                state.password_mut()
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the effect can change a journal on disk, after which the
+    /// cached metadata in `DecryptedStore` is stale.
+    pub fn writes(&self) -> bool {
+        matches!(
+            self,
+            SideEffect::CreateUser(..)
+                | SideEffect::AddFolder(..)
+                | SideEffect::Undo(..)
+                | SideEffect::WritePage(_)
+                | SideEffect::SaveJournalInfo(..)
+                | SideEffect::DeletePages(..)
+                | SideEffect::RenamePage(..)
+                | SideEffect::DuplicatePage(..)
+                | SideEffect::SaveTags(..)
+                | SideEffect::SetLocked(..)
+                | SideEffect::SetChapterColor(..)
+                | SideEffect::ReorderPages(..)
+                | SideEffect::RestoreTrashed(..)
+                | SideEffect::PurgeTrashed(..)
+                | SideEffect::Attach(..)
+                | SideEffect::RemoveAttachment(..)
+                | SideEffect::RestoreBackup(_)
+                | SideEffect::Import(..)
+                | SideEffect::ImportPage(..)
+        )
+    }
+}
+
+/// The last destructive change, kept so `u` can take it back. One level
+/// only, and only in memory: it is gone after quitting, locking or
+/// switching users, and any later write replaces or drops it.
+pub struct Undo {
+    pub folder: String,
+    /// The pages as they were before the change.
+    pub pages: Vec<UndoPage>,
+    /// The pages were deleted rather than saved over.
+    pub deleted: bool,
+}
+
+pub struct UndoPage {
+    pub file: String,
+    pub content: Zeroizing<String>,
+    pub tags: Vec<String>,
+    pub edited: Option<String>,
+}
+
+impl Undo {
+    pub fn describe(&self) -> String {
+        let action = if self.deleted { "deleting" } else { "saving" };
+        match self.pages.as_slice() {
+            [page] => format!("{} '{}'", action, page.file),
+            pages => format!("{} {} pages", action, pages.len()),
+        }
+    }
+}
+
+/// A draft found on unlocking and the page it was made from.
+pub struct Recovery {
+    pub draft: Draft,
+    /// The page as saved in the journal; `None` for a new page or one
+    /// deleted since.
+    pub saved: Option<String>,
+    /// Shows the lines changed from `saved` instead of the draft itself.
+    pub compare: bool,
+    pub scroll: u16,
+}
+
+/// Page content coming back from the editor, on its way into the clog.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PageWrite {
+    pub user_path: String,
+    pub password: String,
+    pub folder: String,
+    pub file: String,
+    pub content: String,
+    /// Adds the page instead of updating an existing one.
+    pub new_page: bool,
+    /// Tags saved with a new page.
+    pub tags: Vec<String>,
+}
+
+pub struct App {
+    pub state: AppState,
+    pub selected_index: usize,
+    pub input_buffer: String,
+    pub input_cursor: usize,
+    /// What Enter on an empty input line submits, shown there until
+    /// something is typed.
+    pub input_default: Option<String>,
+    pub items: Vec<(String, String)>,
+    pub message: Option<(String, String)>,
+    /// The help overlay is drawn over the current screen, which is left
+    /// untouched underneath.
+    pub show_help: bool,
+    /// A password being typed is shown as it is instead of masked, until
+    /// the prompt is left.
+    pub reveal_password: bool,
+    /// Whether replacing in the inline editor tells upper and lower case
+    /// apart; Tab on the replace prompt flips it.
+    replace_match_case: bool,
+    /// Started with `--readonly`: nothing can be added, edited, renamed or
+    /// deleted, whatever the day.
+    pub readonly: bool,
+    /// The open journal was opened read-only because another session has
+    /// it; `readonly` goes back off when it is closed.
+    pub readonly_journal: bool,
+    /// Set at startup with `reopen_last_page`: the first journal unlocked
+    /// opens on its last edited page.
+    pub resume_last_page: bool,
+    /// Set at startup from `startup`: the screen the first journal unlocked
+    /// opens on, unless its last page is reopened.
+    pub startup: Startup,
+    pub undo: Option<Undo>,
+    /// Pages marked with Space on the page list for a bulk action.
+    pub marked: BTreeSet<String>,
+    /// Selection of each list screen visited this session, so going back to
+    /// a list lands on the row that was left.
+    remembered_selection: HashMap<String, usize>,
+    /// The page behind each row of the journal search results.
+    pub search_hits: Vec<PageRef>,
+    /// Content of the pages on the page list for the preview pane, by file
+    /// name, read as each is first highlighted. `None` when it couldn't be
+    /// read.
+    pub previews: HashMap<String, Option<String>>,
+    /// Totals shown on the statistics screen, once they are counted.
+    pub journal_stats: Option<JournalStats>,
+    /// Every page of the open journal, newest first, for the quick-open
+    /// filter.
+    pub all_pages: Vec<PageRef>,
+    /// Metadata of the open journal, decrypted once and reused until a write
+    /// or a lock drops it.
+    pub store: DecryptedStore,
+    pub journal: JournalSettings,
+    pub viewer: Option<Viewer>,
+    pub view_options: ViewOptions,
+    pub editor: Option<Editor>,
+    /// When the inline editor's draft is next written, if it has changed
+    /// since the last one.
+    draft_due: Option<Instant>,
+    /// Edits since the last draft.
+    draft_edits: usize,
+    /// Whether this editing session has written a draft, which a save or a
+    /// discard then removes.
+    pub draft_written: bool,
+    /// Recoveries listed on `Recoveries`, in list order.
+    pub recovery_files: Vec<RecoveryFile>,
+    /// The draft offered on `RecoverDraft`, once read.
+    pub recovery: Option<Recovery>,
+    /// Word list for the viewer's spellcheck, loaded the first time it is
+    /// switched on.
+    pub dictionary: Option<HashSet<String>>,
+    /// Prompts the writing prompt of the day is picked from.
+    pub writing_prompts: Vec<String>,
+    /// Palette colours handed to the journals in the data directory, by
+    /// file name in alphabetical order, when the user list is loaded.
+    pub journal_colors: HashMap<String, Color>,
+    pub config: Config,
+    pub user_sort: UserSort,
+    /// Journals found damaged this session, badged on the user list.
+    pub damaged: HashSet<String>,
+    pub data_dir: PathBuf,
+    pub config_dir: PathBuf,
+    /// Name of the profile whose `data_dir` is in use, if profiles are
+    /// configured.
+    pub profile: Option<String>,
+    /// Set when the screen may be out of date; nothing is drawn otherwise.
+    needs_redraw: bool,
+    /// Auto-lock countdown shown by the last draw.
+    shown_countdown: Option<u64>,
+    /// Transient footer message and when it expires, cleared early by the
+    /// next key.
+    status: Option<(String, Instant)>,
+    /// Whether the last draw showed the status message.
+    shown_status: bool,
+    /// When the last key was pressed, for auto-lock.
+    last_input: Instant,
+    /// Where the last render drew the list rows and the index of the first
+    /// visible one, so clicks can be mapped to items.
+    pub list_area: Cell<Option<(Rect, usize)>>,
+    last_click: Option<(Instant, usize)>,
+    pending_keys: PendingKeys,
+}
+
+impl App {
+    pub fn new() -> Result<Self, AppError> {
+        let project_dirs = ProjectDirs::from("com", "levi", "clog")
+            .ok_or_else(|| AppError::Config("Failed to get project directories".to_string()))?;
+        let data_dir = project_dirs.data_dir().to_path_buf();
+        fs::create_dir_all(&data_dir)?;
+
+        let config_dir = project_dirs.config_dir().to_path_buf();
+        let mut app = Self::with_dirs(data_dir, config_dir);
+        match Config::load(&app.config_dir) {
+            Ok(mut config) => {
+                if let Err(e) = config.keys.check() {
+                    app.show_message("Error", &format!("{}\n\nUsing the default keys.", e));
+                    config.keys = KeyMap::default();
+                }
+                if let Err(e) = config.check_profiles() {
+                    app.show_message("Error", &format!("{}\n\nProfiles are ignored.", e));
+                    config.profiles.clear();
+                }
+                if let Err(e) = config.check_startup() {
+                    app.show_message("Error", &format!("{}\n\nStarting on the user list.", e));
+                    config.startup = Startup::UserList;
+                }
+                app.user_sort = config.user_sort;
+                app.config = config;
+            }
+            Err(e) => app.show_message("Error", &format!("{}\n\nUsing default settings.", e)),
+        }
+        if app.config.writing_prompt != WritingPrompt::Off {
+            app.writing_prompts = prompts::load(app.config.writing_prompts.as_deref())
+                .unwrap_or_else(|e| {
+                    app.show_message(
+                        "Error",
+                        &format!("{}\n\nUsing the bundled writing prompts.", e),
+                    );
+                    prompts::load(None).unwrap_or_default()
+                });
+        }
+        if app.config.profiles.len() == 1 {
+            app.use_profile(0)?;
+        }
+        Ok(app)
+    }
+
+    /// Today's writing prompt, unless they are turned off.
+    pub fn writing_prompt(&self) -> Option<&str> {
+        prompts::for_day(&self.writing_prompts, Local::now().date_naive())
+    }
+
+    /// Whether there are profiles to choose between, so the app starts on
+    /// their list.
+    pub fn has_profiles(&self) -> bool {
+        self.config.profiles.len() > 1
+    }
+
+    /// Switches to the data directory of the profile at `index`, creating
+    /// it if needed.
+    pub fn use_profile(&mut self, index: usize) -> Result<(), AppError> {
+        let profile = self
+            .config
+            .profiles
+            .get(index)
+            .ok_or_else(|| AppError::Config(format!("no profile number {}", index + 1)))?;
+        let data_dir = export::resolve_destination(&profile.data_dir);
+        fs::create_dir_all(&data_dir)?;
+        self.data_dir = data_dir;
+        self.profile = Some(profile.name.clone());
+        self.damaged.clear();
+        Ok(())
+    }
+
+    /// Where the plain-text lists kept beside the config (titles, recent
+    /// journals, last pages) are stored: the config directory, or a
+    /// `profiles/<name>` directory in it, so journals of the same name in
+    /// two profiles don't share them.
+    pub fn state_dir(&self) -> PathBuf {
+        match &self.profile {
+            Some(name) => self.config_dir.join("profiles").join(name),
+            None => self.config_dir.clone(),
+        }
+    }
+
+    pub fn with_dirs(data_dir: PathBuf, config_dir: PathBuf) -> Self {
+        Self {
+            state: AppState::SelectUser,
+            selected_index: 0,
+            input_buffer: String::new(),
+            input_cursor: 0,
+            input_default: None,
+            items: Vec::new(),
+            message: None,
+            show_help: false,
+            reveal_password: false,
+            replace_match_case: true,
+            readonly: false,
+            readonly_journal: false,
+            resume_last_page: false,
+            startup: Startup::UserList,
+            undo: None,
+            marked: BTreeSet::new(),
+            search_hits: Vec::new(),
+            previews: HashMap::new(),
+            all_pages: Vec::new(),
+            store: DecryptedStore::default(),
+            journal_stats: None,
+            remembered_selection: HashMap::new(),
+            journal: JournalSettings::default(),
+            viewer: None,
+            view_options: ViewOptions::default(),
+            editor: None,
+            draft_due: None,
+            draft_edits: 0,
+            draft_written: false,
+            recovery_files: Vec::new(),
+            recovery: None,
+            dictionary: None,
+            writing_prompts: Vec::new(),
+            journal_colors: HashMap::new(),
+            config: Config::default(),
+            user_sort: UserSort::default(),
+            damaged: HashSet::new(),
+            data_dir,
+            config_dir,
+            profile: None,
+            needs_redraw: true,
+            shown_countdown: None,
+            status: None,
+            shown_status: false,
+            last_input: Instant::now(),
+            list_area: Cell::new(None),
+            last_click: None,
+            pending_keys: PendingKeys::default(),
+        }
+    }
+
+    /// The keys that do something on the current screen, as `(keys, action)`
+    /// pairs. Only actions that are actually available right now are listed.
+    pub fn key_hints(&self) -> Vec<KeyHint> {
+        let mut hints = Vec::new();
+        let has_items = !self.items.is_empty();
+        let keys = &self.config.keys;
+        let navigate = || -> Cow<'static, str> {
+            format!("↑/↓ or {}/{}", key_label(keys.down), key_label(keys.up)).into()
+        };
+        let select = || -> Cow<'static, str> {
+            match keys.select {
+                KeyCode::Enter => "Enter".into(),
+                other => format!("Enter/{}", key_label(other)).into(),
+            }
+        };
+        let back = || -> Cow<'static, str> { format!("{}/Esc", key_label(keys.back)).into() };
+        match &self.state {
+            AppState::SelectProfile => {
+                hints.push((navigate(), "Navigate"));
+                hints.push((select(), "Open"));
+            }
+            AppState::SelectUser => {
+                hints.push((navigate(), "Navigate"));
+                hints.push((select(), "Select"));
+                hints.push(("s".into(), self.user_sort.label()));
+                hints.push(("R".into(), "Recoveries"));
+                if self.has_profiles() {
+                    hints.push((back(), "Profiles"));
+                }
+            }
+            AppState::Recoveries => {
+                if has_items {
+                    hints.push((navigate(), "Navigate"));
+                    hints.push((select(), "Open"));
+                    hints.push((key_label(keys.delete).into(), "Delete"));
+                }
+                hints.push((back(), "Back"));
+            }
+            AppState::ViewRecovery(_) => {
+                hints.push(("↑/↓ PgUp/PgDn".into(), "Scroll"));
+                hints.push(("y".into(), "Copy"));
+                hints.push((back(), "Back"));
+            }
+            AppState::SelectFolder(_, _) => {
+                if self.readonly {
+                    hints.push(("".into(), "Read-only"));
+                }
+                hints.push((navigate(), "Navigate"));
+                hints.push((select(), "Open"));
+                let has_items = self.has_chapters();
+                if has_items {
+                    hints.push((key_label(keys.search).into(), "Search all"));
+                    hints.push(("c".into(), "Calendar"));
+                    hints.push(("T".into(), "Timeline"));
+                    hints.push(("s".into(), "Stats"));
+                }
+                if !self.journal.trash.is_empty() {
+                    hints.push(("x".into(), "Trash"));
+                }
+                if !self.readonly && self.selected_folder().is_some() {
+                    hints.push(("o".into(), "Colour"));
+                }
+                if !self.readonly {
+                    hints.push(("i".into(), "Journal info"));
+                }
+                if has_items {
+                    hints.push(("e".into(), "Export"));
+                }
+                if !self.readonly {
+                    hints.push(("I".into(), "Import"));
+                }
+                if self.undo.is_some() {
+                    hints.push(("u".into(), "Undo"));
+                }
+                hints.push(("t".into(), "Tags"));
+                hints.push(("^L".into(), "Lock"));
+                hints.push((back(), "Back"));
+            }
+            AppState::SelectFile(_, _, folder) => {
+                if self.readonly {
+                    hints.push(("".into(), "Read-only"));
+                }
+                if has_items {
+                    hints.push((navigate(), "Navigate"));
+                    let locked = self
+                        .selected_page()
+                        .is_some_and(|file| self.journal.is_locked(folder, &file));
+                    let editable = self.is_editable(folder) && !locked;
+                    hints.push((select(), if editable { "Edit" } else { "View" }));
+                    hints.push(("Space".into(), "Mark"));
+                    if !self.readonly {
+                        hints.push(("t".into(), "Tags"));
+                    }
+                    if self.is_renamable(folder) && !locked {
+                        hints.push(("r".into(), "Rename"));
+                    }
+                    if !self.readonly {
+                        hints.push(("c".into(), "Duplicate"));
+                        hints.push(("p".into(), if locked { "Unlock" } else { "Lock" }));
+                        hints.push(("J/K".into(), "Move"));
+                    }
+                    hints.push(("e".into(), "Share"));
+                    hints.push(("y/E".into(), "Copy/Export chapter"));
+                    hints.push(("a".into(), "Attachments"));
+                    if editable {
+                        hints.push((
+                            key_label(keys.delete).into(),
+                            if self.marked.is_empty() {
+                                "Delete"
+                            } else {
+                                "Delete marked"
+                            },
+                        ));
+                    }
+                    hints.push((key_label(keys.search).into(), "Search"));
+                }
+                if !self.readonly {
+                    hints.push(("I".into(), "Import shared"));
+                }
+                if self.undo.is_some() {
+                    hints.push(("u".into(), "Undo"));
+                }
+                hints.push(("^L".into(), "Lock"));
+                hints.push((back(), "Back"));
+            }
+            AppState::Calendar(_, _, _) => {
+                hints.push((
+                    format!(
+                        "←/→/↑/↓ or h/l/{}/{}",
+                        key_label(keys.up),
+                        key_label(keys.down)
+                    )
+                    .into(),
+                    "Move",
+                ));
+                hints.push(("PgUp/PgDn".into(), "Month"));
+                hints.push((select(), "Open"));
+                hints.push((format!("c/{}/Esc", key_label(keys.back)).into(), "List"));
+            }
+            AppState::ChapterResults(_, _, _, _)
+            | AppState::SearchResults(_, _, _)
+            | AppState::SelectTag(_, _)
+            | AppState::TagResults(_, _, _)
+            | AppState::Timeline(_, _) => {
+                hints.push((navigate(), "Navigate"));
+                hints.push((select(), "Open"));
+                hints.push(("^L".into(), "Lock"));
+                hints.push((back(), "Back"));
+            }
+            AppState::Stats(_, _) => {
+                hints.push(("^L".into(), "Lock"));
+                hints.push((back(), "Back"));
+            }
+            AppState::Trash(_, _) => {
+                if self.readonly {
+                    hints.push(("".into(), "Read-only"));
+                }
+                if !self.items.is_empty() {
+                    hints.push((navigate(), "Navigate"));
+                    if !self.readonly {
+                        hints.push((select(), "Restore"));
+                        hints.push((key_label(keys.delete).into(), "Purge"));
+                    }
+                }
+                hints.push(("^L".into(), "Lock"));
+                hints.push((back(), "Back"));
+            }
+            AppState::Attachments(_, _, _, _) => {
+                if self.readonly {
+                    hints.push(("".into(), "Read-only"));
+                }
+                if !self.items.is_empty() {
+                    hints.push((navigate(), "Navigate"));
+                    hints.push((select(), "Extract / Add"));
+                    if !self.readonly {
+                        hints.push((key_label(keys.delete).into(), "Remove"));
+                    }
+                }
+                hints.push(("^L".into(), "Lock"));
+                hints.push((back(), "Back"));
+            }
+            AppState::JournalInUse(_, _, _) => {
+                hints.push(("r".into(), "Open read-only"));
+                hints.push(("f".into(), "Force-unlock"));
+                hints.push((back(), "Back"));
+                return hints;
+            }
+            AppState::RecoverDraft(_, _) => {
+                hints.push(("Enter".into(), "Recover"));
+                let comparing = self.recovery.as_ref().is_some_and(|r| r.compare);
+                hints.push(("c".into(), if comparing { "Draft" } else { "Compare" }));
+                hints.push(("y".into(), "Copy"));
+                hints.push(("d".into(), "Discard"));
+                hints.push((back(), "Later"));
+            }
+            AppState::QuickOpen(_, _) => {
+                hints.push(("↑/↓ or ^P/^N".into(), "Navigate"));
+                hints.push(("Enter".into(), "Open"));
+                hints.push(("Esc".into(), "Back"));
+                return hints;
+            }
+            AppState::EditOrViewFile(_, _, _, _) => {
+                hints.push(("".into(), "Page will open in editor"));
+            }
+            AppState::ViewPage(_, _, _, _) if self.viewer.as_ref().is_some_and(|v| v.searching) => {
+                hints.push(("Enter".into(), "Find"));
+                hints.push(("Esc".into(), "Cancel"));
+                return hints;
+            }
+            AppState::ViewPage(_, _, _, _) => {
+                hints.push(("↑/↓ PgUp/PgDn".into(), "Scroll"));
+                let searched = self.viewer.as_ref().is_some_and(|v| !v.query.is_empty());
+                if searched {
+                    hints.push(("n/N".into(), "Next/previous match"));
+                    hints.push(("Esc".into(), "Clear search"));
+                } else {
+                    hints.push((key_label(keys.search).into(), "Search"));
+                }
+                let markdown = self.view_options.markdown;
+                hints.push(("m".into(), if markdown { "Raw" } else { "Markdown" }));
+                let spellcheck_on = self.viewer.as_ref().is_some_and(|v| v.spellcheck);
+                if !markdown {
+                    hints.push((
+                        "s".into(),
+                        if spellcheck_on {
+                            "Spellcheck off"
+                        } else {
+                            "Spellcheck"
+                        },
+                    ));
+                }
+                hints.push((
+                    "w".into(),
+                    if self.view_options.wrap {
+                        "No wrap"
+                    } else {
+                        "Wrap"
+                    },
+                ));
+                if !self.view_options.wrap {
+                    hints.push(("←/→ or h/l".into(), "Side-scroll"));
+                }
+                if !markdown && !searched {
+                    hints.push((
+                        "n".into(),
+                        if self.view_options.line_numbers {
+                            "Hide numbers"
+                        } else {
+                            "Line numbers"
+                        },
+                    ));
+                }
+                hints.push(("y".into(), "Copy"));
+                hints.push(("^L".into(), "Lock"));
+                hints.push((back(), "Back"));
+            }
+            AppState::InlineEdit(_) => {
+                match self.editor.as_ref().filter(|editor| editor.is_modal()) {
+                    Some(editor) if editor.mode == Mode::Normal => {
+                        hints.push(("i/a/o".into(), "Insert"));
+                        hints.push(("h/j/k/l".into(), "Move"));
+                        hints.push(("dd".into(), "Delete line"));
+                        hints.push(("ZZ/^S".into(), "Save"));
+                        hints.push(("ZQ".into(), "Discard"));
+                    }
+                    Some(_) => {
+                        hints.push(("Esc".into(), "Normal mode"));
+                        hints.push(("^S".into(), "Save"));
+                    }
+                    None => {
+                        hints.push(("^S".into(), "Save"));
+                        hints.push(("Esc".into(), "Discard"));
+                    }
+                }
+                hints.push(("^R".into(), "Replace"));
+                hints.push(("^T".into(), "Insert time"));
+                hints.push(("^Y".into(), "Copy"));
+                hints.push(("^L".into(), "Lock"));
+                hints.push(("^Q".into(), "Quit"));
+                return hints;
+            }
+            AppState::Confirm(_, _, _) => {
+                hints.push(("y/Enter".into(), "Yes"));
+                hints.push(("n/Esc".into(), "No"));
+                return hints;
+            }
+            AppState::Locked(_, _) => {
+                hints.push(("Enter".into(), "Unlock"));
+                hints.push(("Esc".into(), "Switch user"));
+                hints.push(("^R".into(), self.reveal_hint()));
+                return hints;
+            }
+            AppState::EnterNewUser | AppState::EnterPassword(_) => {
+                hints.push(("Enter".into(), "Confirm"));
+                hints.push(("Esc".into(), "Back"));
+            }
+            AppState::InputPrompt(_, _)
+            | AppState::AddPagePrompt(_, _, _)
+            | AppState::NewChapter(_, _, _)
+            | AppState::EditJournalTitle(_, _)
+            | AppState::EditJournalDescription(_, _, _)
+            | AppState::SearchChapter(_, _, _)
+            | AppState::SearchPrompt(_, _)
+            | AppState::RenamePage(_, _, _, _)
+            | AppState::DuplicatePage(_, _, _, _)
+            | AppState::ExportPage(_, _, _, _, _)
+            | AppState::ExportChapter(_, _, _)
+            | AppState::ImportPage(_, _, _, _)
+            | AppState::ReplaceText(_, _)
+            | AppState::NewPageTags(_, _, _, _)
+            | AppState::EditTags(_, _, _, _)
+            | AppState::AttachFile(_, _, _, _)
+            | AppState::ExtractAttachment(_, _, _, _, _)
+            | AppState::OpenRecovery(_)
+            | AppState::Export(_, _)
+            | AppState::Import(_, _) => {
+                hints.push(("←/→ Home/End".into(), "Move cursor"));
+                hints.push(("Enter".into(), "Confirm"));
+                hints.push(("Esc".into(), "Cancel"));
+                if self.state.is_password_prompt() {
+                    hints.push(("^R".into(), self.reveal_hint()));
+                }
+                if matches!(&self.state, AppState::InputPrompt(_, next)
+                    if matches!(**next, AppState::ReplaceText(_, _)))
+                {
+                    hints.push(("Tab".into(), "Toggle case"));
+                }
+                return hints;
+            }
+        }
+        hints.push(("?".into(), "Help"));
+        hints.push((key_label(keys.quit).into(), "Quit"));
+        hints
+    }
+
+    fn reveal_hint(&self) -> &'static str {
+        if self.reveal_password {
+            "Hide"
+        } else {
+            "Reveal"
+        }
+    }
+
+    /// What the help overlay lists: the keys of the current screen, the
+    /// same ones the footer shows, then those that work everywhere.
+    pub fn help_sections(&self) -> Vec<(&'static str, Vec<KeyHint>)> {
+        let fixed = |keys: &[(&'static str, &'static str)]| {
+            keys.iter()
+                .map(|(keys, action)| (Cow::Borrowed(*keys), *action))
+                .collect()
+        };
+        let mut sections = vec![("This screen", self.key_hints())];
+        if self.list_area.get().is_some() {
+            sections.push(("Lists", fixed(LIST_KEYS)));
+        }
+        if self.state.user_path().is_some() {
+            sections.push(("Everywhere", fixed(GLOBAL_KEYS)));
+        }
+        sections
+    }
+
+    /// Where the screen is, from the profile and journal down to the page,
+    /// followed by the screen itself when it isn't a chapter or a page.
+    /// Questions and prompts show the place they were asked from; the lock
+    /// screen only the journal, since nothing of it is on screen.
+    pub fn breadcrumb(&self) -> Vec<String> {
+        let mut crumbs: Vec<String> = self.profile.iter().cloned().collect();
+        let mut state = &self.state;
+        while let AppState::InputPrompt(_, next) | AppState::Confirm(_, _, next) = state {
+            state = next;
+        }
+        if let Some(user_path) = state.user_path() {
+            crumbs.push(user_path.trim_end_matches(".clog").to_string());
+        }
+        if let AppState::EnterPassword(user_path) = state {
+            crumbs.push(user_path.trim_end_matches(".clog").to_string());
+        }
+        let (folder, file, screen) = match state {
+            AppState::Locked(_, _) => return crumbs,
+            AppState::SelectFile(_, _, folder)
+            | AppState::AddPagePrompt(_, _, folder)
+            | AppState::SearchChapter(_, _, folder)
+            | AppState::ImportPage(_, _, folder, _)
+            | AppState::ExportChapter(_, _, folder)
+            | AppState::NewChapter(_, _, Some(folder)) => (Some(folder), None, None),
+            AppState::EditOrViewFile(_, _, folder, file)
+            | AppState::ViewPage(_, _, folder, file)
+            | AppState::RenamePage(_, _, folder, file)
+            | AppState::DuplicatePage(_, _, folder, file)
+            | AppState::ExportPage(_, _, folder, file, _)
+            | AppState::NewPageTags(_, _, folder, file)
+            | AppState::EditTags(_, _, folder, file) => (Some(folder), Some(file), None),
+            AppState::Attachments(_, _, folder, file)
+            | AppState::AttachFile(_, _, folder, file)
+            | AppState::ExtractAttachment(_, _, folder, file, _) => {
+                (Some(folder), Some(file), Some("Attachments".to_string()))
+            }
+            AppState::InlineEdit(write) | AppState::ReplaceText(write, _) => {
+                (Some(&write.folder), Some(&write.file), None)
+            }
+            AppState::ChapterResults(_, _, folder, query) => {
+                (Some(folder), None, Some(format!("'{}'", query)))
+            }
+            AppState::SearchResults(_, _, query) => (None, None, Some(format!("'{}'", query))),
+            AppState::TagResults(_, _, tag) => (None, None, Some(format!("#{}", tag))),
+            AppState::SelectTag(_, _) => (None, None, Some("Tags".to_string())),
+            AppState::Timeline(_, _) => (None, None, Some("Timeline".to_string())),
+            AppState::Stats(_, _) => (None, None, Some("Stats".to_string())),
+            AppState::Trash(_, _) => (None, None, Some("Trash".to_string())),
+            AppState::Calendar(_, _, _) => (None, None, Some("Calendar".to_string())),
+            AppState::QuickOpen(_, _) => (None, None, Some("Quick open".to_string())),
+            AppState::RecoverDraft(_, _) => (None, None, Some("Draft".to_string())),
+            AppState::JournalInUse(_, _, _) => (None, None, Some("In use".to_string())),
+            AppState::Recoveries | AppState::OpenRecovery(_) | AppState::ViewRecovery(_) => {
+                (None, None, Some("Recoveries".to_string()))
+            }
+            AppState::EditJournalTitle(_, _) | AppState::EditJournalDescription(_, _, _) => {
+                (None, None, Some("Journal info".to_string()))
+            }
+            AppState::Export(_, _) => (None, None, Some("Export".to_string())),
+            AppState::Import(_, _) => (None, None, Some("Import".to_string())),
+            _ => (None, None, None),
+        };
+        if let Some(folder) = folder {
+            crumbs.extend(
+                self.journal
+                    .chapter_path(folder)
+                    .into_iter()
+                    .map(folder_label),
+            );
+        }
+        crumbs.extend(file.cloned());
+        crumbs.extend(screen);
+        crumbs
+    }
+
+    pub fn get_help_text(&self) -> String {
+        let mut help = self
+            .key_hints()
+            .iter()
+            .map(|(keys, action)| {
+                if keys.is_empty() {
+                    action.to_string()
+                } else {
+                    format!("{}: {}", keys, action)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" | ");
+        if let Some(status) = self.status_text() {
+            help = format!("{} | {}", status, help);
+        }
+        if let Some(seconds) = self.auto_lock_countdown() {
+            help = format!("Locking in {}s | {}", seconds, help);
+        }
+        help
+    }
+
+    /// Whether pages of `folder` open in the editor rather than the viewer.
+    /// Past days are read-only unless `allow_backdating` is set; topic
+    /// chapters are always editable.
+    pub fn is_editable(&self, folder: &str) -> bool {
+        !self.readonly && (self.is_renamable(folder) || self.config.allow_backdating)
+    }
+
+    /// Whether pages of `folder` can be renamed, which copies them into
+    /// today's folder: only pages already added today or filed under a
+    /// topic can move there.
+    fn is_renamable(&self, folder: &str) -> bool {
+        !self.readonly && (folder == today_str() || self.journal.is_chapter(folder))
+    }
+
+    /// The day new pages and chapters go to, or empty in a read-only session
+    /// so the lists leave out "Add Page" and "New Chapter".
+    pub fn writable_day(&self) -> String {
+        if self.readonly {
+            String::new()
+        } else {
+            today_str()
+        }
+    }
+
+    /// Whether a page opens in the editor: its chapter is editable and the
+    /// page isn't locked.
+    pub fn is_page_editable(&self, folder: &str, file: &str) -> bool {
+        self.is_editable(folder) && !self.journal.is_locked(folder, file)
+    }
+
+    fn locked_reason(file: &str) -> String {
+        format!("'{}' is locked. Press p to unlock it first.", file)
+    }
+
+    /// Why pages here can't be `action`: the journal is read-only, or they
+    /// belong to a past day.
+    fn not_editable_reason(&self, action: &str) -> String {
+        if self.readonly {
+            "The journal is open read-only".to_string()
+        } else {
+            format!("Pages of past days can't be {}", action)
+        }
+    }
+
+    /// The highlighted row of the page list if it is a page, rather than a
+    /// sub-chapter or an item that adds one.
+    fn selected_page(&self) -> Option<String> {
+        let (file, badge) = self.items.get(self.selected_index)?;
+        is_page_item(file, badge).then(|| file.clone())
+    }
+
+    /// Moves the highlighted page one place up or down its chapter, saving
+    /// the order of every page there.
+    fn move_page(
+        &self,
+        user_path: String,
+        password: String,
+        folder: String,
+        up: bool,
+    ) -> Option<SideEffect> {
+        let file = self.selected_page()?;
+        let mut pages: Vec<String> = self
+            .items
+            .iter()
+            .filter(|(file, badge)| is_page_item(file, badge))
+            .map(|(file, _)| file.clone())
+            .collect();
+        let at = pages.iter().position(|page| *page == file)?;
+        let to = if up { at.checked_sub(1)? } else { at + 1 };
+        if to == pages.len() {
+            return None;
+        }
+        pages.swap(at, to);
+        let selected = if up {
+            self.selected_index - 1
+        } else {
+            self.selected_index + 1
+        };
+        Some(SideEffect::ReorderPages(
+            user_path, password, folder, pages, selected,
+        ))
+    }
+
+    /// The chapter highlighted on the chapter list, if it is one.
+    fn selected_folder(&self) -> Option<String> {
+        self.items
+            .get(self.selected_index)
+            .map(|(folder, _)| folder.clone())
+            .filter(|folder| folder != NEW_CHAPTER_ITEM)
+    }
+
+    /// The colour of a journal's screens: its own from `user_colors`, or
+    /// the one it was handed from the palette.
+    pub fn journal_color(&self, user_path: &str) -> Color {
+        self.config
+            .user_colors
+            .get(user_path.trim_end_matches(".clog"))
+            .or_else(|| self.journal_colors.get(user_path))
+            .copied()
+            .unwrap_or(self.config.theme.title)
+    }
+
+    /// The theme with the titles in the colour of the journal on screen.
+    pub fn screen_theme(&self) -> Theme {
+        let mut state = &self.state;
+        while let AppState::InputPrompt(_, next) | AppState::Confirm(_, _, next) = state {
+            state = next;
+        }
+        let user_path = match state {
+            AppState::EnterPassword(user_path) => Some(user_path.as_str()),
+            state => state.user_path(),
+        };
+        Theme {
+            title: user_path.map_or(self.config.theme.title, |user_path| {
+                self.journal_color(user_path)
+            }),
+            ..self.config.theme
+        }
+    }
+
+    /// Whether the chapter list shows any chapter besides the item that
+    /// creates one.
+    fn has_chapters(&self) -> bool {
+        self.items
+            .iter()
+            .any(|(folder, _)| folder != NEW_CHAPTER_ITEM)
+    }
+
+    pub fn touch(&mut self) {
+        self.last_input = Instant::now();
+    }
+
+    /// Time until the open journal is locked for inactivity, if one is open
+    /// and auto-lock is on.
+    fn auto_lock_remaining(&self) -> Option<Duration> {
+        let timeout = self.config.auto_lock_after()?;
+        if matches!(self.state, AppState::Locked(_, _)) || self.state.user_path().is_none() {
+            return None;
+        }
+        Some(timeout.saturating_sub(self.last_input.elapsed()))
+    }
+
+    /// Whole seconds left before auto-lock, once the footer counts down.
+    fn auto_lock_countdown(&self) -> Option<u64> {
+        self.auto_lock_remaining()
+            .filter(|left| *left < AUTO_LOCK_WARNING)
+            .map(|left| left.as_secs() + 1)
+    }
+
+    /// Locks the journal once no key has been pressed for the configured
+    /// auto-lock time.
+    pub fn check_auto_lock(&mut self) {
+        if self.auto_lock_remaining() == Some(Duration::ZERO) {
+            self.touch();
+            self.lock();
+            self.request_redraw();
+        }
+    }
+
+    pub fn request_redraw(&mut self) {
+        self.needs_redraw = true;
+    }
+
+    /// Whether the screen has to be drawn again: something changed, the
+    /// auto-lock countdown ticked or the status message expired.
+    pub fn should_render(&mut self) -> bool {
+        let countdown = self.auto_lock_countdown();
+        let status = self.status_text().is_some();
+        let changed = std::mem::take(&mut self.needs_redraw)
+            || countdown != self.shown_countdown
+            || status != self.shown_status;
+        self.shown_countdown = countdown;
+        self.shown_status = status;
+        changed
+    }
+
+    /// How long the loop can wait for input before the screen changes by
+    /// itself, which the auto-lock countdown and an expiring status message
+    /// do.
+    pub fn idle_timeout(&self) -> Duration {
+        let lock = match self.auto_lock_remaining() {
+            Some(left) if left >= AUTO_LOCK_WARNING => left - AUTO_LOCK_WARNING,
+            Some(left) => Duration::from_nanos(left.subsec_nanos().into()),
+            None => IDLE_POLL,
+        };
+        let status = self.status.as_ref().map_or(IDLE_POLL, |(_, expires)| {
+            expires.saturating_duration_since(Instant::now())
+        });
+        let draft = self.draft_due.map_or(IDLE_POLL, |due| {
+            due.saturating_duration_since(Instant::now())
+        });
+        lock.min(status).min(draft).max(MIN_POLL)
+    }
+
+    /// Shows `text` in the footer for a few seconds, or until the next key.
+    /// Destructive actions use it to spell out what they are about to do
+    /// while their confirmation is open.
+    pub fn announce(&mut self, text: String) {
+        self.status = Some((text, Instant::now() + STATUS_TTL));
+    }
+
+    /// Opens the inline editor on `write`'s page, with no draft written yet.
+    pub fn open_editor(&mut self, editor: Editor, write: PageWrite) {
+        self.editor = Some(match self.config.editor_mode {
+            EditorMode::Insert => editor,
+            EditorMode::Modal => editor.modal(),
+        });
+        self.draft_due = None;
+        self.draft_edits = 0;
+        self.draft_written = false;
+        self.state = AppState::InlineEdit(write);
+    }
+
+    /// Counts an edit in the inline editor towards the next draft.
+    fn note_edit(&mut self) {
+        self.draft_edits += 1;
+        let now = Instant::now();
+        let due = self.draft_due.get_or_insert(now + DRAFT_INTERVAL);
+        if self.draft_edits >= DRAFT_EDITS {
+            *due = now;
+        }
+    }
+
+    /// The draft to write now, if the inline editor is open and its draft is
+    /// due. Waits while a prompt is over the editor.
+    pub fn due_draft(&mut self) -> Option<SideEffect> {
+        let AppState::InlineEdit(write) = &self.state else {
+            return None;
+        };
+        if self.draft_due.is_none_or(|due| Instant::now() < due) {
+            return None;
+        }
+        self.draft_due = None;
+        self.draft_edits = 0;
+        let editor = self.editor.as_ref()?;
+        // Nothing worth keeping until the page differs from what's saved.
+        if !self.draft_written && !editor.is_modified() {
+            return None;
+        }
+        Some(SideEffect::SaveDraft(PageWrite {
+            content: editor.content(),
+            ..write.clone()
+        }))
+    }
+
+    /// The highlighted page of the page list, if the preview pane hasn't
+    /// read it yet. Only the pages looked at are decrypted, not the whole
+    /// chapter.
+    pub fn due_preview(&self) -> Option<SideEffect> {
+        let AppState::SelectFile(user_path, password, folder) = &self.state else {
+            return None;
+        };
+        let file = self.selected_page()?;
+        if self.previews.contains_key(&file) {
+            return None;
+        }
+        Some(SideEffect::LoadPreview(
+            user_path.clone(),
+            password.clone(),
+            folder.clone(),
+            file,
+        ))
+    }
+
+    /// Reopens the offered draft in the inline editor, over the page it was
+    /// made from. A page deleted since comes back as a new page.
+    fn recover_draft(&mut self, user_path: String, password: String) -> Option<SideEffect> {
+        let recovery = self.recovery.as_ref()?;
+        let draft = &recovery.draft;
+        let new_page = draft.new_page || recovery.saved.is_none();
+        let blocked = if self.readonly {
+            Some(self.not_editable_reason("edited"))
+        } else if new_page {
+            None
+        } else if self.journal.is_locked(&draft.folder, &draft.file) {
+            Some(Self::locked_reason(&draft.file))
+        } else if !self.is_editable(&draft.folder) {
+            Some(self.not_editable_reason("edited"))
+        } else {
+            None
+        };
+        if let Some(reason) = blocked {
+            self.show_message(
+                "Error",
+                &format!("{}\n\nCopy the draft with y, or discard it with d.", reason),
+            );
+            return None;
+        }
+        let Recovery { draft, saved, .. } = self.recovery.take()?;
+        // New pages are added to today, or to the topic they were drafted in.
+        let folder = if new_page && !self.journal.is_chapter(&draft.folder) {
+            today_str()
+        } else {
+            draft.folder
+        };
+        let editor = Editor::recovered(&draft.content, saved.as_deref().unwrap_or(""));
+        let write = PageWrite {
+            user_path,
+            password,
+            folder,
+            file: draft.file,
+            content: String::new(),
+            new_page,
+            tags: draft.tags,
+        };
+        self.open_editor(editor, write);
+        self.draft_written = true;
+        None
+    }
+
+    fn status_text(&self) -> Option<&str> {
+        self.status
+            .as_ref()
+            .filter(|(_, expires)| Instant::now() < *expires)
+            .map(|(text, _)| text.as_str())
+    }
+
+    pub fn clear_input(&mut self) {
+        self.input_buffer.clear();
+        self.input_cursor = 0;
+    }
+
+    pub fn set_input(&mut self, value: &str) {
+        self.input_buffer = value.to_string();
+        self.input_cursor = value.chars().count();
+    }
+
+    /// `handle_prompt_input` on the input line, where Enter on an empty
+    /// line submits the prompt's default, or says input is needed when the
+    /// prompt has none and can't be left empty.
+    fn prompt_input(&mut self, key: KeyEvent, allow_empty: bool) -> Option<PromptAction> {
+        if key.code == KeyCode::Enter && self.input_buffer.is_empty() {
+            if let Some(default) = &self.input_default {
+                return Some(PromptAction::Submit(default.clone()));
+            }
+            if !allow_empty {
+                self.announce("Input required".to_string());
+                return None;
+            }
+        }
+        handle_prompt_input(
+            key,
+            &mut self.input_buffer,
+            &mut self.input_cursor,
+            allow_empty,
+        )
+    }
+
+    /// The login name, offered for a new journal if it makes a valid
+    /// username that isn't taken yet.
+    fn default_username(&self) -> Option<String> {
+        let name = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .ok()?;
+        (validate_username(&name).is_ok() && !self.data_dir.join(format!("{}.clog", name)).exists())
+            .then_some(name)
+    }
+
+    /// `Page N` for the first N not listed in the chapter yet.
+    fn default_page_name(&self) -> String {
+        (1..)
+            .map(|n| format!("Page {}", n))
+            .find(|name| !self.items.iter().any(|(item, _)| item == name))
+            .unwrap_or_default()
+    }
+
+    pub fn show_message(&mut self, title: &str, message: &str) {
+        self.message = Some((title.to_string(), message.to_string()));
+    }
+
+    /// Hides everything read from the open journal behind a lock screen and
+    /// wipes the password. Returns false when no journal is open.
+    pub fn lock(&mut self) -> bool {
+        let mut resume = self.state.clone();
+        match resume.password_mut() {
+            Some(password) if !password.is_empty() => password.zeroize(),
+            _ => return false,
+        }
+        if let Some(password) = self.state.password_mut() {
+            password.zeroize();
+        }
+        // A pending yes/no keeps its effect, which carries the password too;
+        // the lock screen puts it back.
+        for state in [&mut resume, &mut self.state] {
+            if let AppState::Confirm(_, effect, _) = state
+                && let Some(password) = effect.password_mut()
+            {
+                password.zeroize();
+            }
+        }
+        if let Some(mut viewer) = self.viewer.take() {
+            viewer.content.zeroize();
+        }
+        self.clear_previews();
+        // Invalidation point: nothing decrypted is kept behind the lock.
+        self.store.invalidate();
+        self.undo = None;
+        self.input_buffer.zeroize();
+        self.input_cursor = 0;
+        self.message = None;
+        self.journal = JournalSettings::default();
+        let selected_index = self.selected_index;
+        self.enter(AppState::Locked(Box::new(resume), selected_index));
+        true
+    }
+
+    /// Quits, asking first when the inline editor or a prompt holds text
+    /// that would be lost.
+    fn quit(&mut self) -> Option<SideEffect> {
+        let unsaved = match &self.state {
+            AppState::InlineEdit(_) => self.editor.as_ref().is_some_and(Editor::is_modified),
+            AppState::InputPrompt(_, next) if matches!(**next, AppState::ReplaceText(_, _)) => {
+                self.editor.as_ref().is_some_and(Editor::is_modified)
+            }
+            AppState::EnterNewUser
+            | AppState::InputPrompt(_, _)
+            | AppState::AddPagePrompt(_, _, _)
+            | AppState::NewChapter(_, _, _)
+            | AppState::EditJournalTitle(_, _)
+            | AppState::EditJournalDescription(_, _, _)
+            | AppState::RenamePage(_, _, _, _)
+            | AppState::DuplicatePage(_, _, _, _) => !self.input_buffer.is_empty(),
+            _ => false,
+        };
+        if !unsaved {
+            return Some(SideEffect::Quit);
+        }
+        let back = self.state.clone();
+        self.state = AppState::Confirm(
+            "You have unsaved text.\n\nQuit anyway?".to_string(),
+            Box::new(SideEffect::Quit),
+            Box::new(back),
+        );
+        None
+    }
+
+    /// Applies a mouse event to the list on screen. Clicking a row selects
+    /// it and the wheel moves the selection; a double-click, or any click
+    /// while a message is open, comes back as the key that does the same.
+    fn mouse_key(&mut self, mouse: MouseEvent) -> Option<KeyEvent> {
+        if self.message.is_some() || self.show_help {
+            return matches!(mouse.kind, MouseEventKind::Down(_))
+                .then(|| KeyEvent::from(KeyCode::Esc));
+        }
+        let (area, offset) = self.list_area.get()?;
+        let last = self.items.len().checked_sub(1)?;
+        match mouse.kind {
+            MouseEventKind::ScrollUp => self.selected_index = self.selected_index.saturating_sub(1),
+            MouseEventKind::ScrollDown => self.selected_index = (self.selected_index + 1).min(last),
+            MouseEventKind::Down(MouseButton::Left)
+                if area.contains(Position::new(mouse.column, mouse.row)) =>
+            {
+                let row = offset + usize::from(mouse.row - area.y);
+                if row > last {
+                    return None;
+                }
+                self.selected_index = row;
+                let now = Instant::now();
+                if let Some((at, clicked)) = self.last_click.take()
+                    && clicked == row
+                    && now.duration_since(at) < DOUBLE_CLICK
+                {
+                    return Some(KeyEvent::from(KeyCode::Enter));
+                }
+                self.last_click = Some((now, row));
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Switches to `state`, restoring the selection it had last time, and
+    /// returns the effect that loads the list shown by that screen, if any.
+    pub fn enter(&mut self, state: AppState) -> Option<SideEffect> {
+        let effect = match &state {
+            AppState::SelectProfile => Some(SideEffect::LoadProfiles),
+            AppState::Recoveries => Some(SideEffect::LoadRecoveries),
+            AppState::SelectUser => {
+                // Undo belongs to the journal that was open.
+                self.undo = None;
+                Some(SideEffect::LoadUsers)
+            }
+            AppState::SelectFolder(user_path, password)
+            | AppState::Calendar(user_path, password, _) => {
+                Some(SideEffect::LoadFolders(user_path.clone(), password.clone()))
+            }
+            AppState::SelectFile(user_path, password, folder) => Some(SideEffect::LoadFiles(
+                user_path.clone(),
+                password.clone(),
+                folder.clone(),
+            )),
+            AppState::SelectTag(user_path, password) => {
+                Some(SideEffect::LoadTags(user_path.clone(), password.clone()))
+            }
+            AppState::Timeline(user_path, password) => Some(SideEffect::LoadTimeline(
+                user_path.clone(),
+                password.clone(),
+            )),
+            AppState::Stats(user_path, password) => {
+                Some(SideEffect::LoadStats(user_path.clone(), password.clone()))
+            }
+            AppState::Trash(user_path, password) => {
+                Some(SideEffect::LoadTrash(user_path.clone(), password.clone()))
+            }
+            AppState::Attachments(user_path, password, folder, file) => {
+                Some(SideEffect::LoadAttachments(
+                    user_path.clone(),
+                    password.clone(),
+                    folder.clone(),
+                    file.clone(),
+                ))
+            }
+            AppState::RecoverDraft(user_path, password) => {
+                Some(SideEffect::LoadDraft(user_path.clone(), password.clone()))
+            }
+            AppState::QuickOpen(user_path, password) => Some(SideEffect::LoadQuickOpen(
+                user_path.clone(),
+                password.clone(),
+            )),
+            AppState::TagResults(user_path, password, tag) => Some(SideEffect::LoadTagged(
+                user_path.clone(),
+                password.clone(),
+                tag.clone(),
+            )),
+            _ => None,
+        };
+        self.state = state;
+        self.reveal_password = false;
+        self.input_default = None;
+        self.items.clear();
+        self.marked.clear();
+        self.search_hits.clear();
+        self.all_pages.clear();
+        self.journal_stats = None;
+        if let Some(mut recovery) = self.recovery.take() {
+            recovery.draft.content.zeroize();
+        }
+        self.clear_previews();
+        self.pending_keys = PendingKeys::default();
+        self.list_area.set(None);
+        self.selected_index = list_key(&self.state)
+            .and_then(|key| self.remembered_selection.get(&key).copied())
+            .unwrap_or(0);
+        effect
+    }
+
+    pub fn clear_previews(&mut self) {
+        for (_, mut content) in self.previews.drain() {
+            content.zeroize();
+        }
+    }
+
+    /// Replaces the list shown by the current screen, keeping the selection
+    /// inside it.
+    pub fn set_items(&mut self, items: Vec<(String, String)>) {
+        self.items = items;
+        self.selected_index = self.selected_index.min(self.items.len().saturating_sub(1));
+    }
+
+    /// Ranks `all_pages` against the input line and lists the matches, best
+    /// first. Ties keep the newest page on top.
+    pub fn filter_quick_open(&mut self) {
+        let mut ranked: Vec<(i64, &PageRef)> = self
+            .all_pages
+            .iter()
+            .filter_map(|page| {
+                let name = page_label(page);
+                Some((search::fuzzy_score(&name, &self.input_buffer)?, page))
+            })
+            .collect();
+        ranked.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        self.search_hits = ranked.into_iter().map(|(_, page)| page.clone()).collect();
+        self.items = self
+            .search_hits
+            .iter()
+            .map(|page| (page_label(page), String::new()))
+            .collect();
+        self.selected_index = 0;
+        self.list_area.set(None);
+    }
+
+    fn remember_selection(&mut self) {
+        if let Some(key) = list_key(&self.state) {
+            self.remembered_selection.insert(key, self.selected_index);
+        }
+    }
+
+    pub fn handle_event(&mut self, event: Event) -> Option<SideEffect> {
+        let key = match event {
+            Event::Key(key) => key,
+            Event::Mouse(mouse) => {
+                self.touch();
+                self.mouse_key(mouse)?
+            }
+            _ => return None,
+        };
+        // Fix Windows double keypress issue
+        if key.kind != KeyEventKind::Press {
+            return None;
+        }
+        self.touch();
+        self.status = None;
+
+        if key.code == KeyCode::Char('l')
+            && key.modifiers.contains(KeyModifiers::CONTROL)
+            && self.lock()
+        {
+            return None;
+        }
+
+        if key.code == KeyCode::Char('q')
+            && key.modifiers.contains(KeyModifiers::CONTROL)
+            && !matches!(self.state, AppState::Confirm(_, _, _))
+        {
+            self.message = None;
+            return self.quit();
+        }
+
+        if key.code == KeyCode::Char('p')
+            && key.modifiers.contains(KeyModifiers::CONTROL)
+            && self.message.is_none()
+            && self.state.is_navigation()
+        {
+            let user_path = self.state.user_path()?.to_string();
+            let password = self.state.password_mut()?.clone();
+            self.remember_selection();
+            self.viewer = None;
+            self.clear_input();
+            return self.enter(AppState::QuickOpen(user_path, password));
+        }
+
+        // Today's folder only exists once a page is added to it, so an empty
+        // day lands on a list holding just "Add Page".
+        if key.code == KeyCode::Char('t')
+            && key.modifiers.contains(KeyModifiers::CONTROL)
+            && self.message.is_none()
+            && self.state.is_navigation()
+        {
+            let user_path = self.state.user_path()?.to_string();
+            let password = self.state.password_mut()?.clone();
+            self.remember_selection();
+            self.viewer = None;
+            return self.enter(AppState::SelectFile(user_path, password, today_str()));
+        }
+
+        if key.code == KeyCode::Char('r')
+            && key.modifiers.contains(KeyModifiers::CONTROL)
+            && self.message.is_none()
+            && self.state.is_password_prompt()
+        {
+            self.reveal_password = !self.reveal_password;
+            return None;
+        }
+
+        self.remember_selection();
+
+        // Any key closes the help overlay, leaving the screen as it was
+        if self.show_help {
+            self.show_help = false;
+            return None;
+        }
+        if key.code == KeyCode::Char('?')
+            && self.message.is_none()
+            && self.key_hints().contains(&("?".into(), "Help"))
+        {
+            self.show_help = true;
+            return None;
+        }
+
+        // Any key dismisses an open message
+        if self.message.take().is_some() {
+            return None;
+        }
+
+        match self.state.clone() {
+            AppState::SelectUser => {
+                if key.code == KeyCode::Char('s') {
+                    self.user_sort = self.user_sort.next();
+                    return self.enter(AppState::SelectUser);
+                }
+                if key.code == KeyCode::Char('R') {
+                    return self.enter(AppState::Recoveries);
+                }
+                let allow_back = self.has_profiles();
+                match handle_menu_input(
+                    key,
+                    &mut self.selected_index,
+                    self.items.len(),
+                    allow_back,
+                    &mut self.pending_keys,
+                    &self.config.keys,
+                )? {
+                    MenuAction::Select => {
+                        let (selection, badge) = self.items.get(self.selected_index)?.clone();
+                        if badge == UNRECOGNIZED_BADGE {
+                            self.show_message(
+                                "Error",
+                                &format!("'{}' is not a recognized clog file", selection),
+                            );
+                            return None;
+                        }
+                        if badge == CORRUPT_BADGE {
+                            return Some(SideEffect::PrepareRestore(selection));
+                        }
+                        self.clear_input();
+                        if selection == ADD_USER_ITEM {
+                            let default = self.default_username();
+                            let next = self.enter(AppState::InputPrompt(
+                                "Enter new username:".to_string(),
+                                Box::new(AppState::EnterNewUser),
+                            ));
+                            self.input_default = default;
+                            return next;
+                        }
+                        self.enter(AppState::InputPrompt(
+                            "Enter password:".to_string(),
+                            Box::new(AppState::EnterPassword(selection)),
+                        ))
+                    }
+                    MenuAction::Back => self.enter(AppState::SelectProfile),
+                    MenuAction::Quit => Some(SideEffect::Quit),
+                }
+            }
+            AppState::Recoveries => {
+                if key.code == self.config.keys.delete
+                    && let Some(recovery) = self.recovery_files.get(self.selected_index)
+                {
+                    self.state = AppState::Confirm(
+                        format!(
+                            "Delete the recovery of '{}' from {}?",
+                            recovery.user_file,
+                            recovery
+                                .saved
+                                .map_or_else(|| recovery.name.clone(), time_label)
+                        ),
+                        Box::new(SideEffect::DiscardRecovery(recovery.name.clone())),
+                        Box::new(AppState::Recoveries),
+                    );
+                    return None;
+                }
+                match handle_menu_input(
+                    key,
+                    &mut self.selected_index,
+                    self.items.len(),
+                    true,
+                    &mut self.pending_keys,
+                    &self.config.keys,
+                )? {
+                    MenuAction::Select => {
+                        let recovery = self.recovery_files.get(self.selected_index)?.clone();
+                        self.clear_input();
+                        self.enter(AppState::InputPrompt(
+                            format!("Password of '{}':", recovery.user_file),
+                            Box::new(AppState::OpenRecovery(recovery.name)),
+                        ))
+                    }
+                    MenuAction::Back => self.enter(AppState::SelectUser),
+                    MenuAction::Quit => Some(SideEffect::Quit),
+                }
+            }
+            AppState::ViewRecovery(_) => {
+                let recovery = self.recovery.as_mut()?;
+                match key.code {
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        recovery.scroll = recovery.scroll.saturating_sub(1)
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        recovery.scroll = recovery.scroll.saturating_add(1)
+                    }
+                    KeyCode::PageUp => recovery.scroll = recovery.scroll.saturating_sub(10),
+                    KeyCode::PageDown => recovery.scroll = recovery.scroll.saturating_add(10),
+                    KeyCode::Char('y') => {
+                        return Some(SideEffect::Copy(recovery.draft.content.clone()));
+                    }
+                    code if code == KeyCode::Esc || code == self.config.keys.back => {
+                        return self.enter(AppState::Recoveries);
+                    }
+                    code if code == self.config.keys.quit => return Some(SideEffect::Quit),
+                    _ => {}
+                }
+                None
+            }
+            AppState::SelectProfile => match handle_menu_input(
+                key,
+                &mut self.selected_index,
+                self.items.len(),
+                false,
+                &mut self.pending_keys,
+                &self.config.keys,
+            )? {
+                MenuAction::Select => (self.selected_index < self.items.len())
+                    .then_some(SideEffect::SwitchProfile(self.selected_index)),
+                MenuAction::Back => None,
+                MenuAction::Quit => Some(SideEffect::Quit),
+            },
+            AppState::InputPrompt(_, next_state) => {
+                if key.code == KeyCode::Tab
+                    && let AppState::ReplaceText(_, find) = next_state.as_ref()
+                {
+                    self.replace_match_case = !self.replace_match_case;
+                    let prompt = replace_prompt(find.as_deref(), self.replace_match_case);
+                    self.state = AppState::InputPrompt(prompt, next_state);
+                    return None;
+                }
+                let allow_empty = matches!(
+                    *next_state,
+                    AppState::EditJournalDescription(_, _, _)
+                        | AppState::NewPageTags(_, _, _, _)
+                        | AppState::EditTags(_, _, _, _)
+                        | AppState::ReplaceText(_, Some(_))
+                );
+                match self.prompt_input(key, allow_empty)? {
+                    PromptAction::Submit(input) => {
+                        self.clear_input();
+                        match *next_state {
+                            AppState::EnterNewUser => {
+                                if let Err(reason) = validate_username(&input) {
+                                    self.show_message("Error", &reason);
+                                    self.set_input(&input);
+                                    return None;
+                                }
+                                self.enter(AppState::InputPrompt(
+                                    "Enter password:".to_string(),
+                                    Box::new(AppState::SelectFolder(
+                                        format!("{}.clog", input),
+                                        String::new(),
+                                    )),
+                                ))
+                            }
+                            AppState::EnterPassword(user_path) => {
+                                Some(SideEffect::Unlock(user_path, input))
+                            }
+                            AppState::SelectFolder(user_path, _) => {
+                                Some(SideEffect::CreateUser(user_path, input))
+                            }
+                            AppState::EditJournalTitle(user_path, password) => {
+                                let description = self.journal.description.clone();
+                                let next = self.enter(AppState::InputPrompt(
+                                    "Journal description (optional):".to_string(),
+                                    Box::new(AppState::EditJournalDescription(
+                                        user_path, password, input,
+                                    )),
+                                ));
+                                self.set_input(&description);
+                                next
+                            }
+                            AppState::EditJournalDescription(user_path, password, title) => Some(
+                                SideEffect::SaveJournalInfo(user_path, password, title, input),
+                            ),
+                            AppState::SearchChapter(user_path, password, folder) => Some(
+                                SideEffect::SearchChapter(user_path, password, folder, input),
+                            ),
+                            AppState::SearchPrompt(user_path, password) => {
+                                Some(SideEffect::SearchJournal(user_path, password, input))
+                            }
+                            AppState::Export(user_path, password) => {
+                                Some(SideEffect::PrepareExport(user_path, password, input))
+                            }
+                            AppState::Import(user_path, password) => {
+                                Some(SideEffect::PrepareImport(user_path, password, input))
+                            }
+                            AppState::ReplaceText(write, None) => {
+                                self.enter(AppState::InputPrompt(
+                                    replace_prompt(Some(&input), self.replace_match_case),
+                                    Box::new(AppState::ReplaceText(write, Some(input))),
+                                ))
+                            }
+                            AppState::ReplaceText(write, Some(find)) => {
+                                let replaced = self.editor.as_mut()?.replace_all(
+                                    &find,
+                                    &input,
+                                    self.replace_match_case,
+                                );
+                                if replaced > 0 {
+                                    self.note_edit();
+                                }
+                                self.announce(match replaced {
+                                    0 => format!("No '{}' on this page", find),
+                                    1 => format!("Replaced 1 '{}'", find),
+                                    n => format!("Replaced {} '{}'", n, find),
+                                });
+                                self.enter(AppState::InlineEdit(write))
+                            }
+                            AppState::ExportPage(user_path, password, folder, file, None) => {
+                                let destination = export::resolve_destination(&input);
+                                let question = format!(
+                                    "Share '{}' as an encrypted page file at\n{}",
+                                    file,
+                                    destination.display()
+                                );
+                                self.state = AppState::Confirm(
+                                    question,
+                                    Box::new(SideEffect::SharePage(
+                                        user_path.clone(),
+                                        password.clone(),
+                                        folder.clone(),
+                                        file,
+                                        destination,
+                                    )),
+                                    Box::new(AppState::SelectFile(user_path, password, folder)),
+                                );
+                                None
+                            }
+                            AppState::ExportPage(
+                                user_path,
+                                password,
+                                folder,
+                                file,
+                                Some(destination),
+                            ) => Some(SideEffect::ExportPage(
+                                user_path,
+                                password,
+                                folder,
+                                file,
+                                destination,
+                                input,
+                            )),
+                            AppState::ImportPage(user_path, password, folder, None) => {
+                                self.enter(AppState::InputPrompt(
+                                    "Passphrase of the shared page:".to_string(),
+                                    Box::new(AppState::ImportPage(
+                                        user_path,
+                                        password,
+                                        folder,
+                                        Some(input),
+                                    )),
+                                ))
+                            }
+                            AppState::ImportPage(user_path, password, folder, Some(source)) => {
+                                Some(SideEffect::ImportPage(
+                                    user_path, password, folder, source, input,
+                                ))
+                            }
+                            AppState::OpenRecovery(name) => {
+                                Some(SideEffect::OpenRecovery(name, input))
+                            }
+                            AppState::ExportChapter(user_path, password, folder) => {
+                                Some(SideEffect::PrepareExportChapter(
+                                    user_path, password, folder, input,
+                                ))
+                            }
+                            AppState::AttachFile(user_path, password, folder, file) => Some(
+                                SideEffect::PrepareAttach(user_path, password, folder, file, input),
+                            ),
+                            AppState::ExtractAttachment(
+                                user_path,
+                                password,
+                                folder,
+                                file,
+                                page,
+                            ) => Some(SideEffect::ExtractAttachment(
+                                user_path, password, folder, file, page, input,
+                            )),
+                            AppState::NewChapter(user_path, password, parent) => {
+                                if is_day_name(&input)
+                                    || input == NEW_CHAPTER_ITEM
+                                    || input == ADD_PAGE_ITEM
+                                {
+                                    self.show_message(
+                                        "Error",
+                                        &format!(
+                                            "'{}' is reserved: chapters named after a day are \
+                                             made by writing on that day",
+                                            input
+                                        ),
+                                    );
+                                    self.set_input(&input);
+                                    return None;
+                                }
+                                Some(SideEffect::AddFolder(user_path, password, input, parent))
+                            }
+                            AppState::RenamePage(user_path, password, folder, old_name) => {
+                                if is_reserved_page(&input) {
+                                    self.show_message(
+                                        "Error",
+                                        &format!("'{}' is reserved, choose another name", input),
+                                    );
+                                    self.set_input(&input);
+                                    return None;
+                                }
+                                if input == old_name {
+                                    return self
+                                        .enter(AppState::SelectFile(user_path, password, folder));
+                                }
+                                Some(SideEffect::RenamePage(
+                                    user_path, password, folder, old_name, input,
+                                ))
+                            }
+                            AppState::DuplicatePage(user_path, password, folder, file) => {
+                                if is_reserved_page(&input) {
+                                    self.show_message(
+                                        "Error",
+                                        &format!("'{}' is reserved, choose another name", input),
+                                    );
+                                    self.set_input(&input);
+                                    return None;
+                                }
+                                Some(SideEffect::DuplicatePage(
+                                    user_path, password, folder, file, input,
+                                ))
+                            }
+                            AppState::NewPageTags(user_path, password, folder, file) => {
+                                Some(SideEffect::AddPage(
+                                    user_path,
+                                    password,
+                                    folder,
+                                    file,
+                                    parse_tags(&input),
+                                ))
+                            }
+                            AppState::EditTags(user_path, password, folder, file) => {
+                                Some(SideEffect::SaveTags(
+                                    user_path,
+                                    password,
+                                    folder,
+                                    file,
+                                    parse_tags(&input),
+                                ))
+                            }
+                            _ => None,
+                        }
+                    }
+                    PromptAction::Cancel => {
+                        self.clear_input();
+                        match *next_state {
+                            AppState::EditJournalTitle(user_path, password)
+                            | AppState::EditJournalDescription(user_path, password, _)
+                            | AppState::SearchPrompt(user_path, password)
+                            | AppState::NewChapter(user_path, password, None)
+                            | AppState::Export(user_path, password)
+                            | AppState::Import(user_path, password) => {
+                                self.enter(AppState::SelectFolder(user_path, password))
+                            }
+                            AppState::NewChapter(user_path, password, Some(folder)) => {
+                                self.enter(AppState::SelectFile(user_path, password, folder))
+                            }
+                            AppState::ReplaceText(write, _) => {
+                                self.enter(AppState::InlineEdit(write))
+                            }
+                            AppState::AttachFile(user_path, password, folder, file)
+                            | AppState::ExtractAttachment(user_path, password, folder, file, _) => {
+                                self.enter(AppState::Attachments(user_path, password, folder, file))
+                            }
+                            AppState::SearchChapter(user_path, password, folder)
+                            | AppState::RenamePage(user_path, password, folder, _)
+                            | AppState::DuplicatePage(user_path, password, folder, _)
+                            | AppState::ExportPage(user_path, password, folder, _, _)
+                            | AppState::ExportChapter(user_path, password, folder)
+                            | AppState::ImportPage(user_path, password, folder, _)
+                            | AppState::NewPageTags(user_path, password, folder, _)
+                            | AppState::EditTags(user_path, password, folder, _) => {
+                                self.enter(AppState::SelectFile(user_path, password, folder))
+                            }
+                            _ => self.enter(AppState::SelectUser),
+                        }
+                    }
+                }
+            }
+            AppState::AddPagePrompt(user_path, password, folder) => {
+                match self.prompt_input(key, false)? {
+                    PromptAction::Submit(filename) => {
+                        if is_reserved_page(&filename) {
+                            self.show_message(
+                                "Error",
+                                &format!("'{}' is reserved, choose another name", filename),
+                            );
+                            return None;
+                        }
+                        self.clear_input();
+                        self.enter(AppState::InputPrompt(
+                            format!("Tags for '{}' (comma-separated, optional):", filename),
+                            Box::new(AppState::NewPageTags(user_path, password, folder, filename)),
+                        ))
+                    }
+                    PromptAction::Cancel => {
+                        self.clear_input();
+                        self.enter(AppState::SelectFile(user_path, password, folder))
+                    }
+                }
+            }
+            AppState::SelectFolder(user_path, password) => {
+                if key.code == KeyCode::Char('i') && !self.readonly {
+                    let title = self.journal.title.clone();
+                    let next = self.enter(AppState::InputPrompt(
+                        "Journal title:".to_string(),
+                        Box::new(AppState::EditJournalTitle(user_path, password)),
+                    ));
+                    self.set_input(&title);
+                    return next;
+                }
+                if key.code == KeyCode::Char('e') && self.has_chapters() {
+                    let next = self.enter(AppState::InputPrompt(
+                        "Export as Markdown to directory:".to_string(),
+                        Box::new(AppState::Export(user_path, password)),
+                    ));
+                    self.set_input("~/clog-export");
+                    return next;
+                }
+                if key.code == KeyCode::Char('t') {
+                    return self.enter(AppState::SelectTag(user_path, password));
+                }
+                if key.code == KeyCode::Char('u') && self.undo.is_some() {
+                    return Some(SideEffect::Undo(user_path, password));
+                }
+                if key.code == KeyCode::Char('T') && self.has_chapters() {
+                    return self.enter(AppState::Timeline(user_path, password));
+                }
+                if key.code == KeyCode::Char('s') && self.has_chapters() {
+                    return self.enter(AppState::Stats(user_path, password));
+                }
+                if key.code == KeyCode::Char('x') && !self.journal.trash.is_empty() {
+                    return self.enter(AppState::Trash(user_path, password));
+                }
+                if key.code == KeyCode::Char('o')
+                    && !self.readonly
+                    && let Some(folder) = self.selected_folder()
+                {
+                    // Through the palette, then back to none.
+                    let color = match self.journal.color_of(&folder) {
+                        None => PALETTE.first(),
+                        Some(current) => PALETTE
+                            .iter()
+                            .position(|color| color.to_string() == current)
+                            .and_then(|index| PALETTE.get(index + 1)),
+                    };
+                    return Some(SideEffect::SetChapterColor(
+                        user_path,
+                        password,
+                        folder,
+                        color.map(Color::to_string),
+                    ));
+                }
+                if key.code == KeyCode::Char('I') && !self.readonly {
+                    self.clear_input();
+                    return self.enter(AppState::InputPrompt(
+                        "Import .txt/.md files from directory:".to_string(),
+                        Box::new(AppState::Import(user_path, password)),
+                    ));
+                }
+                if key.code == KeyCode::Char('c') && self.has_chapters() {
+                    let date = self
+                        .items
+                        .get(self.selected_index)
+                        .and_then(|(folder, _)| parse_folder_date(folder))
+                        .unwrap_or_else(|| Local::now().date_naive());
+                    return self.enter(AppState::Calendar(user_path, password, date));
+                }
+                if key.code == self.config.keys.search && self.has_chapters() {
+                    self.clear_input();
+                    return self.enter(AppState::InputPrompt(
+                        "Search all pages:".to_string(),
+                        Box::new(AppState::SearchPrompt(user_path, password)),
+                    ));
+                }
+                match handle_menu_input(
+                    key,
+                    &mut self.selected_index,
+                    self.items.len(),
+                    true,
+                    &mut self.pending_keys,
+                    &self.config.keys,
+                )? {
+                    MenuAction::Select => {
+                        let folder = self.items.get(self.selected_index)?.0.clone();
+                        if folder == NEW_CHAPTER_ITEM {
+                            self.clear_input();
+                            return self.enter(AppState::InputPrompt(
+                                "New chapter name:".to_string(),
+                                Box::new(AppState::NewChapter(user_path, password, None)),
+                            ));
+                        }
+                        self.enter(AppState::SelectFile(user_path, password, folder))
+                    }
+                    MenuAction::Back => self.enter(AppState::SelectUser),
+                    MenuAction::Quit => Some(SideEffect::Quit),
+                }
+            }
+            AppState::SelectFile(user_path, password, folder) => {
+                let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+                match key.code {
+                    KeyCode::Up | KeyCode::Down if shift && !self.readonly => {
+                        let up = key.code == KeyCode::Up;
+                        return self.move_page(user_path, password, folder, up);
+                    }
+                    KeyCode::Char('K') | KeyCode::Char('J') if !self.readonly => {
+                        let up = key.code == KeyCode::Char('K');
+                        return self.move_page(user_path, password, folder, up);
+                    }
+                    KeyCode::Char(' ') => {
+                        let file = self.selected_page()?;
+                        if !self.marked.remove(&file) {
+                            self.marked.insert(file);
+                        }
+                        return None;
+                    }
+                    KeyCode::Char('t') if !self.readonly => {
+                        let file = self.selected_page()?;
+                        let tags = self.journal.tags_of(&folder, &file).join(", ");
+                        let next = self.enter(AppState::InputPrompt(
+                            format!("Tags for '{}' (comma-separated):", file),
+                            Box::new(AppState::EditTags(user_path, password, folder, file)),
+                        ));
+                        self.set_input(&tags);
+                        return next;
+                    }
+                    KeyCode::Char('u') if self.undo.is_some() => {
+                        return Some(SideEffect::Undo(user_path, password));
+                    }
+                    KeyCode::Char('c') if !self.readonly => {
+                        let file = self.selected_page()?;
+                        let next = self.enter(AppState::InputPrompt(
+                            format!("Name for the copy of '{}':", file),
+                            Box::new(AppState::DuplicatePage(
+                                user_path,
+                                password,
+                                folder,
+                                file.clone(),
+                            )),
+                        ));
+                        self.set_input(&file);
+                        return next;
+                    }
+                    KeyCode::Char('e') => {
+                        let file = self.selected_page()?;
+                        let next = self.enter(AppState::InputPrompt(
+                            format!("Share '{}' as an encrypted page file:", file),
+                            Box::new(AppState::ExportPage(
+                                user_path,
+                                password,
+                                folder,
+                                file.clone(),
+                                None,
+                            )),
+                        ));
+                        self.set_input(&format!("~/{}.clogpage", file));
+                        return next;
+                    }
+                    KeyCode::Char('y') => {
+                        self.selected_page()?;
+                        return Some(SideEffect::CopyChapter(user_path, password, folder));
+                    }
+                    KeyCode::Char('E') => {
+                        self.selected_page()?;
+                        let file = export::day_file_name(&folder);
+                        let next = self.enter(AppState::InputPrompt(
+                            format!("Export {} as Markdown to:", folder_label(&folder)),
+                            Box::new(AppState::ExportChapter(user_path, password, folder)),
+                        ));
+                        self.set_input(&format!("~/{}", file));
+                        return next;
+                    }
+                    KeyCode::Char('a') => {
+                        let file = self.selected_page()?;
+                        return self
+                            .enter(AppState::Attachments(user_path, password, folder, file));
+                    }
+                    KeyCode::Char('I') if !self.readonly => {
+                        self.clear_input();
+                        return self.enter(AppState::InputPrompt(
+                            "Import shared .clogpage file:".to_string(),
+                            Box::new(AppState::ImportPage(user_path, password, folder, None)),
+                        ));
+                    }
+                    KeyCode::Char('p') if !self.readonly => {
+                        let file = self.selected_page()?;
+                        if !self.journal.is_locked(&folder, &file) {
+                            return Some(SideEffect::SetLocked(
+                                user_path, password, folder, file, true,
+                            ));
+                        }
+                        let back = AppState::SelectFile(
+                            user_path.clone(),
+                            password.clone(),
+                            folder.clone(),
+                        );
+                        self.state = AppState::Confirm(
+                            format!(
+                                "Unlock '{}'?\n\nIt can be edited, renamed and deleted again.",
+                                file
+                            ),
+                            Box::new(SideEffect::SetLocked(
+                                user_path, password, folder, file, false,
+                            )),
+                            Box::new(back),
+                        );
+                        return None;
+                    }
+                    KeyCode::Char('r') => {
+                        let file = self.selected_page()?;
+                        if !self.is_renamable(&folder) {
+                            let reason = self.not_editable_reason("renamed");
+                            self.show_message("Error", &reason);
+                            return None;
+                        }
+                        if self.journal.is_locked(&folder, &file) {
+                            self.show_message("Error", &Self::locked_reason(&file));
+                            return None;
+                        }
+                        let next = self.enter(AppState::InputPrompt(
+                            "Rename page to:".to_string(),
+                            Box::new(AppState::RenamePage(
+                                user_path,
+                                password,
+                                folder,
+                                file.clone(),
+                            )),
+                        ));
+                        self.set_input(&file);
+                        return next;
+                    }
+                    code if code == self.config.keys.delete => {
+                        let files: Vec<String> = if self.marked.is_empty() {
+                            vec![self.selected_page()?]
+                        } else {
+                            self.marked.iter().cloned().collect()
+                        };
+                        if !self.is_editable(&folder) {
+                            let reason = self.not_editable_reason("deleted");
+                            self.show_message("Error", &reason);
+                            return None;
+                        }
+                        if let Some(file) = files
+                            .iter()
+                            .find(|file| self.journal.is_locked(&folder, file))
+                        {
+                            self.show_message("Error", &Self::locked_reason(file));
+                            return None;
+                        }
+                        let question = match files.as_slice() {
+                            [file] => format!(
+                                "Delete page '{}' from {}?\n\nIt goes to the trash.",
+                                file,
+                                folder_label(&folder)
+                            ),
+                            _ => format!(
+                                "Delete {} pages from {}?\n\n{}\n\nThey go to the trash.",
+                                files.len(),
+                                folder_label(&folder),
+                                files.join(", ")
+                            ),
+                        };
+                        self.announce(match files.as_slice() {
+                            [file] => {
+                                format!("Will delete '{}' from {}", file, folder_label(&folder))
+                            }
+                            _ => format!(
+                                "Will delete {} pages from {}",
+                                files.len(),
+                                folder_label(&folder)
+                            ),
+                        });
+                        let back = AppState::SelectFile(
+                            user_path.clone(),
+                            password.clone(),
+                            folder.clone(),
+                        );
+                        self.state = AppState::Confirm(
+                            question,
+                            Box::new(SideEffect::DeletePages(user_path, password, folder, files)),
+                            Box::new(back),
+                        );
+                        return None;
+                    }
+                    _ => {}
+                }
+                if key.code == self.config.keys.search && !self.items.is_empty() {
+                    self.clear_input();
+                    return self.enter(AppState::InputPrompt(
+                        "Search this chapter:".to_string(),
+                        Box::new(AppState::SearchChapter(user_path, password, folder)),
+                    ));
+                }
+                match handle_menu_input(
+                    key,
+                    &mut self.selected_index,
+                    self.items.len(),
+                    true,
+                    &mut self.pending_keys,
+                    &self.config.keys,
+                )? {
+                    MenuAction::Select => {
+                        let (file, badge) = self.items.get(self.selected_index)?.clone();
+                        if file == ADD_PAGE_ITEM {
+                            self.clear_input();
+                            self.input_default = Some(self.default_page_name());
+                            self.state = AppState::AddPagePrompt(user_path, password, folder);
+                            None
+                        } else if file == NEW_CHAPTER_ITEM {
+                            self.clear_input();
+                            self.enter(AppState::InputPrompt(
+                                format!("New chapter in '{}':", folder),
+                                Box::new(AppState::NewChapter(user_path, password, Some(folder))),
+                            ))
+                        } else if badge == SUB_CHAPTER_BADGE {
+                            self.enter(AppState::SelectFile(user_path, password, file))
+                        } else {
+                            self.state = AppState::EditOrViewFile(
+                                user_path.clone(),
+                                password.clone(),
+                                folder.clone(),
+                                file.clone(),
+                            );
+                            Some(SideEffect::OpenPage(user_path, password, folder, file))
+                        }
+                    }
+                    MenuAction::Back => match self.journal.parent_of(&folder) {
+                        Some(parent) => {
+                            let parent = parent.to_string();
+                            self.enter(AppState::SelectFile(user_path, password, parent))
+                        }
+                        None => self.enter(AppState::SelectFolder(user_path, password)),
+                    },
+                    MenuAction::Quit => Some(SideEffect::Quit),
+                }
+            }
+            AppState::ChapterResults(user_path, password, folder, _) => {
+                match handle_menu_input(
+                    key,
+                    &mut self.selected_index,
+                    self.items.len(),
+                    true,
+                    &mut self.pending_keys,
+                    &self.config.keys,
+                )? {
+                    MenuAction::Select => {
+                        let file = self.items.get(self.selected_index)?.0.clone();
+                        self.state = AppState::EditOrViewFile(
+                            user_path.clone(),
+                            password.clone(),
+                            folder.clone(),
+                            file.clone(),
+                        );
+                        Some(SideEffect::OpenPage(user_path, password, folder, file))
+                    }
+                    MenuAction::Back => {
+                        self.enter(AppState::SelectFile(user_path, password, folder))
+                    }
+                    MenuAction::Quit => Some(SideEffect::Quit),
+                }
+            }
+            AppState::Calendar(user_path, password, date) => {
+                let keys = self.config.keys;
+                let moved = match key.code {
+                    KeyCode::Left | KeyCode::Char('h') => date.checked_sub_days(Days::new(1)),
+                    KeyCode::Right | KeyCode::Char('l') => date.checked_add_days(Days::new(1)),
+                    code if code == KeyCode::Up || code == keys.up => {
+                        date.checked_sub_days(Days::new(7))
+                    }
+                    code if code == KeyCode::Down || code == keys.down => {
+                        date.checked_add_days(Days::new(7))
+                    }
+                    KeyCode::PageUp => date.checked_sub_months(Months::new(1)),
+                    KeyCode::PageDown => date.checked_add_months(Months::new(1)),
+                    code if code == KeyCode::Enter || code == keys.select => {
+                        let folder = folder_name(date);
+                        if !self.items.iter().any(|(item, _)| *item == folder) {
+                            return None;
+                        }
+                        return self.enter(AppState::SelectFile(user_path, password, folder));
+                    }
+                    code if code == KeyCode::Char('c')
+                        || code == KeyCode::Esc
+                        || code == keys.back =>
+                    {
+                        return self.enter(AppState::SelectFolder(user_path, password));
+                    }
+                    code if code == keys.quit => return Some(SideEffect::Quit),
+                    _ => None,
+                };
+                self.state = AppState::Calendar(user_path, password, moved?);
+                None
+            }
+            AppState::SelectTag(user_path, password) => {
+                match handle_menu_input(
+                    key,
+                    &mut self.selected_index,
+                    self.items.len(),
+                    true,
+                    &mut self.pending_keys,
+                    &self.config.keys,
+                )? {
+                    MenuAction::Select => {
+                        let tag = self.items.get(self.selected_index)?.0.clone();
+                        self.enter(AppState::TagResults(user_path, password, tag))
+                    }
+                    MenuAction::Back => self.enter(AppState::SelectFolder(user_path, password)),
+                    MenuAction::Quit => Some(SideEffect::Quit),
+                }
+            }
+            AppState::TagResults(user_path, password, _)
+            | AppState::Timeline(user_path, password) => {
+                let back = match &self.state {
+                    AppState::TagResults(_, _, _) => {
+                        AppState::SelectTag(user_path.clone(), password.clone())
+                    }
+                    _ => AppState::SelectFolder(user_path.clone(), password.clone()),
+                };
+                match handle_menu_input(
+                    key,
+                    &mut self.selected_index,
+                    self.items.len(),
+                    true,
+                    &mut self.pending_keys,
+                    &self.config.keys,
+                )? {
+                    MenuAction::Select => {
+                        let PageRef { folder, file } =
+                            self.search_hits.get(self.selected_index)?.clone();
+                        self.state = AppState::EditOrViewFile(
+                            user_path.clone(),
+                            password.clone(),
+                            folder.clone(),
+                            file.clone(),
+                        );
+                        Some(SideEffect::OpenPage(user_path, password, folder, file))
+                    }
+                    MenuAction::Back => self.enter(back),
+                    MenuAction::Quit => Some(SideEffect::Quit),
+                }
+            }
+            AppState::Trash(user_path, password) => {
+                // Listed newest first.
+                let index = self
+                    .journal
+                    .trash
+                    .len()
+                    .checked_sub(self.selected_index + 1);
+                if key.code == self.config.keys.delete
+                    && !self.readonly
+                    && let Some(index) = index
+                {
+                    let page = &self.journal.trash[index];
+                    let question = format!(
+                        "Purge '{}' from the trash?\n\nIt can't be restored afterwards.",
+                        page.file
+                    );
+                    self.announce(format!("Will purge '{}' for good", page.file));
+                    let back = AppState::Trash(user_path.clone(), password.clone());
+                    self.state = AppState::Confirm(
+                        question,
+                        Box::new(SideEffect::PurgeTrashed(user_path, password, index)),
+                        Box::new(back),
+                    );
+                    return None;
+                }
+                match handle_menu_input(
+                    key,
+                    &mut self.selected_index,
+                    self.items.len(),
+                    true,
+                    &mut self.pending_keys,
+                    &self.config.keys,
+                )? {
+                    MenuAction::Select if self.readonly => None,
+                    MenuAction::Select => {
+                        Some(SideEffect::RestoreTrashed(user_path, password, index?))
+                    }
+                    MenuAction::Back => self.enter(AppState::SelectFolder(user_path, password)),
+                    MenuAction::Quit => Some(SideEffect::Quit),
+                }
+            }
+            AppState::Attachments(user_path, password, folder, file) => {
+                let page = self
+                    .journal
+                    .attachments_of(&folder, &file)
+                    .get(self.selected_index)
+                    .map(|attachment| (attachment.name.clone(), attachment.page.clone()));
+                if key.code == self.config.keys.delete
+                    && !self.readonly
+                    && let Some((name, page)) = page
+                {
+                    let question = format!(
+                        "Remove '{}' from '{}'?\n\nThe attachment is erased from the journal.",
+                        name, file
+                    );
+                    let back = AppState::Attachments(
+                        user_path.clone(),
+                        password.clone(),
+                        folder.clone(),
+                        file.clone(),
+                    );
+                    self.state = AppState::Confirm(
+                        question,
+                        Box::new(SideEffect::RemoveAttachment(
+                            user_path, password, folder, file, page,
+                        )),
+                        Box::new(back),
+                    );
+                    return None;
+                }
+                match handle_menu_input(
+                    key,
+                    &mut self.selected_index,
+                    self.items.len(),
+                    true,
+                    &mut self.pending_keys,
+                    &self.config.keys,
+                )? {
+                    MenuAction::Select => match page {
+                        Some((name, page)) => {
+                            let next = self.enter(AppState::InputPrompt(
+                                format!("Extract '{}' to directory:", name),
+                                Box::new(AppState::ExtractAttachment(
+                                    user_path, password, folder, file, page,
+                                )),
+                            ));
+                            self.set_input("~");
+                            next
+                        }
+                        None if self.readonly => None,
+                        None => {
+                            self.clear_input();
+                            self.enter(AppState::InputPrompt(
+                                format!("Attach file to '{}':", file),
+                                Box::new(AppState::AttachFile(user_path, password, folder, file)),
+                            ))
+                        }
+                    },
+                    MenuAction::Back => {
+                        self.enter(AppState::SelectFile(user_path, password, folder))
+                    }
+                    MenuAction::Quit => Some(SideEffect::Quit),
+                }
+            }
+            AppState::Stats(user_path, password) => match key.code {
+                code if code == KeyCode::Esc || code == self.config.keys.back => {
+                    self.enter(AppState::SelectFolder(user_path, password))
+                }
+                code if code == self.config.keys.quit => Some(SideEffect::Quit),
+                _ => None,
+            },
+            AppState::JournalInUse(user_path, password, _) => match key.code {
+                KeyCode::Char('r') => {
+                    self.readonly = true;
+                    self.readonly_journal = true;
+                    Some(SideEffect::Unlock(user_path, password))
+                }
+                KeyCode::Char('f') => Some(SideEffect::ForceUnlock(user_path, password)),
+                code if code == KeyCode::Esc || code == self.config.keys.back => {
+                    self.enter(AppState::SelectUser)
+                }
+                code if code == self.config.keys.quit => Some(SideEffect::Quit),
+                _ => None,
+            },
+            AppState::RecoverDraft(user_path, password) => {
+                let recovery = self.recovery.as_mut()?;
+                match key.code {
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        recovery.scroll = recovery.scroll.saturating_sub(1)
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        recovery.scroll = recovery.scroll.saturating_add(1)
+                    }
+                    KeyCode::PageUp => recovery.scroll = recovery.scroll.saturating_sub(10),
+                    KeyCode::PageDown => recovery.scroll = recovery.scroll.saturating_add(10),
+                    KeyCode::Enter => return self.recover_draft(user_path, password),
+                    KeyCode::Char('c') if recovery.saved.is_none() => {
+                        self.announce("A new page has no saved version to compare".to_string())
+                    }
+                    KeyCode::Char('c') => {
+                        recovery.compare = !recovery.compare;
+                        recovery.scroll = 0;
+                    }
+                    KeyCode::Char('y') => {
+                        return Some(SideEffect::Copy(recovery.draft.content.clone()));
+                    }
+                    KeyCode::Char('d') => {
+                        let file = recovery.draft.file.clone();
+                        self.state = AppState::Confirm(
+                            format!("Discard the unsaved draft of '{}'?", file),
+                            Box::new(SideEffect::DiscardDraft(
+                                user_path.clone(),
+                                Box::new(AppState::SelectFolder(
+                                    user_path.clone(),
+                                    password.clone(),
+                                )),
+                            )),
+                            Box::new(AppState::RecoverDraft(user_path, password)),
+                        );
+                    }
+                    code if code == KeyCode::Esc || code == self.config.keys.back => {
+                        return self.enter(AppState::SelectFolder(user_path, password));
+                    }
+                    code if code == self.config.keys.quit => return Some(SideEffect::Quit),
+                    _ => {}
+                }
+                None
+            }
+            AppState::QuickOpen(user_path, password) => {
+                let last = self.items.len().saturating_sub(1);
+                let control = key.modifiers.contains(KeyModifiers::CONTROL);
+                match key.code {
+                    KeyCode::Up => self.selected_index = self.selected_index.saturating_sub(1),
+                    KeyCode::Char('p') if control => {
+                        self.selected_index = self.selected_index.saturating_sub(1)
+                    }
+                    KeyCode::Down => self.selected_index = (self.selected_index + 1).min(last),
+                    KeyCode::Char('n') if control => {
+                        self.selected_index = (self.selected_index + 1).min(last)
+                    }
+                    KeyCode::Enter => {
+                        let PageRef { folder, file } =
+                            self.search_hits.get(self.selected_index)?.clone();
+                        self.clear_input();
+                        self.state = AppState::EditOrViewFile(
+                            user_path.clone(),
+                            password.clone(),
+                            folder.clone(),
+                            file.clone(),
+                        );
+                        return Some(SideEffect::OpenPage(user_path, password, folder, file));
+                    }
+                    _ => match handle_prompt_input(
+                        key,
+                        &mut self.input_buffer,
+                        &mut self.input_cursor,
+                        true,
+                    ) {
+                        Some(PromptAction::Cancel) => {
+                            self.clear_input();
+                            return self.enter(AppState::SelectFolder(user_path, password));
+                        }
+                        Some(PromptAction::Submit(_)) => {}
+                        None => self.filter_quick_open(),
+                    },
+                }
+                None
+            }
+            AppState::SearchResults(user_path, password, _) => {
+                match handle_menu_input(
+                    key,
+                    &mut self.selected_index,
+                    self.items.len(),
+                    true,
+                    &mut self.pending_keys,
+                    &self.config.keys,
+                )? {
+                    MenuAction::Select => {
+                        let PageRef { folder, file } =
+                            self.search_hits.get(self.selected_index)?.clone();
+                        self.state = AppState::EditOrViewFile(
+                            user_path.clone(),
+                            password.clone(),
+                            folder.clone(),
+                            file.clone(),
+                        );
+                        Some(SideEffect::OpenPage(user_path, password, folder, file))
+                    }
+                    MenuAction::Back => self.enter(AppState::SelectFolder(user_path, password)),
+                    MenuAction::Quit => Some(SideEffect::Quit),
+                }
+            }
+            AppState::ViewPage(_, _, _, _) if self.viewer.as_ref().is_some_and(|v| v.searching) => {
+                let action =
+                    handle_prompt_input(key, &mut self.input_buffer, &mut self.input_cursor, true);
+                let viewer = self.viewer.as_mut()?;
+                match action {
+                    Some(PromptAction::Submit(query)) if !query.is_empty() => {
+                        viewer.searching = false;
+                        if viewer.match_spots.borrow().is_empty() {
+                            self.announce(format!("No '{}' on this page", query));
+                        } else {
+                            let first = viewer.first_match_in_view();
+                            viewer.show_match(first);
+                        }
+                    }
+                    Some(_) => viewer.clear_search(),
+                    None => {
+                        viewer.query = self.input_buffer.clone();
+                        viewer.current_match = 0;
+                    }
+                }
+                None
+            }
+            AppState::ViewPage(_, _, _, _)
+                if key.code == self.config.keys.search
+                    || (key.code == KeyCode::Esc
+                        && self.viewer.as_ref().is_some_and(|v| !v.query.is_empty())) =>
+            {
+                let viewer = self.viewer.as_mut()?;
+                viewer.clear_search();
+                if key.code != KeyCode::Esc {
+                    viewer.searching = true;
+                    self.clear_input();
+                }
+                None
+            }
+            AppState::ViewPage(_, _, _, _)
+                if matches!(key.code, KeyCode::Char('n') | KeyCode::Char('N'))
+                    && self.viewer.as_ref().is_some_and(|v| !v.query.is_empty()) =>
+            {
+                let viewer = self.viewer.as_mut()?;
+                let next = viewer.step_match(key.code == KeyCode::Char('n'));
+                viewer.show_match(next);
+                None
+            }
+            AppState::ViewPage(user_path, password, folder, _) => match key.code {
+                KeyCode::Char('y') => Some(SideEffect::Copy(self.viewer.as_ref()?.content.clone())),
+                KeyCode::Char('m') => {
+                    self.view_options.markdown = !self.view_options.markdown;
+                    self.viewer.as_mut()?.scroll = 0;
+                    None
+                }
+                KeyCode::Char('s') if !self.view_options.markdown => {
+                    let viewer = self.viewer.as_mut()?;
+                    viewer.spellcheck = !viewer.spellcheck;
+                    if viewer.spellcheck && self.dictionary.is_none() {
+                        Some(SideEffect::LoadDictionary)
+                    } else {
+                        None
+                    }
+                }
+                KeyCode::Char('w') => {
+                    self.view_options.wrap = !self.view_options.wrap;
+                    self.viewer.as_mut()?.hscroll = 0;
+                    None
+                }
+                KeyCode::Char('n') if !self.view_options.markdown => {
+                    self.view_options.line_numbers = !self.view_options.line_numbers;
+                    None
+                }
+                KeyCode::Left | KeyCode::Char('h') => {
+                    self.viewer.as_mut()?.scroll_left(HSCROLL_STEP);
+                    None
+                }
+                KeyCode::Right | KeyCode::Char('l') => {
+                    self.viewer.as_mut()?.scroll_right(HSCROLL_STEP);
+                    None
+                }
+                code if code == KeyCode::Up || code == self.config.keys.up => {
+                    self.viewer.as_mut()?.scroll_up(1);
+                    None
+                }
+                code if code == KeyCode::Down || code == self.config.keys.down => {
+                    self.viewer.as_mut()?.scroll_down(1);
+                    None
+                }
+                KeyCode::PageUp => {
+                    let viewer = self.viewer.as_mut()?;
+                    viewer.scroll_up(viewer.page_height.get());
+                    None
+                }
+                KeyCode::PageDown => {
+                    let viewer = self.viewer.as_mut()?;
+                    viewer.scroll_down(viewer.page_height.get());
+                    None
+                }
+                KeyCode::Home => {
+                    self.viewer.as_mut()?.scroll = 0;
+                    None
+                }
+                KeyCode::End => {
+                    self.viewer.as_mut()?.scroll_to_end();
+                    None
+                }
+                code if code == KeyCode::Esc
+                    || code == KeyCode::Enter
+                    || code == self.config.keys.back =>
+                {
+                    self.viewer = None;
+                    self.enter(AppState::SelectFile(user_path, password, folder))
+                }
+                code if code == self.config.keys.quit => Some(SideEffect::Quit),
+                _ => None,
+            },
+            AppState::Locked(mut resume, selected_index) => {
+                let action = self.prompt_input(key, false)?;
+                self.input_buffer.zeroize();
+                self.input_cursor = 0;
+                match action {
+                    PromptAction::Submit(input) => {
+                        if let AppState::Confirm(_, effect, _) = resume.as_mut()
+                            && let Some(password) = effect.password_mut()
+                        {
+                            password.clone_from(&input);
+                        }
+                        *resume.password_mut()? = input;
+                        Some(SideEffect::Resume(resume, selected_index))
+                    }
+                    PromptAction::Cancel => self.enter(AppState::SelectUser),
+                }
+            }
+            AppState::InlineEdit(_)
+                if key.code == KeyCode::Char('y')
+                    && key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                Some(SideEffect::Copy(self.editor.as_ref()?.content()))
+            }
+            AppState::InlineEdit(write) => match self.editor.as_mut()?.handle_key(key) {
+                None => {
+                    self.note_edit();
+                    None
+                }
+                Some(EditorAction::Save) => Some(SideEffect::SaveInlineEdit(write)),
+                Some(EditorAction::Replace) => {
+                    self.clear_input();
+                    self.enter(AppState::InputPrompt(
+                        replace_prompt(None, self.replace_match_case),
+                        Box::new(AppState::ReplaceText(write, None)),
+                    ))
+                }
+                Some(EditorAction::Cancel) => {
+                    let modified = self
+                        .editor
+                        .take()
+                        .is_some_and(|editor| editor.is_modified());
+                    if modified {
+                        self.show_message("Info", "Changes discarded");
+                    }
+                    let back = AppState::SelectFile(write.user_path, write.password, write.folder);
+                    if self.draft_written {
+                        let user_path = back.user_path()?.to_string();
+                        return Some(SideEffect::DiscardDraft(user_path, Box::new(back)));
+                    }
+                    self.enter(back)
+                }
+            },
+            AppState::Confirm(_, effect, back) => match key.code {
+                KeyCode::Char('y') | KeyCode::Enter => Some(*effect),
+                KeyCode::Char('n') | KeyCode::Esc => self.enter(*back),
+                _ => None,
+            },
+            AppState::EditOrViewFile(_, _, _, _) => None,
+            AppState::EnterNewUser
+            | AppState::EnterPassword(_)
+            | AppState::NewChapter(_, _, _)
+            | AppState::EditJournalTitle(_, _)
+            | AppState::EditJournalDescription(_, _, _)
+            | AppState::SearchChapter(_, _, _)
+            | AppState::SearchPrompt(_, _)
+            | AppState::RenamePage(_, _, _, _)
+            | AppState::DuplicatePage(_, _, _, _)
+            | AppState::ExportPage(_, _, _, _, _)
+            | AppState::ExportChapter(_, _, _)
+            | AppState::ImportPage(_, _, _, _)
+            | AppState::ReplaceText(_, _)
+            | AppState::NewPageTags(_, _, _, _)
+            | AppState::EditTags(_, _, _, _)
+            | AppState::AttachFile(_, _, _, _)
+            | AppState::ExtractAttachment(_, _, _, _, _)
+            | AppState::OpenRecovery(_)
+            | AppState::Export(_, _)
+            | AppState::Import(_, _) => None,
+        }
+    }
+}
+
+/// Identifies the list screens whose selection is remembered.
+fn list_key(state: &AppState) -> Option<String> {
+    match state {
+        AppState::SelectProfile => Some("profiles".to_string()),
+        AppState::SelectUser => Some("users".to_string()),
+        AppState::SelectFolder(user_path, _) => Some(format!("folders:{}", user_path)),
+        AppState::SelectFile(user_path, _, folder) => {
+            Some(format!("pages:{}:{}", user_path, folder))
+        }
+        AppState::SelectTag(user_path, _) => Some(format!("tags:{}", user_path)),
+        AppState::Timeline(user_path, _) => Some(format!("timeline:{}", user_path)),
+        AppState::Trash(user_path, _) => Some(format!("trash:{}", user_path)),
+        AppState::Attachments(user_path, _, folder, file) => {
+            Some(format!("attachments:{}:{}:{}", user_path, folder, file))
+        }
+        _ => None,
+    }
+}
+
+pub enum MenuAction {
+    Select,
+    Back,
+    Quit,
+}
+
+/// Keys typed ahead of a list motion: a count, as in `5j`, or the first
+/// `g` of `gg`. Dropped when the next key doesn't come within
+/// `PENDING_TIMEOUT`.
+#[derive(Default)]
+pub struct PendingKeys {
+    count: usize,
+    g: bool,
+    since: Option<Instant>,
+}
+
+impl PendingKeys {
+    fn expire(&mut self) {
+        if self
+            .since
+            .is_some_and(|since| since.elapsed() > PENDING_TIMEOUT)
+        {
+            *self = Self::default();
+        }
+    }
+}
+
+pub fn handle_menu_input(
+    key: KeyEvent,
+    selected_index: &mut usize,
+    items_len: usize,
+    allow_back: bool,
+    pending: &mut PendingKeys,
+    keys: &KeyMap,
+) -> Option<MenuAction> {
+    pending.expire();
+    let last = items_len.saturating_sub(1);
+    match key.code {
+        KeyCode::Char(digit @ '0'..='9') if digit != '0' || pending.count > 0 => {
+            let digit = digit.to_digit(10).unwrap_or(0) as usize;
+            pending.count = (pending.count * 10 + digit).min(MAX_COUNT);
+            pending.since = Some(Instant::now());
+            return None;
+        }
+        KeyCode::Char('g') if !pending.g => {
+            pending.g = true;
+            pending.since = Some(Instant::now());
+            return None;
+        }
+        _ => {}
+    }
+    let count = std::mem::take(pending).count;
+    let code = key.code;
+    let up = code == KeyCode::Up || code == keys.up;
+    let down = code == KeyCode::Down || code == keys.down;
+    match code {
+        KeyCode::Char('g') => *selected_index = 0,
+        KeyCode::Char('G') => *selected_index = last,
+        _ if up && count > 0 => *selected_index = selected_index.saturating_sub(count),
+        _ if down && count > 0 => *selected_index = (*selected_index + count).min(last),
+        _ if up => {
+            if *selected_index > 0 {
+                *selected_index -= 1;
+            } else {
+                *selected_index = last;
+            }
+        }
+        _ if down => {
+            if *selected_index < last {
+                *selected_index += 1;
+            } else {
+                *selected_index = 0;
+            }
+        }
+        _ if (code == KeyCode::Enter || code == keys.select) && items_len > 0 => {
+            return Some(MenuAction::Select);
+        }
+        _ if (code == KeyCode::Esc || code == keys.back) && allow_back => {
+            return Some(MenuAction::Back);
+        }
+        _ if code == keys.quit => return Some(MenuAction::Quit),
+        _ => {}
+    }
+    None
+}
+
+pub enum PromptAction {
+    Submit(String),
+    Cancel,
+}
+
+pub fn handle_prompt_input(
+    key: KeyEvent,
+    input_buffer: &mut String,
+    input_cursor: &mut usize,
+    allow_empty: bool,
+) -> Option<PromptAction> {
+    match key.code {
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            return Some(PromptAction::Cancel);
+        }
+        KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            input::delete_word_before(input_buffer, input_cursor)
+        }
+        KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            input::delete_to_start(input_buffer, input_cursor)
+        }
+        KeyCode::Char('a') if key.modifiers.contains(KeyModifiers::CONTROL) => *input_cursor = 0,
+        KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            input::move_end(input_buffer, input_cursor)
+        }
+        KeyCode::Char('k') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            input::delete_to_end(input_buffer, *input_cursor)
+        }
+        // Other control keys mean nothing here and shouldn't type their
+        // letter. AltGr arrives as Ctrl+Alt on Windows and still types.
+        KeyCode::Char(_)
+            if key.modifiers.contains(KeyModifiers::CONTROL)
+                && !key.modifiers.contains(KeyModifiers::ALT) => {}
+        KeyCode::Char(c) => input::insert_char(input_buffer, input_cursor, c),
+        KeyCode::Backspace => input::delete_before(input_buffer, input_cursor),
+        KeyCode::Delete => input::delete_at(input_buffer, *input_cursor),
+        KeyCode::Left => input::move_left(input_cursor),
+        KeyCode::Right => input::move_right(input_buffer, input_cursor),
+        KeyCode::Home => *input_cursor = 0,
+        KeyCode::End => input::move_end(input_buffer, input_cursor),
+        KeyCode::Enter if allow_empty || !input_buffer.is_empty() => {
+            return Some(PromptAction::Submit(input_buffer.clone()));
+        }
+        KeyCode::Esc => return Some(PromptAction::Cancel),
+        _ => {}
+    }
+    None
+}
+
+/// The replace prompt's question, which shows whether case matters.
+fn replace_prompt(find: Option<&str>, match_case: bool) -> String {
+    let case = if match_case {
+        "match case"
+    } else {
+        "ignore case"
+    };
+    match find {
+        None => format!("Replace ({}):", case),
+        Some(find) => format!("Replace '{}' with ({}):", find, case),
+    }
+}
+
+/// Usernames become file names in the data directory, so only letters,
+/// digits, `-` and `_` are allowed: nothing that could leave the directory
+/// or mean something to the filesystem.
+pub fn validate_username(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("The username can't be empty".to_string());
+    }
+    match name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '_'))
+    {
+        Some(c) => Err(format!(
+            "Usernames can only use letters, digits, '-' and '_', not '{}'",
+            c
+        )),
+        None => Ok(()),
+    }
+}
+
+pub fn folder_items(
+    metadata: &Value,
+    journal: &JournalSettings,
+    today: &str,
+) -> Vec<(String, String)> {
+    let mut folders: Vec<String> = metadata["folders"]
+        .as_object()
+        .map(|obj| {
+            obj.iter()
+                .filter(|(folder, files)| {
+                    *folder == today || !only_hidden_pages(folder, files, journal)
+                })
+                .map(|(folder, _)| folder.clone())
+                .collect()
+        })
+        .unwrap_or_default();
+    folders.extend(journal.chapters.iter().cloned());
+    // Most recent day first; names that aren't dates, such as topic
+    // chapters, go last, by name.
+    folders.sort_by_cached_key(|folder| {
+        let date = parse_folder_date(folder);
+        (date.is_none(), std::cmp::Reverse(date), folder.clone())
+    });
+
+    let mut display_items: Vec<(String, String)> = folders
+        .into_iter()
+        .map(|folder| {
+            let empty = file_items(metadata, &folder, "", journal).is_empty()
+                && journal.sub_chapters_of(&folder).is_empty();
+            let badge = if empty { EMPTY_BADGE } else { "" };
+            (folder, badge.to_string())
+        })
+        .collect();
+    if !today.is_empty() {
+        display_items.push((NEW_CHAPTER_ITEM.to_string(), String::new()));
+    }
+    display_items
+}
+
+/// The chapter list: `folder_items` without the sub-chapters, which are
+/// listed inside their topic instead.
+pub fn top_folder_items(
+    metadata: &Value,
+    journal: &JournalSettings,
+    today: &str,
+) -> Vec<(String, String)> {
+    let mut items = folder_items(metadata, journal, today);
+    items.retain(|(folder, _)| journal.parent_of(folder).is_none());
+    items
+}
+
+/// Folders holding nothing but the settings page, deleted pages and pages
+/// filed under a topic chapter are not shown.
+fn only_hidden_pages(folder: &str, files: &Value, journal: &JournalSettings) -> bool {
+    files.as_object().is_some_and(|files| {
+        !files.is_empty()
+            && files.keys().all(|name| {
+                is_reserved_page(name)
+                    || journal.is_deleted(folder, name)
+                    || journal.listed_in(folder, name) != folder
+            })
+    })
+}
+
+/// Whether a row of the page list is a page.
+fn is_page_item(file: &str, badge: &str) -> bool {
+    file != ADD_PAGE_ITEM && file != NEW_CHAPTER_ITEM && badge != SUB_CHAPTER_BADGE
+}
+
+pub fn file_items(
+    metadata: &Value,
+    folder: &str,
+    today: &str,
+    journal: &JournalSettings,
+) -> Vec<(String, String)> {
+    // Each page with the day and time it was created, to order by.
+    let mut pages = Vec::new();
+    if journal.is_chapter(folder) {
+        // A topic's pages come from the days they were added on, so the day
+        // is shown with the time.
+        for page in journal.filed_in(folder) {
+            if journal.is_deleted(folder, &page.file) {
+                continue;
+            }
+            let created_at = metadata["folders"][&page.folder][&page.file]["created_at"]
+                .as_str()
+                .unwrap_or("");
+            pages.push((
+                page.file.clone(),
+                format!("{} {}", page.folder, created_at),
+                parse_folder_date(&page.folder),
+                created_at.to_string(),
+            ));
+        }
+    } else if let Some(files_obj) = metadata["folders"][folder].as_object() {
+        for (filename, file_data) in files_obj {
+            if is_reserved_page(filename)
+                || journal.is_deleted(folder, filename)
+                || journal.listed_in(folder, filename) != folder
+            {
+                continue;
+            }
+            let created_at = file_data["created_at"].as_str().unwrap_or("").to_string();
+            pages.push((filename.clone(), created_at.clone(), None, created_at));
+        }
+    }
+    // Pages moved by hand keep their place; the rest follow, oldest first.
+    pages.sort_by(|(a, _, a_day, a_time), (b, _, b_day, b_time)| {
+        let place = |file| journal.order_of(folder, file).unwrap_or(usize::MAX);
+        (place(a), a_day, a_time).cmp(&(place(b), b_day, b_time))
+    });
+    let mut display_items: Vec<(String, String)> = pages
+        .into_iter()
+        .map(|(file, badge, _, _)| (file, badge))
+        .collect();
+
+    // `today` is empty when the list is only read, as by search and export.
+    if !today.is_empty() && (folder == today || journal.is_chapter(folder)) {
+        display_items.push((ADD_PAGE_ITEM.to_string(), String::new()));
+    }
+    display_items
+}
+
+/// The chapter already listing a page named `name` that a new page in
+/// `folder` would clash with: `folder` itself, or whichever chapter lists
+/// the page of that name stored in `today`, where new pages go.
+pub fn page_name_owner(
+    metadata: &Value,
+    journal: &JournalSettings,
+    folder: &str,
+    name: &str,
+    today: &str,
+) -> Option<String> {
+    let in_folder = file_items(metadata, folder, "", journal)
+        .iter()
+        .any(|(file, _)| file == name);
+    if in_folder {
+        return Some(folder.to_string());
+    }
+    let owner = journal.listed_in(today, name);
+    (metadata["folders"][today].get(name).is_some() && !journal.is_deleted(owner, name))
+        .then(|| owner.to_string())
+}
+
+/// Every page of the journal, newest day first and latest page first within
+/// a day, with the time it was created.
+pub fn all_pages(metadata: &Value, journal: &JournalSettings) -> Vec<(PageRef, String)> {
+    let mut pages = Vec::new();
+    for (folder, _) in folder_items(metadata, journal, "") {
+        let mut files = file_items(metadata, &folder, "", journal);
+        if journal.is_chapter(&folder) {
+            // Filed in the order they were added.
+            files.reverse();
+        } else {
+            // created_at is HH:MM:SS, so it sorts as text.
+            files.sort_by(|(_, a), (_, b)| b.cmp(a));
+        }
+        for (file, created_at) in files {
+            let folder = folder.clone();
+            pages.push((PageRef { folder, file }, created_at));
+        }
+    }
+    pages
+}
+
+pub fn today_str() -> String {
+    folder_name(Local::now().date_naive())
+}
+
+/// Folders are named after the day their pages were written, as dd/mm/yyyy.
+pub fn folder_name(date: NaiveDate) -> String {
+    date.format("%d/%m/%Y").to_string()
+}
+
+pub fn parse_folder_date(folder: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(folder, "%d/%m/%Y").ok()
+}
+
+/// `date_format` and `datetime_format` from the config, set at startup.
+static DISPLAY_FORMATS: OnceLock<(String, String)> = OnceLock::new();
+
+pub fn set_display_formats(date_format: &str, datetime_format: &str) {
+    DISPLAY_FORMATS
+        .set((date_format.to_string(), datetime_format.to_string()))
+        .ok();
+}
+
+/// How a folder is shown: a day in `date_format`, a topic chapter by its
+/// name. Folder keys themselves are always clog_rs's dd/mm/yyyy, so
+/// changing the format never changes what is stored.
+pub fn folder_label(folder: &str) -> String {
+    match (parse_folder_date(folder), DISPLAY_FORMATS.get()) {
+        (Some(date), Some((date_format, _))) => date.format(date_format).to_string(),
+        _ => folder.to_string(),
+    }
+}
+
+/// How long ago the day `folder` is named after was, within the last
+/// week: `Today`, `Yesterday`, `3 days ago`. Older days go by their date.
+pub fn relative_day(folder: &str, today: NaiveDate) -> Option<String> {
+    let days = (today - parse_folder_date(folder)?).num_days();
+    match days {
+        0 => Some(TODAY_BADGE.to_string()),
+        1 => Some(YESTERDAY_BADGE.to_string()),
+        2..=6 => Some(format!("{} days ago", days)),
+        _ => None,
+    }
+}
+
+pub fn time_label(time: NaiveDateTime) -> String {
+    let datetime_format = DISPLAY_FORMATS
+        .get()
+        .map_or(TIMESTAMP_FORMAT, |(_, datetime_format)| datetime_format);
+    time.format(datetime_format).to_string()
+}
+
+/// A time stored as `TIMESTAMP_FORMAT` shown in `datetime_format`.
+pub fn timestamp_label(stored: &str) -> String {
+    NaiveDateTime::parse_from_str(stored, TIMESTAMP_FORMAT)
+        .map_or_else(|_| stored.to_string(), time_label)
+}
+
+/// `folder/page` as lists and messages show it.
+pub fn page_label(page: &PageRef) -> String {
+    format!("{}/{}", folder_label(&page.folder), page.file)
+}
+
+/// Whether `name` reads as a day in `date_format`, which a topic chapter
+/// can't be named after any more than after a dd/mm/yyyy folder.
+pub fn is_day_name(name: &str) -> bool {
+    parse_folder_date(name).is_some()
+        || DISPLAY_FORMATS
+            .get()
+            .is_some_and(|(date_format, _)| NaiveDate::parse_from_str(name, date_format).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(app: &mut App, code: KeyCode) -> Option<SideEffect> {
+        app.handle_event(Event::Key(KeyEvent::new(code, KeyModifiers::NONE)))
+    }
+
+    #[test]
+    fn unlocking_a_journal_from_the_user_list() {
+        let data = tempfile::tempdir().unwrap();
+        let config = tempfile::tempdir().unwrap();
+        let mut app = App::with_dirs(data.path().to_path_buf(), config.path().to_path_buf());
+        app.set_items(vec![
+            ("alice.clog".to_string(), String::new()),
+            (ADD_USER_ITEM.to_string(), String::new()),
+        ]);
+
+        assert_eq!(press(&mut app, KeyCode::Enter), None);
+        match &app.state {
+            AppState::InputPrompt(_, next) => {
+                assert_eq!(**next, AppState::EnterPassword("alice.clog".to_string()))
+            }
+            state => panic!("expected the password prompt, got {:?}", state),
+        }
+
+        for c in "pw".chars() {
+            assert_eq!(press(&mut app, KeyCode::Char(c)), None);
+        }
+        assert_eq!(
+            press(&mut app, KeyCode::Enter),
+            Some(SideEffect::Unlock(
+                "alice.clog".to_string(),
+                "pw".to_string()
+            ))
+        );
+    }
+
+    /// Where the highlight of a list of `len` rows ends up after typing
+    /// `keys` from row `from`.
+    fn menu_motion(keys: &str, from: usize, len: usize) -> usize {
+        let mut selected = from;
+        let mut pending = PendingKeys::default();
+        for c in keys.chars() {
+            handle_menu_input(
+                KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE),
+                &mut selected,
+                len,
+                false,
+                &mut pending,
+                &KeyMap::default(),
+            );
+        }
+        selected
+    }
+
+    #[test]
+    fn counts_move_that_many_rows_and_stop_at_the_ends() {
+        assert_eq!(menu_motion("3j", 0, 10), 3);
+        assert_eq!(menu_motion("12j", 0, 20), 12);
+        assert_eq!(menu_motion("5j", 7, 10), 9);
+        assert_eq!(menu_motion("5k", 2, 10), 0);
+        // Without a count j wraps around instead.
+        assert_eq!(menu_motion("j", 9, 10), 0);
+    }
+
+    #[test]
+    fn counts_are_capped() {
+        let mut pending = PendingKeys::default();
+        let mut selected = 0;
+        for c in "123456".chars() {
+            handle_menu_input(
+                KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE),
+                &mut selected,
+                100_000,
+                false,
+                &mut pending,
+                &KeyMap::default(),
+            );
+        }
+        assert_eq!(pending.count, MAX_COUNT);
+    }
+
+    #[test]
+    fn a_leading_zero_is_not_a_count() {
+        assert_eq!(menu_motion("0j", 0, 10), 1);
+        assert_eq!(menu_motion("10j", 0, 20), 10);
+    }
+
+    #[test]
+    fn gg_and_g_go_to_the_first_and_last_rows() {
+        assert_eq!(menu_motion("gg", 7, 10), 0);
+        assert_eq!(menu_motion("G", 2, 10), 9);
+        // A lone g waits for the second.
+        assert_eq!(menu_motion("g", 7, 10), 7);
+    }
+
+    #[test]
+    fn pending_keys_expire() {
+        let mut pending = PendingKeys {
+            count: 5,
+            g: true,
+            since: Instant::now().checked_sub(PENDING_TIMEOUT * 2),
+        };
+        let mut selected = 3;
+        handle_menu_input(
+            KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE),
+            &mut selected,
+            10,
+            false,
+            &mut pending,
+            &KeyMap::default(),
+        );
+        assert_eq!(selected, 4);
+    }
+
+    #[test]
+    fn usernames_cannot_leave_the_data_directory() {
+        for name in ["../../etc/evil", "a/b", "/etc/evil", "..", ""] {
+            assert!(validate_username(name).is_err(), "{:?} was accepted", name);
+        }
+    }
+
+    #[test]
+    fn plain_usernames_are_accepted() {
+        assert_eq!(validate_username("alice"), Ok(()));
+        assert_eq!(validate_username("alice_2-work"), Ok(()));
+    }
+}
@@ -0,0 +1,144 @@
+//! Command-line options, read before the terminal is taken over.
+
+use crate::error::AppError;
+
+pub const USAGE: &str = "Usage: clog-tui [--user NAME] [--readonly]
+       clog-tui add --user NAME --name PAGE < content
+
+Options:
+  -u, --user NAME   Ask for the password of NAME's journal straight away
+  -r, --readonly    Browse without adding, editing, renaming or deleting
+  -h, --help        Show this help
+
+Commands:
+  add               Add standard input as a page of today without opening
+                    the interface. The password is read from CLOG_PASSWORD,
+                    or asked for on the terminal.
+  -n, --name PAGE   Name of the page to add";
+
+#[derive(Debug, Default, PartialEq)]
+pub enum Command {
+    /// Open the interface.
+    #[default]
+    Tui,
+    /// Add standard input as a page with this name, headless.
+    Add(String),
+}
+
+#[derive(Debug, Default)]
+pub struct Options {
+    /// Journal to unlock on start instead of picking it from the list.
+    pub user: Option<String>,
+    pub readonly: bool,
+    pub help: bool,
+    pub command: Command,
+}
+
+/// Parses the arguments after the program name. `--user` and `--name` take
+/// their value as the next argument or after `=`. A first argument of `add`
+/// picks that command, which needs both.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, AppError> {
+    let mut options = Options::default();
+    let mut args = args.into_iter().peekable();
+    let add = args.next_if(|arg| arg == "add").is_some();
+    let mut name = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-u" | "--user" => {
+                let user = args
+                    .next()
+                    .ok_or_else(|| AppError::Usage(format!("{} needs a username", arg)))?;
+                options.user = Some(user);
+            }
+            "-n" | "--name" if add => {
+                let page = args
+                    .next()
+                    .ok_or_else(|| AppError::Usage(format!("{} needs a page name", arg)))?;
+                name = Some(page);
+            }
+            "-r" | "--readonly" => options.readonly = true,
+            "-h" | "--help" => options.help = true,
+            _ => match (arg.strip_prefix("--user="), arg.strip_prefix("--name=")) {
+                (Some(user), _) => options.user = Some(user.to_string()),
+                (_, Some(page)) if add => name = Some(page.to_string()),
+                _ => return Err(AppError::Usage(format!("unknown option '{}'", arg))),
+            },
+        }
+    }
+    if add && !options.help {
+        if options.readonly {
+            return Err(AppError::Usage("add can't be --readonly".to_string()));
+        }
+        if options.user.is_none() {
+            return Err(AppError::Usage("add needs --user".to_string()));
+        }
+        let name = name
+            .filter(|name| !name.trim().is_empty())
+            .ok_or_else(|| AppError::Usage("add needs --name".to_string()))?;
+        options.command = Command::Add(name);
+    }
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Options, AppError> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn usage_error(args: &[&str]) -> String {
+        match parse_args(args) {
+            Err(AppError::Usage(message)) => message,
+            Err(e) => panic!("{:?} gave {}, not a usage error", args, e),
+            Ok(_) => panic!("{:?} was accepted", args),
+        }
+    }
+
+    #[test]
+    fn add_takes_a_user_and_a_page_name() {
+        for args in [
+            &["add", "-u", "alice", "-n", "Morning"][..],
+            &["add", "--user=alice", "--name=Morning"],
+            &["add", "--name", "Morning", "--user", "alice"],
+        ] {
+            let options = parse_args(args).unwrap();
+            assert_eq!(options.user.as_deref(), Some("alice"));
+            assert_eq!(options.command, Command::Add("Morning".to_string()));
+        }
+    }
+
+    #[test]
+    fn options_without_a_command_open_the_interface() {
+        let options = parse_args(&["--user=alice", "-r"]).unwrap();
+        assert_eq!(options.user.as_deref(), Some("alice"));
+        assert!(options.readonly);
+        assert_eq!(options.command, Command::Tui);
+    }
+
+    #[test]
+    fn usage_errors() {
+        assert_eq!(usage_error(&["-u"]), "-u needs a username");
+        assert_eq!(
+            usage_error(&["add", "-u", "alice", "--name"]),
+            "--name needs a page name"
+        );
+        assert_eq!(usage_error(&["--bogus"]), "unknown option '--bogus'");
+        // Page names only mean something to add.
+        assert_eq!(
+            usage_error(&["--name=Morning"]),
+            "unknown option '--name=Morning'"
+        );
+        assert_eq!(
+            usage_error(&["add", "-u", "alice", "-n", "Morning", "-r"]),
+            "add can't be --readonly"
+        );
+        assert_eq!(usage_error(&["add", "-n", "Morning"]), "add needs --user");
+        assert_eq!(usage_error(&["add", "-u", "alice"]), "add needs --name");
+        assert_eq!(
+            usage_error(&["add", "-u", "alice", "-n", "  "]),
+            "add needs --name"
+        );
+    }
+}
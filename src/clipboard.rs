@@ -0,0 +1,49 @@
+//! Copying page text to the system clipboard. A clipboard tool on `PATH` is
+//! preferred because it reports failures and keeps the text after the app
+//! exits; otherwise the terminal is asked to take it with an OSC 52 escape.
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use crate::error::AppError;
+
+/// Clipboard tools tried in order, with the arguments that make them read
+/// the text from stdin.
+const TOOLS: &[(&str, &[&str])] = &[
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("clip.exe", &[]),
+];
+
+/// Copies `text` and returns what it was copied with, for the confirmation.
+pub fn copy(text: &str) -> Result<&'static str, AppError> {
+    let Some((tool, args)) = TOOLS.iter().find(|(tool, _)| crate::on_path(tool)) else {
+        copy_with_osc52(text)?;
+        return Ok("the terminal");
+    };
+    let mut child = Command::new(tool)
+        .args(*args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    if !child.wait()?.success() {
+        return Err(AppError::Io(io::Error::other(format!(
+            "{} could not set the clipboard",
+            tool
+        ))));
+    }
+    Ok(tool)
+}
+
+fn copy_with_osc52(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    stdout.flush()
+}
@@ -0,0 +1,478 @@
+//! The only place that talks to `clog_rs`. Everything above this layer works
+//! with `Path`s and `Result`s and never sees the on-disk page encoding.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, rand_core::RngCore};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use clog_rs::{add_file, add_new_user, get_file_content, get_json_metadata, update_file_content};
+use flate2::{Compression, read::DeflateDecoder, write::DeflateEncoder};
+use serde_json::Value;
+use std::cell::Cell;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Write};
+use std::panic::{self, UnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use zeroize::Zeroize;
+
+use crate::error::AppError;
+
+/// Prefix of a page stored compressed: version 1 is raw deflate, base64
+/// encoded so it survives as page text. Pages without it are plain text.
+const COMPRESSED_MARKER: &str = "\u{1}clogz1\u{1}";
+
+/// Start of a shared page file, followed by the format version.
+const SHARED_PAGE_MAGIC: &[u8] = b"CLOGPAGE";
+/// Start of an inline editor draft, sealed the same way as a shared page.
+const DRAFT_MAGIC: &[u8] = b"CLOGDRFT";
+const SEALED_VERSION: u8 = 1;
+const SEALED_SALT_LEN: usize = 16;
+const SEALED_NONCE_LEN: usize = 12;
+
+/// Copies of a journal kept before each write; set from the config at
+/// startup.
+static BACKUP_COUNT: AtomicUsize = AtomicUsize::new(1);
+
+thread_local! {
+    static CATCHING_PANIC: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Whether a panic happening right now will be caught by this layer, in which
+/// case the panic hook must leave the terminal alone.
+pub fn catching_panic() -> bool {
+    CATCHING_PANIC.get()
+}
+
+/// clog_rs reports its failures, such as a wrong password, by panicking.
+/// Every call into it goes through here, so the panic stops at this layer
+/// and the rest of the app only sees a `ClogError`.
+fn catch<T>(f: impl FnOnce() -> T + UnwindSafe) -> std::thread::Result<T> {
+    CATCHING_PANIC.set(true);
+    let result = panic::catch_unwind(f);
+    CATCHING_PANIC.set(false);
+    result
+}
+
+fn path_str(file_path: &Path) -> Result<&str, ClogError> {
+    file_path
+        .to_str()
+        .ok_or_else(|| ClogError::Corrupt(format!("invalid path: {}", file_path.display())))
+}
+
+/// Why a journal couldn't be read or written.
+#[derive(Debug)]
+pub enum ClogError {
+    Io(io::Error),
+    /// clog_rs couldn't decrypt the file, which for a file that is otherwise
+    /// intact means the password is wrong.
+    WrongPassword,
+    Corrupt(String),
+    /// The metadata decrypted but isn't the JSON clog_rs writes.
+    Serde(serde_json::Error),
+}
+
+impl fmt::Display for ClogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClogError::Io(e) => write!(f, "I/O error: {}", e),
+            ClogError::WrongPassword => write!(f, "wrong password or unreadable file"),
+            ClogError::Corrupt(msg) => write!(f, "journal is damaged: {}", msg),
+            ClogError::Serde(e) => write!(f, "journal is damaged: bad metadata: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ClogError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClogError::Io(e) => Some(e),
+            ClogError::Serde(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ClogError {
+    fn from(e: io::Error) -> Self {
+        ClogError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for ClogError {
+    fn from(e: serde_json::Error) -> Self {
+        ClogError::Serde(e)
+    }
+}
+
+impl From<ClogError> for AppError {
+    fn from(e: ClogError) -> Self {
+        match e {
+            ClogError::Io(e) => AppError::Io(e),
+            ClogError::WrongPassword => AppError::Decrypt(e.to_string()),
+            ClogError::Corrupt(_) | ClogError::Serde(_) => AppError::Parse(e.to_string()),
+        }
+    }
+}
+
+/// `get_json_metadata` without the panic: the file is checked first, so
+/// only a failed decryption is left to unwind and it is reported as a wrong
+/// password.
+pub fn try_get_json_metadata(password: &str, file_path: &Path) -> Result<String, ClogError> {
+    let len = fs::metadata(file_path)?.len();
+    if len == 0 {
+        return Err(ClogError::Corrupt("the file is empty".to_string()));
+    }
+    let path_str = path_str(file_path)?;
+    let metadata_str =
+        catch(|| get_json_metadata(password, path_str)).map_err(|_| ClogError::WrongPassword)?;
+    let metadata: Value = serde_json::from_str(&metadata_str)?;
+    if !metadata["folders"].is_object() {
+        return Err(ClogError::Corrupt("no folders in metadata".to_string()));
+    }
+    Ok(metadata_str)
+}
+
+pub fn set_backup_count(count: usize) {
+    BACKUP_COUNT.store(count, Ordering::Relaxed);
+}
+
+/// Copies the journal to `<user>.clog.bak` before clog_rs writes to it. With
+/// more than one backup kept, older copies move down to `.bak.1`, `.bak.2`
+/// and so on, and the oldest is dropped. A failed copy stops the write, so
+/// the journal is never changed without a good copy beside it.
+fn back_up(file_path: &Path) -> Result<(), ClogError> {
+    let count = BACKUP_COUNT.load(Ordering::Relaxed);
+    if count == 0 || !file_path.exists() {
+        return Ok(());
+    }
+    let newest = backup_path(file_path);
+    let numbered = |n: usize| {
+        if n == 0 {
+            newest.clone()
+        } else {
+            let mut path = newest.as_os_str().to_owned();
+            path.push(format!(".{}", n));
+            PathBuf::from(path)
+        }
+    };
+    let rotated = (1..count).rev().try_for_each(|n| {
+        let older = numbered(n - 1);
+        if older.exists() {
+            fs::rename(older, numbered(n))?;
+        }
+        Ok(())
+    });
+    rotated
+        .and_then(|()| fs::copy(file_path, &newest).map(drop))
+        .map_err(|e: io::Error| {
+            ClogError::Io(io::Error::new(
+                e.kind(),
+                format!("couldn't back up the journal, nothing was written: {}", e),
+            ))
+        })
+}
+
+/// A page read or write that clog_rs gave up on with the password already
+/// accepted: the file itself is damaged.
+fn damaged_page(action: &str, file: &str) -> ClogError {
+    ClogError::Corrupt(format!("couldn't {} page '{}'", action, file))
+}
+
+pub fn read_metadata(password: &str, file_path: &Path) -> Result<Value, ClogError> {
+    let metadata_str = try_get_json_metadata(password, file_path)?;
+    Ok(serde_json::from_str(&metadata_str)?)
+}
+
+pub fn create_journal(password: &str, file_path: &Path) -> Result<(), ClogError> {
+    let path_str = path_str(file_path)?;
+    catch(|| add_new_user(password, path_str)).map_err(|_| {
+        ClogError::Io(io::Error::other(format!(
+            "couldn't write {}",
+            file_path.display()
+        )))
+    })
+}
+
+pub fn read_page(
+    password: &str,
+    file_path: &Path,
+    file: &str,
+    folder: &str,
+) -> Result<String, ClogError> {
+    let path_str = path_str(file_path)?;
+    let stored = catch(|| get_file_content(password, path_str, file, folder))
+        .map_err(|_| damaged_page("read", file))?;
+    decode(&stored)
+}
+
+/// Adds a page to today's folder, which is the only folder clog_rs can add to.
+pub fn add_page(
+    password: &str,
+    file_path: &Path,
+    file: &str,
+    content: &str,
+    compress: bool,
+) -> Result<(), ClogError> {
+    let path_str = path_str(file_path)?;
+    let stored = encode(content, compress)?;
+    back_up(file_path)?;
+    catch(|| add_file(password, path_str, file, &stored)).map_err(|_| damaged_page("add", file))
+}
+
+pub fn update_page(
+    password: &str,
+    file_path: &Path,
+    file: &str,
+    folder: &str,
+    content: &str,
+    compress: bool,
+) -> Result<(), ClogError> {
+    let path_str = path_str(file_path)?;
+    let stored = encode(content, compress)?;
+    back_up(file_path)?;
+    catch(|| update_file_content(password, path_str, file, folder, &stored))
+        .map_err(|_| damaged_page("update", file))
+}
+
+/// Adds `bytes` as a page of today's folder. clog_rs only stores text, so
+/// they are kept base64-encoded.
+pub fn add_binary_page(
+    password: &str,
+    file_path: &Path,
+    file: &str,
+    bytes: &[u8],
+    compress: bool,
+) -> Result<(), ClogError> {
+    let mut encoded = STANDARD.encode(bytes);
+    let added = add_page(password, file_path, file, &encoded, compress);
+    encoded.zeroize();
+    added
+}
+
+/// Reads a page written by `add_binary_page`.
+pub fn read_binary_page(
+    password: &str,
+    file_path: &Path,
+    file: &str,
+    folder: &str,
+) -> Result<Vec<u8>, ClogError> {
+    let mut encoded = read_page(password, file_path, file, folder)?;
+    let bytes = STANDARD
+        .decode(&encoded)
+        .map_err(|_| damaged_page("decode", file));
+    encoded.zeroize();
+    bytes
+}
+
+/// The copy of a journal kept next to it as `<user>.clog.bak`.
+pub fn backup_path(file_path: &Path) -> PathBuf {
+    let mut path = file_path.as_os_str().to_owned();
+    path.push(".bak");
+    PathBuf::from(path)
+}
+
+/// Puts the backup in place of a damaged journal. The damaged file is moved
+/// to `<user>.clog.corrupt` rather than deleted, and its path is returned.
+pub fn restore_backup(file_path: &Path) -> Result<PathBuf, ClogError> {
+    let mut corrupt = file_path.as_os_str().to_owned();
+    corrupt.push(".corrupt");
+    let corrupt = PathBuf::from(corrupt);
+    fs::rename(file_path, &corrupt)?;
+    if let Err(e) = fs::copy(backup_path(file_path), file_path) {
+        fs::rename(&corrupt, file_path).ok();
+        return Err(e.into());
+    }
+    Ok(corrupt)
+}
+
+fn encode(content: &str, compress: bool) -> Result<String, ClogError> {
+    if !compress {
+        return Ok(content.to_string());
+    }
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(content.as_bytes())?;
+    Ok(format!(
+        "{}{}",
+        COMPRESSED_MARKER,
+        STANDARD.encode(encoder.finish()?)
+    ))
+}
+
+fn decode(stored: &str) -> Result<String, ClogError> {
+    let Some(encoded) = stored.strip_prefix(COMPRESSED_MARKER) else {
+        return Ok(stored.to_string());
+    };
+    let damaged =
+        |e: &dyn fmt::Display| ClogError::Corrupt(format!("damaged compressed page: {}", e));
+    let compressed = STANDARD.decode(encoded).map_err(|e| damaged(&e))?;
+    let mut content = String::new();
+    DeflateDecoder::new(compressed.as_slice())
+        .read_to_string(&mut content)
+        .map_err(|e| damaged(&e))?;
+    Ok(content)
+}
+
+/// Derives the AES-256 key of a sealed file from its passphrase with
+/// Argon2id, default parameters.
+fn sealed_cipher(passphrase: &str, salt: &[u8]) -> Result<Aes256Gcm, ClogError> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| ClogError::Corrupt(format!("bad sealed file key: {}", e)))?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    key.zeroize();
+    Ok(cipher)
+}
+
+/// Encrypts `plaintext` into the layout shared pages and drafts use:
+///
+/// | bytes | content                                  |
+/// |-------|------------------------------------------|
+/// | 8     | `magic`                                  |
+/// | 1     | format version, 1                        |
+/// | 16    | random Argon2id salt                     |
+/// | 12    | random AES-256-GCM nonce                 |
+/// | rest  | ciphertext and 16-byte tag               |
+fn seal(passphrase: &str, magic: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, ClogError> {
+    let mut salt = [0u8; SEALED_SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = sealed_cipher(passphrase, &salt)?
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| ClogError::Corrupt("the page could not be encrypted".to_string()))?;
+    let mut sealed =
+        Vec::with_capacity(magic.len() + 1 + salt.len() + nonce.len() + ciphertext.len());
+    sealed.extend_from_slice(magic);
+    sealed.push(SEALED_VERSION);
+    sealed.extend_from_slice(&salt);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Decrypts what `seal` made with the same `magic`. `kind` names the file
+/// in errors. A passphrase that doesn't match fails the GCM tag check and
+/// comes back as `WrongPassword`.
+fn unseal(passphrase: &str, magic: &[u8], bytes: &[u8], kind: &str) -> Result<Vec<u8>, ClogError> {
+    let not_sealed = || ClogError::Corrupt(format!("not a {}", kind));
+    let (&version, rest) = bytes
+        .strip_prefix(magic)
+        .and_then(<[u8]>::split_first)
+        .ok_or_else(not_sealed)?;
+    if version != SEALED_VERSION {
+        return Err(ClogError::Corrupt(format!(
+            "unsupported {} version {}",
+            kind, version
+        )));
+    }
+    if rest.len() <= SEALED_SALT_LEN + SEALED_NONCE_LEN {
+        return Err(not_sealed());
+    }
+    let (salt, rest) = rest.split_at(SEALED_SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(SEALED_NONCE_LEN);
+    sealed_cipher(passphrase, salt)?
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| ClogError::WrongPassword)
+}
+
+/// Writes one page as a standalone `.clogpage` file that can be opened
+/// without the journal, only with `passphrase`. It is sealed as `seal`
+/// describes, starting with `CLOGPAGE`, and the plaintext is
+/// `{"name": ..., "content": ...}` as JSON. An existing file is never
+/// overwritten.
+pub fn write_shared_page(
+    passphrase: &str,
+    path: &Path,
+    name: &str,
+    content: &str,
+) -> Result<(), ClogError> {
+    let mut plaintext = serde_json::to_vec(&serde_json::json!({
+        "name": name,
+        "content": content,
+    }))?;
+    let sealed = seal(passphrase, SHARED_PAGE_MAGIC, &plaintext);
+    plaintext.zeroize();
+    let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+    file.write_all(&sealed?)?;
+    Ok(())
+}
+
+/// Opens a file made by `write_shared_page` and returns the page name and
+/// content.
+pub fn read_shared_page(passphrase: &str, path: &Path) -> Result<(String, String), ClogError> {
+    let bytes = fs::read(path)?;
+    let mut plaintext = unseal(passphrase, SHARED_PAGE_MAGIC, &bytes, "shared page")?;
+    let page: Result<Value, _> = serde_json::from_slice(&plaintext);
+    plaintext.zeroize();
+    let page = page?;
+    match (page["name"].as_str(), page["content"].as_str()) {
+        (Some(name), Some(content)) if !name.is_empty() => {
+            Ok((name.to_string(), content.to_string()))
+        }
+        _ => Err(ClogError::Corrupt("not a shared page".to_string())),
+    }
+}
+
+/// Replaces the draft at `path` with `draft`, sealed with the journal's
+/// password. The new file is written beside the old one and renamed over
+/// it, so a crash part way leaves the previous draft.
+pub fn write_draft(password: &str, path: &Path, draft: &Value) -> Result<(), ClogError> {
+    let mut plaintext = serde_json::to_vec(draft)?;
+    let sealed = seal(password, DRAFT_MAGIC, &plaintext);
+    plaintext.zeroize();
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut file = tempfile::NamedTempFile::new_in(dir)?;
+    file.write_all(&sealed?)?;
+    file.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Opens a draft made by `write_draft`.
+pub fn read_draft(password: &str, path: &Path) -> Result<Value, ClogError> {
+    let bytes = fs::read(path)?;
+    let mut plaintext = unseal(password, DRAFT_MAGIC, &bytes, "draft")?;
+    let draft = serde_json::from_slice(&plaintext);
+    plaintext.zeroize();
+    Ok(draft?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_shared_page_opens_with_its_passphrase() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("page.clogpage");
+        write_shared_page("secret", &path, "today", "first line\nsecond").unwrap();
+        let (name, content) = read_shared_page("secret", &path).unwrap();
+        assert_eq!(name, "today");
+        assert_eq!(content, "first line\nsecond");
+    }
+
+    #[test]
+    fn a_wrong_passphrase_is_reported_as_such() {
+        let sealed = seal("secret", SHARED_PAGE_MAGIC, b"plaintext").unwrap();
+        assert!(matches!(
+            unseal("guess", SHARED_PAGE_MAGIC, &sealed, "shared page"),
+            Err(ClogError::WrongPassword)
+        ));
+    }
+
+    #[test]
+    fn damaged_files_are_corrupt() {
+        let sealed = seal("secret", SHARED_PAGE_MAGIC, b"plaintext").unwrap();
+        let truncated = &sealed[..SHARED_PAGE_MAGIC.len() + 1 + SEALED_SALT_LEN];
+        let mut bad_magic = sealed.clone();
+        bad_magic[0] = b'X';
+        for bytes in [truncated, &bad_magic, &sealed[..4]] {
+            assert!(matches!(
+                unseal("secret", SHARED_PAGE_MAGIC, bytes, "shared page"),
+                Err(ClogError::Corrupt(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,488 @@
+//! User configuration read from `config.toml` in the platform config directory.
+//! Every field is optional; anything missing falls back to the defaults below.
+
+use chrono::format::{Item, StrftimeItems};
+use crossterm::event::KeyCode;
+use ratatui::style::Color;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::error::AppError;
+
+pub const CONFIG_FILE: &str = "config.toml";
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Compress page content before it is encrypted. Pages written without
+    /// compression keep loading either way.
+    pub compress: bool,
+    /// Word list, one word per line, used by the viewer's spellcheck. When
+    /// unset the system dictionary is used if there is one.
+    pub dictionary: Option<PathBuf>,
+    /// Edit pages inside the TUI instead of in an external editor.
+    pub inline_editor: bool,
+    /// Whether the inline editor only types, or has Vim-like normal and
+    /// insert modes.
+    pub editor_mode: EditorMode,
+    /// What happens to the end of a page when it is saved from the editor.
+    pub trailing_newline: NewlinePolicy,
+    /// Whether changes made in the external editor are saved straight away
+    /// or after a question.
+    pub save_mode: SaveMode,
+    /// Whether adding a page shows the writing prompt of the day, and
+    /// whether it also starts the page.
+    pub writing_prompt: WritingPrompt,
+    /// File of writing prompts, one per line, used instead of the bundled
+    /// ones.
+    pub writing_prompts: Option<PathBuf>,
+    /// Initial order of the user list; `s` cycles it while the app runs.
+    pub user_sort: UserSort,
+    /// Minutes without a key press before an open journal is locked; 0
+    /// turns auto-lock off.
+    pub auto_lock_minutes: u64,
+    /// Click and scroll lists with the mouse. Capturing the mouse stops the
+    /// terminal from selecting text, so it can be turned off.
+    pub mouse: bool,
+    /// Let pages of past days be edited and deleted, not only viewed.
+    /// clog_rs can only add pages to today's folder, so new pages still go
+    /// there.
+    pub allow_backdating: bool,
+    /// Start on the password prompt of the journal unlocked last and, once
+    /// it opens, on the page last opened for editing in it. The page's name
+    /// is remembered in plain text in the config directory.
+    pub reopen_last_page: bool,
+    /// Screen to land on. `today` and `timeline` start on the password
+    /// prompt of `default_user` and go there once it is unlocked.
+    pub startup: Startup,
+    /// Journal opened at startup when `startup` isn't the user list.
+    pub default_user: Option<String>,
+    /// Copies of a journal kept before each write: `<user>.clog.bak`, then
+    /// `.bak.1`, `.bak.2`... for older ones. 0 turns backups off.
+    pub backups: usize,
+    /// Days a deleted page stays in a journal's trash before it is purged
+    /// on unlocking; 0 keeps it until purged by hand.
+    pub trash_days: u64,
+    /// Largest file that can be attached to a page, in megabytes. The
+    /// journal is rewritten whole on every save, so big ones slow it down.
+    pub attachment_limit_mb: u64,
+    /// How days are shown, as a chrono `strftime` string. Journals keep
+    /// naming day folders `dd/mm/yyyy` whatever is set here.
+    #[serde(deserialize_with = "strftime")]
+    pub date_format: String,
+    /// How creation and edit times are shown.
+    #[serde(deserialize_with = "strftime")]
+    pub datetime_format: String,
+    /// Named data directories, set as `[[profiles]]` tables. With more than
+    /// one the app starts on a list of them; a single one is simply used.
+    pub profiles: Vec<Profile>,
+    /// Colours of the interface, set in a `[theme]` table.
+    pub theme: Theme,
+    /// Colour of each journal's screens, by username, set in a
+    /// `[user_colors]` table. Journals left out take one from `PALETTE`.
+    #[serde(deserialize_with = "colors")]
+    pub user_colors: HashMap<String, Color>,
+    /// Keys for the actions shared by the lists, set in a `[keys]` table.
+    pub keys: KeyMap,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            compress: false,
+            dictionary: None,
+            inline_editor: false,
+            editor_mode: EditorMode::default(),
+            trailing_newline: NewlinePolicy::default(),
+            save_mode: SaveMode::default(),
+            writing_prompt: WritingPrompt::default(),
+            writing_prompts: None,
+            user_sort: UserSort::default(),
+            auto_lock_minutes: 5,
+            mouse: true,
+            allow_backdating: false,
+            reopen_last_page: false,
+            startup: Startup::default(),
+            default_user: None,
+            backups: 1,
+            trash_days: 30,
+            attachment_limit_mb: 10,
+            date_format: "%d/%m/%Y".to_string(),
+            datetime_format: "%d/%m/%Y %H:%M".to_string(),
+            profiles: Vec::new(),
+            theme: Theme::default(),
+            user_colors: HashMap::new(),
+            keys: KeyMap::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    /// Directory holding the profile's journals; a leading `~` is the home
+    /// directory.
+    pub data_dir: String,
+}
+
+/// Colours given by name (`"light blue"`), ANSI index (`"33"`) or hex
+/// (`"#3a7bd5"`).
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    /// Screen titles and their borders.
+    #[serde(deserialize_with = "color")]
+    pub title: Color,
+    /// Borders of lists, inputs and the editor.
+    #[serde(deserialize_with = "color")]
+    pub border: Color,
+    /// Background of the selected row.
+    #[serde(deserialize_with = "color")]
+    pub highlight: Color,
+    /// The key hints at the bottom.
+    #[serde(deserialize_with = "color")]
+    pub help: Color,
+    /// Borders of the viewer, messages and confirmations.
+    #[serde(deserialize_with = "color")]
+    pub popup: Color,
+    /// Border of error messages.
+    #[serde(deserialize_with = "color")]
+    pub error: Color,
+    /// Body text.
+    #[serde(deserialize_with = "color")]
+    pub text: Color,
+}
+
+/// Colours handed out in turn to journals without one of their own, and
+/// cycled through by `o` on the chapter list.
+pub const PALETTE: [Color; 6] = [
+    Color::Cyan,
+    Color::LightGreen,
+    Color::Yellow,
+    Color::LightMagenta,
+    Color::LightBlue,
+    Color::LightRed,
+];
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            title: Color::Cyan,
+            border: Color::Green,
+            highlight: Color::Blue,
+            help: Color::Yellow,
+            popup: Color::Magenta,
+            error: Color::Magenta,
+            text: Color::White,
+        }
+    }
+}
+
+fn color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    name.parse()
+        .map_err(|_| serde::de::Error::custom(format!("unknown colour '{}'", name)))
+}
+
+fn colors<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<String, Color>, D::Error> {
+    HashMap::<String, String>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(key, name)| match name.parse() {
+            Ok(color) => Ok((key, color)),
+            Err(_) => Err(serde::de::Error::custom(format!(
+                "unknown colour '{}' for '{}'",
+                name, key
+            ))),
+        })
+        .collect()
+}
+
+fn strftime<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let format = String::deserialize(deserializer)?;
+    if format.is_empty() || StrftimeItems::new(&format).any(|item| item == Item::Error) {
+        return Err(serde::de::Error::custom(format!(
+            "invalid date format '{}', see chrono's strftime",
+            format
+        )));
+    }
+    Ok(format)
+}
+
+/// Letters that screens use for their own actions, which a list action
+/// bound to them would never see.
+const SCREEN_KEYS: &str = "acefghilmnoprstuwxyEGIJKNRT?0123456789 ";
+
+/// Discards the draft offered after unlocking, whatever `keys.delete` is,
+/// so only deleting may share it.
+const DISCARD_KEY: char = 'd';
+
+/// Keys given as a single character, or as `enter`, `esc`, `tab` or
+/// `space`. The arrow keys, Enter and Esc keep working whatever is set.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct KeyMap {
+    #[serde(deserialize_with = "key")]
+    pub up: KeyCode,
+    #[serde(deserialize_with = "key")]
+    pub down: KeyCode,
+    #[serde(deserialize_with = "key")]
+    pub select: KeyCode,
+    #[serde(deserialize_with = "key")]
+    pub back: KeyCode,
+    #[serde(deserialize_with = "key")]
+    pub quit: KeyCode,
+    #[serde(deserialize_with = "key")]
+    pub search: KeyCode,
+    #[serde(deserialize_with = "key")]
+    pub delete: KeyCode,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self {
+            up: KeyCode::Char('k'),
+            down: KeyCode::Char('j'),
+            select: KeyCode::Enter,
+            back: KeyCode::Char('b'),
+            quit: KeyCode::Char('q'),
+            search: KeyCode::Char('/'),
+            delete: KeyCode::Char('d'),
+        }
+    }
+}
+
+impl KeyMap {
+    fn bindings(&self) -> [(&'static str, KeyCode); 7] {
+        [
+            ("up", self.up),
+            ("down", self.down),
+            ("select", self.select),
+            ("back", self.back),
+            ("quit", self.quit),
+            ("search", self.search),
+            ("delete", self.delete),
+        ]
+    }
+
+    /// Finds two actions sharing a key, or an action on a key a screen
+    /// already uses.
+    pub fn check(&self) -> Result<(), AppError> {
+        let bindings = self.bindings();
+        for (i, (action, code)) in bindings.iter().enumerate() {
+            if let Some((other, _)) = bindings[i + 1..].iter().find(|(_, c)| c == code) {
+                return Err(AppError::Config(format!(
+                    "keys.{} and keys.{} are both bound to {}",
+                    action,
+                    other,
+                    key_label(*code)
+                )));
+            }
+            if let KeyCode::Char(c) = code
+                && (SCREEN_KEYS.contains(*c) || (*c == DISCARD_KEY && *action != "delete"))
+            {
+                return Err(AppError::Config(format!(
+                    "keys.{} can't be {}, a screen already uses that key",
+                    action,
+                    key_label(*code)
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How a key is written in the footer.
+pub fn key_label(code: KeyCode) -> String {
+    match code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn key<'de, D: Deserializer<'de>>(deserializer: D) -> Result<KeyCode, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(KeyCode::Char(c)),
+        _ => match name.to_lowercase().as_str() {
+            "enter" => Ok(KeyCode::Enter),
+            "esc" => Ok(KeyCode::Esc),
+            "tab" => Ok(KeyCode::Tab),
+            "space" => Ok(KeyCode::Char(' ')),
+            _ => Err(serde::de::Error::custom(format!(
+                "unknown key '{}', use one character or enter, esc, tab or space",
+                name
+            ))),
+        },
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UserSort {
+    Name,
+    /// Newest journal first.
+    Created,
+    /// Most recently written journal first.
+    #[default]
+    Modified,
+}
+
+impl UserSort {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Name => Self::Created,
+            Self::Created => Self::Modified,
+            Self::Modified => Self::Name,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Name => "Sorted by name",
+            Self::Created => "Sorted by created",
+            Self::Modified => "Sorted by modified",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NewlinePolicy {
+    /// Keep the content exactly as the editor left it.
+    #[default]
+    Preserve,
+    /// Make the content end with exactly one newline.
+    Single,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SaveMode {
+    /// Save as soon as the editor exits.
+    #[default]
+    Auto,
+    /// Ask before saving; declining discards the edit.
+    Confirm,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EditorMode {
+    /// Every key types; Esc leaves the editor.
+    #[default]
+    Insert,
+    /// Opens in normal mode, `i` to type and Esc to stop.
+    Modal,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Startup {
+    #[default]
+    UserList,
+    /// Today's pages of the default user's journal.
+    Today,
+    /// The default user's timeline.
+    Timeline,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WritingPrompt {
+    Off,
+    /// Show it above the page name.
+    #[default]
+    Show,
+    /// Show it and put it, commented out, at the top of the new page.
+    Prefill,
+}
+
+impl NewlinePolicy {
+    pub fn apply(self, content: &str) -> String {
+        match self {
+            Self::Preserve => content.to_string(),
+            Self::Single => format!("{}\n", content.trim_end_matches(['\n', '\r'])),
+        }
+    }
+}
+
+impl Config {
+    pub fn auto_lock_after(&self) -> Option<Duration> {
+        (self.auto_lock_minutes > 0).then(|| Duration::from_secs(self.auto_lock_minutes * 60))
+    }
+
+    /// Finds a profile name that is empty, used twice, or not usable as a
+    /// directory name, which it becomes in the config directory.
+    pub fn check_profiles(&self) -> Result<(), AppError> {
+        for (i, profile) in self.profiles.iter().enumerate() {
+            let name = profile.name.as_str();
+            if name.is_empty()
+                || name.starts_with('.')
+                || name.contains(['/', '\\', ':'])
+                || name.chars().any(char::is_control)
+            {
+                return Err(AppError::Config(format!(
+                    "'{}' can't be a profile name",
+                    name
+                )));
+            }
+            if self.profiles[..i].iter().any(|other| other.name == name) {
+                return Err(AppError::Config(format!(
+                    "there are two profiles named '{}'",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds a `startup` screen that needs a journal when no
+    /// `default_user` is set.
+    pub fn check_startup(&self) -> Result<(), AppError> {
+        let screen = match self.startup {
+            Startup::UserList => return Ok(()),
+            Startup::Today => "today",
+            Startup::Timeline => "timeline",
+        };
+        match self.default_user.as_deref().map(str::trim) {
+            Some(user) if !user.is_empty() => Ok(()),
+            _ => Err(AppError::Config(format!(
+                "startup = \"{}\" needs a default_user",
+                screen
+            ))),
+        }
+    }
+
+    pub fn load(config_dir: &Path) -> Result<Self, AppError> {
+        let path = config_dir.join(CONFIG_FILE);
+        match fs::read_to_string(&path) {
+            Ok(raw) => toml::from_str(&raw)
+                .map_err(|e| AppError::Config(format!("{}: {}", path.display(), e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_screens_handle_themselves_are_refused() {
+        for c in ['f', 'N', 'd'] {
+            let keys = KeyMap {
+                back: KeyCode::Char(c),
+                delete: KeyCode::Char('z'),
+                ..KeyMap::default()
+            };
+            assert!(keys.check().is_err(), "keys.back = {:?} was accepted", c);
+        }
+        assert!(KeyMap::default().check().is_ok());
+    }
+}
@@ -0,0 +1,305 @@
+//! Multi-line editor used instead of an external editor when `inline_editor`
+//! is set. Lines are edited with the char-indexed helpers of the prompt, so
+//! the cursor never splits a multi-byte character.
+//!
+//! With `editor_mode = "modal"` it takes a few of Vim's keys: it opens in
+//! normal mode, where letters move and edit, and `i`, `a` or `o` switch to
+//! typing until Esc.
+
+use chrono::Local;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::app::time_label;
+use crate::input;
+
+pub enum EditorAction {
+    Save,
+    Cancel,
+    /// Ask for text to replace across the page.
+    Replace,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    /// Keys type text; the only mode unless the editor is modal.
+    Insert,
+    Normal,
+}
+
+pub struct Editor {
+    pub lines: Vec<String>,
+    pub row: usize,
+    pub col: usize,
+    pub mode: Mode,
+    /// Whether Esc leaves insert mode instead of the editor.
+    modal: bool,
+    /// First key of a two-key command in normal mode, such as `dd`.
+    pending: Option<char>,
+    original: String,
+}
+
+impl Editor {
+    pub fn new(content: &str) -> Self {
+        Self {
+            lines: content.split('\n').map(str::to_string).collect(),
+            row: 0,
+            col: 0,
+            mode: Mode::Insert,
+            modal: false,
+            pending: None,
+            original: content.to_string(),
+        }
+    }
+
+    /// Makes the editor modal, starting in normal mode.
+    pub fn modal(self) -> Self {
+        Self {
+            mode: Mode::Normal,
+            modal: true,
+            ..self
+        }
+    }
+
+    pub fn is_modal(&self) -> bool {
+        self.modal
+    }
+
+    /// An editor holding `content` in place of `original`, as when a draft
+    /// is recovered; it counts as modified until saved.
+    pub fn recovered(content: &str, original: &str) -> Self {
+        Self {
+            original: original.to_string(),
+            ..Self::new(content)
+        }
+    }
+
+    pub fn content(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    pub fn original(&self) -> &str {
+        &self.original
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.content() != self.original
+    }
+
+    fn line_len(&self, row: usize) -> usize {
+        self.lines[row].chars().count()
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<EditorAction> {
+        match key.code {
+            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Some(EditorAction::Save);
+            }
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Some(EditorAction::Replace);
+            }
+            // Stamps the time in `datetime_format`, as when logging through
+            // the day within one entry.
+            KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                for c in time_label(Local::now().naive_local()).chars() {
+                    input::insert_char(&mut self.lines[self.row], &mut self.col, c);
+                }
+            }
+            KeyCode::Esc if self.modal => {
+                self.mode = Mode::Normal;
+                input::move_left(&mut self.col);
+            }
+            KeyCode::Esc => return Some(EditorAction::Cancel),
+            KeyCode::Char(_) if key.modifiers.contains(KeyModifiers::CONTROL) => {}
+            KeyCode::Char(c) if self.mode == Mode::Normal => return self.normal_key(c),
+            KeyCode::Enter if self.mode == Mode::Normal => return self.normal_key('j'),
+            KeyCode::Backspace if self.mode == Mode::Normal => return self.normal_key('h'),
+            KeyCode::Delete if self.mode == Mode::Normal => return self.normal_key('x'),
+            KeyCode::Char(c) => input::insert_char(&mut self.lines[self.row], &mut self.col, c),
+            KeyCode::Enter => self.newline(),
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::Delete => self.delete(),
+            KeyCode::Left if self.col == 0 && self.row > 0 => {
+                self.row -= 1;
+                self.col = self.line_len(self.row);
+            }
+            KeyCode::Left => input::move_left(&mut self.col),
+            KeyCode::Right if self.col == self.line_len(self.row) => {
+                if self.row + 1 == self.lines.len() {
+                    return None;
+                }
+                self.row += 1;
+                self.col = 0;
+            }
+            KeyCode::Right => input::move_right(&self.lines[self.row], &mut self.col),
+            KeyCode::Up if self.row > 0 => {
+                self.row -= 1;
+                self.col = self.col.min(self.line_len(self.row));
+            }
+            KeyCode::Down if self.row + 1 < self.lines.len() => {
+                self.row += 1;
+                self.col = self.col.min(self.line_len(self.row));
+            }
+            KeyCode::Home => self.col = 0,
+            KeyCode::End => input::move_end(&self.lines[self.row], &mut self.col),
+            _ => {}
+        }
+        None
+    }
+
+    /// A letter typed in normal mode. Esc there does nothing, so a stray
+    /// one can't throw the page away: `ZZ` saves and `ZQ` discards.
+    fn normal_key(&mut self, c: char) -> Option<EditorAction> {
+        match (self.pending.take(), c) {
+            (Some('d'), 'd') => self.delete_line(),
+            (Some('g'), 'g') => {
+                self.row = 0;
+                self.col = 0;
+            }
+            (Some('Z'), 'Z') => return Some(EditorAction::Save),
+            (Some('Z'), 'Q') => return Some(EditorAction::Cancel),
+            (Some(_), _) => {}
+            (None, 'd' | 'g' | 'Z') => self.pending = Some(c),
+            (None, 'i') => self.mode = Mode::Insert,
+            (None, 'I') => {
+                self.col = 0;
+                self.mode = Mode::Insert;
+            }
+            (None, 'a') => {
+                input::move_right(&self.lines[self.row], &mut self.col);
+                self.mode = Mode::Insert;
+            }
+            (None, 'A') => {
+                input::move_end(&self.lines[self.row], &mut self.col);
+                self.mode = Mode::Insert;
+            }
+            (None, 'o') => {
+                self.row += 1;
+                self.col = 0;
+                self.lines.insert(self.row, String::new());
+                self.mode = Mode::Insert;
+            }
+            (None, 'O') => {
+                self.col = 0;
+                self.lines.insert(self.row, String::new());
+                self.mode = Mode::Insert;
+            }
+            (None, 'h') => input::move_left(&mut self.col),
+            (None, 'l') => input::move_right(&self.lines[self.row], &mut self.col),
+            (None, 'j') if self.row + 1 < self.lines.len() => self.row += 1,
+            (None, 'k') => self.row = self.row.saturating_sub(1),
+            (None, '0') => self.col = 0,
+            (None, '$') => input::move_end(&self.lines[self.row], &mut self.col),
+            (None, 'G') => self.row = self.lines.len() - 1,
+            (None, 'x') if self.col < self.line_len(self.row) => {
+                input::delete_at(&mut self.lines[self.row], self.col)
+            }
+            _ => {}
+        }
+        // The cursor rests on a character, not after the last one.
+        if self.mode == Mode::Normal {
+            self.col = self.col.min(self.line_len(self.row).saturating_sub(1));
+        }
+        None
+    }
+
+    fn delete_line(&mut self) {
+        self.lines.remove(self.row);
+        if self.lines.is_empty() {
+            self.lines.push(String::new());
+        }
+        self.row = self.row.min(self.lines.len() - 1);
+        self.col = 0;
+    }
+
+    /// Replaces every `find` in the buffer with `replacement` and returns
+    /// how many there were. Matches don't span lines. Only the buffer
+    /// changes, so leaving without saving undoes it.
+    pub fn replace_all(&mut self, find: &str, replacement: &str, match_case: bool) -> usize {
+        let needle: Vec<char> = find.chars().collect();
+        if needle.is_empty() {
+            return 0;
+        }
+        let same = |a: char, b: char| {
+            if match_case {
+                a == b
+            } else {
+                a.to_lowercase().eq(b.to_lowercase())
+            }
+        };
+        let mut replaced = 0;
+        for line in &mut self.lines {
+            let chars: Vec<char> = line.chars().collect();
+            let mut result = String::with_capacity(line.len());
+            let mut i = 0;
+            while i < chars.len() {
+                let matches = chars.len() - i >= needle.len()
+                    && needle.iter().zip(&chars[i..]).all(|(n, c)| same(*n, *c));
+                if matches {
+                    result.push_str(replacement);
+                    i += needle.len();
+                    replaced += 1;
+                } else {
+                    result.push(chars[i]);
+                    i += 1;
+                }
+            }
+            *line = result;
+        }
+        self.col = self.col.min(self.line_len(self.row));
+        replaced
+    }
+
+    fn newline(&mut self) {
+        let line = &mut self.lines[self.row];
+        let at = line
+            .char_indices()
+            .nth(self.col)
+            .map_or(line.len(), |(i, _)| i);
+        let rest = line.split_off(at);
+        self.row += 1;
+        self.col = 0;
+        self.lines.insert(self.row, rest);
+    }
+
+    fn backspace(&mut self) {
+        if self.col > 0 {
+            input::delete_before(&mut self.lines[self.row], &mut self.col);
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.line_len(self.row);
+            self.lines[self.row].push_str(&line);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.col < self.line_len(self.row) {
+            input::delete_at(&mut self.lines[self.row], self.col);
+        } else if self.row + 1 < self.lines.len() {
+            let next = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&next);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ctrl_t_inserts_the_time_at_the_cursor() {
+        let mut editor = Editor::new("ab\ncd");
+        editor.row = 1;
+        editor.col = 1;
+        let before = time_label(Local::now().naive_local());
+        editor.handle_key(KeyEvent::new(KeyCode::Char('t'), KeyModifiers::CONTROL));
+        let after = time_label(Local::now().naive_local());
+
+        assert_eq!(editor.lines[0], "ab");
+        // The minute may turn over between the two readings.
+        let stamped = [&before, &after].map(|time| format!("c{}d", time));
+        assert!(stamped.contains(&editor.lines[1]), "{:?}", editor.lines[1]);
+        // The cursor is left after the time, before the `d`.
+        assert_eq!(editor.col, editor.lines[1].chars().count() - 1);
+    }
+}
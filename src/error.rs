@@ -0,0 +1,46 @@
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum AppError {
+    Io(io::Error),
+    Decrypt(String),
+    Parse(String),
+    Editor(String),
+    Config(String),
+    Usage(String),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Io(e) => write!(f, "I/O error: {}", e),
+            AppError::Decrypt(msg) => write!(f, "Decryption failed: {}", msg),
+            AppError::Parse(msg) => write!(f, "Parse error: {}", msg),
+            AppError::Editor(msg) => write!(f, "Editor error: {}", msg),
+            AppError::Config(msg) => write!(f, "Configuration error: {}", msg),
+            AppError::Usage(msg) => write!(f, "{}\n\n{}", msg, crate::cli::USAGE),
+        }
+    }
+}
+
+impl std::error::Error for AppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AppError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for AppError {
+    fn from(e: io::Error) -> Self {
+        AppError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::Parse(e.to_string())
+    }
+}
@@ -0,0 +1,165 @@
+//! Writes decrypted journal content out of the clog.
+
+use directories::BaseDirs;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::app::{file_items, folder_items, parse_folder_date};
+use crate::clog;
+use crate::settings::JournalSettings;
+
+pub struct ExportReport {
+    pub exported: usize,
+    /// `folder/page` of every page that couldn't be read or written.
+    pub failed: Vec<String>,
+}
+
+/// Expands a leading `~` and makes `input` absolute, so the confirmation
+/// shows exactly where files will land.
+pub fn resolve_destination(input: &str) -> PathBuf {
+    let expanded = match input.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => BaseDirs::new()
+            .map(|dirs| dirs.home_dir().join(rest.trim_start_matches(['/', '\\'])))
+            .unwrap_or_else(|| PathBuf::from(input)),
+        _ => PathBuf::from(input),
+    };
+    std::path::absolute(&expanded).unwrap_or(expanded)
+}
+
+/// Days and pages an export of the journal would write.
+pub fn journal_size(metadata: &Value, journal: &JournalSettings) -> (usize, usize) {
+    let folders = folder_items(metadata, journal, "");
+    let pages = folders
+        .iter()
+        .map(|(folder, _)| file_items(metadata, folder, "", journal).len())
+        .sum();
+    (folders.len(), pages)
+}
+
+/// One Markdown file per day, named `yyyy-mm-dd.md`, with a `##` heading per
+/// page. A page that fails is reported and the export carries on. Chapters
+/// whose names come out the same, such as the topic `a/b` and `a-b`, get
+/// `-2`, `-3`… after the later ones rather than overwriting each other.
+pub fn export_markdown(
+    password: &str,
+    file_path: &Path,
+    metadata: &Value,
+    journal: &JournalSettings,
+    destination: &Path,
+) -> ExportReport {
+    let mut report = ExportReport {
+        exported: 0,
+        failed: Vec::new(),
+    };
+    if let Err(e) = fs::create_dir_all(destination) {
+        report
+            .failed
+            .push(format!("{}: {}", destination.display(), e));
+        return report;
+    }
+
+    let mut used = HashSet::new();
+    for (folder, _) in folder_items(metadata, journal, "") {
+        let chapter = chapter_markdown(password, file_path, metadata, journal, &folder);
+        report.failed.extend(chapter.failed);
+        if chapter.pages.is_empty() {
+            continue;
+        }
+        let day_file = destination.join(unused_file_name(&day_file_name(&folder), &mut used));
+        match fs::write(&day_file, chapter.markdown) {
+            Ok(()) => report.exported += chapter.pages.len(),
+            Err(e) => report.failed.extend(
+                chapter
+                    .pages
+                    .iter()
+                    .map(|file| format!("{}/{}: {}", folder, file, e)),
+            ),
+        }
+    }
+    report
+}
+
+pub struct ChapterMarkdown {
+    pub markdown: String,
+    /// Pages in the Markdown.
+    pub pages: Vec<String>,
+    /// `folder/page` of every page that couldn't be read.
+    pub failed: Vec<String>,
+}
+
+/// A chapter as Markdown: its name as a `#` heading, then a `##` heading
+/// per page in list order. A page that fails is left out and reported.
+pub fn chapter_markdown(
+    password: &str,
+    file_path: &Path,
+    metadata: &Value,
+    journal: &JournalSettings,
+    folder: &str,
+) -> ChapterMarkdown {
+    let mut chapter = ChapterMarkdown {
+        markdown: format!("# {}\n", folder),
+        pages: Vec::new(),
+        failed: Vec::new(),
+    };
+    for (file, _) in file_items(metadata, folder, "", journal) {
+        let stored = journal.stored_in(folder, &file);
+        match clog::read_page(password, file_path, &file, stored) {
+            Ok(content) => {
+                chapter
+                    .markdown
+                    .push_str(&format!("\n## {}\n\n{}\n", file, content.trim_end()));
+                chapter.pages.push(file);
+            }
+            Err(e) => chapter.failed.push(format!("{}/{}: {}", folder, file, e)),
+        }
+    }
+    chapter
+}
+
+/// `name`, or `name` with the first free `-n` before its extension, and
+/// marks it as used. Names are compared ignoring case, as the filesystems
+/// of Windows and macOS do.
+fn unused_file_name(name: &str, used: &mut HashSet<String>) -> String {
+    let (stem, extension) = name.rsplit_once('.').unwrap_or((name, ""));
+    let mut candidate = name.to_string();
+    let mut n = 1;
+    while !used.insert(candidate.to_lowercase()) {
+        n += 1;
+        candidate = format!("{}-{}.{}", stem, n, extension);
+    }
+    candidate
+}
+
+/// `yyyy-mm-dd.md` for a day, the chapter's name otherwise.
+pub fn day_file_name(folder: &str) -> String {
+    match parse_folder_date(folder) {
+        Some(date) => date.format("%Y-%m-%d.md").to_string(),
+        None => format!("{}.md", folder.replace(['/', '\\'], "-")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clashing_chapter_files_get_a_number() {
+        let mut used = HashSet::new();
+        let names: Vec<String> = ["2024-01-01", "01/01/2024", "a/b", "a-b", "A-B"]
+            .iter()
+            .map(|folder| unused_file_name(&day_file_name(folder), &mut used))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "2024-01-01.md",
+                "2024-01-01-2.md",
+                "a-b.md",
+                "a-b-2.md",
+                "A-B-3.md"
+            ]
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! Brings plain-text files into a clog as pages of today's folder, the only
+//! folder clog_rs can add to.
+
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::clog;
+use crate::config::Config;
+use crate::settings::SETTINGS_PAGE;
+
+pub struct ImportReport {
+    pub imported: usize,
+    /// File name and reason for every file that was left out.
+    pub skipped: Vec<String>,
+}
+
+/// The `.txt` and `.md` files directly inside `source`, sorted by name.
+pub fn importable_files(source: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(source)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path.extension().is_some_and(|ext| {
+                    ext.eq_ignore_ascii_case("txt") || ext.eq_ignore_ascii_case("md")
+                })
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+pub fn import_files(
+    password: &str,
+    file_path: &Path,
+    metadata: &Value,
+    today: &str,
+    files: &[PathBuf],
+    config: &Config,
+) -> ImportReport {
+    let mut report = ImportReport {
+        imported: 0,
+        skipped: Vec::new(),
+    };
+    let mut taken: HashSet<String> = metadata["folders"][today]
+        .as_object()
+        .map(|pages| pages.keys().cloned().collect())
+        .unwrap_or_default();
+    taken.insert(SETTINGS_PAGE.to_string());
+
+    for path in files {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let content = match fs::read_to_string(path) {
+            Ok(content) if content.contains('\0') => {
+                report
+                    .skipped
+                    .push(format!("{}: looks like a binary file", name));
+                continue;
+            }
+            Ok(content) if content.trim().is_empty() => {
+                report.skipped.push(format!("{}: empty", name));
+                continue;
+            }
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+                report.skipped.push(format!("{}: not UTF-8 text", name));
+                continue;
+            }
+            Err(e) => {
+                report.skipped.push(format!("{}: {}", name, e));
+                continue;
+            }
+        };
+        let page = unique_page_name(path, &taken);
+        let content = config.trailing_newline.apply(&content);
+        match clog::add_page(password, file_path, &page, &content, config.compress) {
+            Ok(()) => {
+                taken.insert(page);
+                report.imported += 1;
+            }
+            Err(e) => report.skipped.push(format!("{}: {}", name, e)),
+        }
+    }
+    report
+}
+
+/// The file's stem with control characters removed, suffixed with ` (2)`,
+/// ` (3)`, … until it doesn't clash with a page already in the folder.
+fn unique_page_name(path: &Path, taken: &HashSet<String>) -> String {
+    let stem: String = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect();
+    let base = match stem.trim() {
+        "" => "imported".to_string(),
+        trimmed => trimmed.to_string(),
+    };
+    let mut name = base.clone();
+    let mut n = 2;
+    while taken.contains(&name) {
+        name = format!("{} ({})", base, n);
+        n += 1;
+    }
+    name
+}
@@ -0,0 +1,66 @@
+//! Line editing for the single-line prompt. The cursor is tracked in chars,
+//! not bytes, so it never lands inside a multi-byte UTF-8 sequence.
+
+fn byte_index(buffer: &str, cursor: usize) -> usize {
+    buffer
+        .char_indices()
+        .nth(cursor)
+        .map_or(buffer.len(), |(i, _)| i)
+}
+
+pub fn insert_char(buffer: &mut String, cursor: &mut usize, c: char) {
+    let at = byte_index(buffer, *cursor);
+    buffer.insert(at, c);
+    *cursor += 1;
+}
+
+pub fn delete_before(buffer: &mut String, cursor: &mut usize) {
+    if *cursor == 0 {
+        return;
+    }
+    *cursor -= 1;
+    let at = byte_index(buffer, *cursor);
+    buffer.remove(at);
+}
+
+pub fn delete_at(buffer: &mut String, cursor: usize) {
+    if cursor < buffer.chars().count() {
+        let at = byte_index(buffer, cursor);
+        buffer.remove(at);
+    }
+}
+
+pub fn move_left(cursor: &mut usize) {
+    *cursor = cursor.saturating_sub(1);
+}
+
+pub fn move_right(buffer: &str, cursor: &mut usize) {
+    *cursor = (*cursor + 1).min(buffer.chars().count());
+}
+
+pub fn move_end(buffer: &str, cursor: &mut usize) {
+    *cursor = buffer.chars().count();
+}
+
+pub fn delete_word_before(buffer: &mut String, cursor: &mut usize) {
+    let chars: Vec<char> = buffer.chars().collect();
+    let mut start = *cursor;
+    while start > 0 && chars[start - 1].is_whitespace() {
+        start -= 1;
+    }
+    while start > 0 && !chars[start - 1].is_whitespace() {
+        start -= 1;
+    }
+    buffer.replace_range(byte_index(buffer, start)..byte_index(buffer, *cursor), "");
+    *cursor = start;
+}
+
+pub fn delete_to_start(buffer: &mut String, cursor: &mut usize) {
+    buffer.replace_range(..byte_index(buffer, *cursor), "");
+    *cursor = 0;
+}
+
+pub fn delete_to_end(buffer: &mut String, cursor: usize) {
+    let at = byte_index(buffer, cursor);
+    buffer.truncate(at);
+}
@@ -0,0 +1,152 @@
+//! Lock files that keep two sessions from writing the same journal, which
+//! would otherwise overwrite each other's changes. A session that opens a
+//! journal for writing leaves `<user>.clog.lock` beside it, naming its
+//! process and machine, and removes it when the journal is closed.
+
+use chrono::{DateTime, Local};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// A lock untouched for this long is taken as left behind, whatever
+/// process it names. Sessions refresh theirs on every write.
+const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The lock this process holds, so it can be removed on exit and on a
+/// panic.
+static HELD: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// The session that holds a journal's lock.
+pub struct Holder {
+    pub pid: Option<u32>,
+    pub host: String,
+    pub since: Option<DateTime<Local>>,
+    /// Its process is gone, or the lock hasn't been touched for a day.
+    pub stale: bool,
+}
+
+pub fn lock_path(journal: &Path) -> PathBuf {
+    let mut path = journal.as_os_str().to_owned();
+    path.push(".lock");
+    PathBuf::from(path)
+}
+
+/// Locks the journal for this session, releasing any other journal it had
+/// locked. Returns who holds it if another session does.
+pub fn acquire(journal: &Path) -> io::Result<Option<Holder>> {
+    let path = lock_path(journal);
+    if held().as_deref() == Some(path.as_path()) {
+        return Ok(None);
+    }
+    match fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+    {
+        Ok(mut file) => {
+            let written =
+                file.write_all(format!("{}\n{}\n", std::process::id(), host_name()).as_bytes());
+            if let Err(e) = written {
+                fs::remove_file(&path).ok();
+                return Err(e);
+            }
+            release();
+            *HELD.lock().unwrap_or_else(|e| e.into_inner()) = Some(path);
+            Ok(None)
+        }
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => holder(journal),
+        Err(e) => Err(e),
+    }
+}
+
+/// Removes the lock another session holds, or left behind, and takes it.
+pub fn force(journal: &Path) -> io::Result<()> {
+    match fs::remove_file(lock_path(journal)) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    match acquire(journal)? {
+        None => Ok(()),
+        Some(_) => Err(io::Error::other("another session locked it again")),
+    }
+}
+
+/// Who holds the journal's lock, if anyone.
+pub fn holder(journal: &Path) -> io::Result<Option<Holder>> {
+    let path = lock_path(journal);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let mut lines = content.lines();
+    let pid = lines.next().and_then(|line| line.trim().parse().ok());
+    let host = lines.next().unwrap_or_default().trim().to_string();
+    let modified = fs::metadata(&path)?.modified().ok();
+    let old = modified
+        .and_then(|time| SystemTime::now().duration_since(time).ok())
+        .is_some_and(|age| age > STALE_AFTER);
+    // A process can only be looked for on this machine.
+    let gone = match pid {
+        Some(pid) => host == host_name() && !is_running(pid),
+        None => true,
+    };
+    Ok(Some(Holder {
+        pid,
+        host,
+        since: modified.map(DateTime::from),
+        stale: old || gone,
+    }))
+}
+
+/// Marks the held lock as still in use, so it doesn't turn stale.
+pub fn refresh() {
+    if let Some(path) = held()
+        && let Ok(file) = fs::OpenOptions::new().append(true).open(&path)
+    {
+        file.set_modified(SystemTime::now()).ok();
+    }
+}
+
+/// Removes the lock this session holds, if any.
+pub fn release() {
+    let path = HELD.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(path) = path {
+        fs::remove_file(path).ok();
+    }
+}
+
+fn held() -> Option<PathBuf> {
+    HELD.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+fn host_name() -> String {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// `kill -0` only checks that the process exists.
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    pid == std::process::id()
+        || std::process::Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+}
+
+/// Without a way to look, the process is assumed to be running and only
+/// the lock's age makes it stale.
+#[cfg(not(unix))]
+fn is_running(_pid: u32) -> bool {
+    true
+}
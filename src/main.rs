@@ -1,963 +1,2908 @@
-use chrono::Local;
-use directories::ProjectDirs;
 use ratatui::{
     Terminal,
-    backend::CrosstermBackend,
-    layout::{Alignment, Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
-    text::{Line, Span},
-    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+    backend::{Backend, CrosstermBackend},
 };
 use serde_json::Value;
 use std::fs;
 use std::io::{self, Read, Write};
-use std::path::PathBuf;
+use std::path::Path;
 use std::process::Command;
-use std::time::{Duration, Instant};
-use tempfile::NamedTempFile;
+use std::rc::Rc;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
 
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, MouseEvent, MouseEventKind},
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
 
-use clog_rs::*;
-
-#[derive(Clone)]
-enum AppState {
-    SelectUser,
-    EnterNewUser,
-    EnterPassword(String),
-    SelectFolder(String, String),
-    SelectFile(String, String, String),
-    EditOrViewFile(String, String, String, String),
-    InputPrompt(String, Box<AppState>),
-    AddPagePrompt(String, String, String),
-    Done,
-}
-
-struct App {
-    state: AppState,
-    selected_index: usize,
-    input_buffer: String,
-    data_dir: PathBuf,
-    last_frame: Instant,
-}
-
-impl App {
-    fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let project_dirs =
-            ProjectDirs::from("com", "levi", "clog").ok_or("Failed to get project directories")?;
-        let data_dir = project_dirs.data_dir().to_path_buf();
-        fs::create_dir_all(&data_dir)?;
-
-        Ok(Self {
-            state: AppState::SelectUser,
-            selected_index: 0,
-            input_buffer: String::new(),
-            data_dir,
-            last_frame: Instant::now(),
-        })
-    }
-
-    fn reset_selection(&mut self) {
-        self.selected_index = 0;
-    }
-
-    fn get_help_text(&self) -> &'static str {
-        match &self.state {
-            AppState::SelectUser => "↑/↓ or j/k: Navigate | Enter: Select | q: Quit",
-            AppState::EnterNewUser | AppState::EnterPassword(_) => {
-                "Enter when prompted | Esc: Back | q: Quit"
-            }
-            AppState::SelectFolder(_, _) | AppState::SelectFile(_, _, _) => {
-                "↑/↓ or j/k: Navigate | Enter: Select | b/Esc: Back | q: Quit"
-            }
-            AppState::EditOrViewFile(_, _, _, _) => "Page will open in editor | q: Quit",
-            AppState::InputPrompt(_, _) | AppState::AddPagePrompt(_, _, _) => {
-                "Type input | Enter: Confirm | Esc: Cancel"
-            }
-            AppState::Done => "Press any key to exit",
+mod app;
+mod cli;
+mod clipboard;
+mod clog;
+mod config;
+mod editor;
+mod error;
+mod export;
+mod import;
+mod input;
+mod lockfile;
+mod markdown;
+mod password;
+mod prompts;
+mod search;
+mod settings;
+mod stats;
+mod store;
+mod ui;
+mod viewer;
+
+use app::{
+    ADD_ATTACHMENT_ITEM, ADD_PAGE_ITEM, ADD_USER_ITEM, App, AppState, CORRUPT_BADGE,
+    NEW_CHAPTER_ITEM, PageWrite, RECENT_BADGE, Recovery, SUB_CHAPTER_BADGE, SideEffect,
+    TIMESTAMP_FORMAT, UNRECOGNIZED_BADGE, Undo, UndoPage, all_pages, file_items, folder_items,
+    folder_label, page_label, page_name_owner, parse_folder_date, time_label, timestamp_label,
+    today_str, top_folder_items, validate_username,
+};
+use clog::ClogError;
+use config::{PALETTE, SaveMode, Startup, UserSort, WritingPrompt};
+use editor::Editor;
+use error::AppError;
+use settings::{Draft, JournalSettings, PageRef};
+use stats::JournalStats;
+use viewer::Viewer;
+use zeroize::{Zeroize, Zeroizing};
+
+/// How long a decryption runs before the spinner shows, so quick ones don't
+/// flash it.
+const SPINNER_DELAY: Duration = Duration::from_millis(150);
+const SPINNER_FRAME: Duration = Duration::from_millis(80);
+/// Attachments from this size on are only added after a warning.
+const LARGE_ATTACHMENT: u64 = 1024 * 1024;
+
+fn main() -> Result<(), AppError> {
+    let options = match cli::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("clog-tui: {}", e);
+            std::process::exit(2);
         }
+    };
+    if options.help {
+        println!("{}", cli::USAGE);
+        return Ok(());
     }
-
-    fn should_render(&mut self) -> bool {
-        let now = Instant::now();
-        let frame_duration = Duration::from_millis(16); // 60 FPS
-
-        if now.duration_since(self.last_frame) >= frame_duration {
-            self.last_frame = now;
-            true
-        } else {
-            false
+    if let cli::Command::Add(name) = &options.command {
+        // Never touches the terminal, so it can run from scripts and hooks.
+        install_panic_hook(false);
+        match add_from_stdin(options.user.as_deref().unwrap_or_default(), name) {
+            Ok(note) => println!("{}", note),
+            Err(e) => {
+                eprintln!("clog-tui: {}", e);
+                std::process::exit(1);
+            }
         }
+        return Ok(());
     }
+    install_panic_hook(true);
+    // Restore the terminal however the session ends, including a failure
+    // halfway through setting it up.
+    let result = start(options);
+    lockfile::release();
+    let restored = restore_terminal();
+    result?;
+    Ok(restored?)
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn start(options: cli::Options) -> Result<(), AppError> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = App::new()?;
+    App::new().and_then(|mut app| {
+        app.readonly = options.readonly;
+        run(&mut terminal, &mut app, options.user)
+    })
+}
+
+/// `clog-tui add`: writes standard input as a new page of today in `user`'s
+/// journal, the way Add Page would, and says what was added.
+fn add_from_stdin(user: &str, name: &str) -> Result<String, String> {
+    let mut app = App::new().map_err(|e| e.to_string())?;
+    if let Some(message) = app.message.take() {
+        eprintln!("clog-tui: {}", message.1);
+    }
+    clog::set_backup_count(app.config.backups);
+    validate_username(user.trim_end_matches(".clog"))?;
+    let user_file = format!("{}.clog", user.trim_end_matches(".clog"));
+    if app.has_profiles() {
+        let index = profile_with_journal(&app, &user_file)
+            .ok_or_else(|| format!("No profile has a journal named '{}'", user))?;
+        app.use_profile(index).map_err(|e| e.to_string())?;
+    }
+    let user_path = existing_journal(&app.data_dir, &user_file)
+        .ok_or_else(|| format!("There is no journal named '{}'", user))?;
+    let file_path = app.data_dir.join(&user_path);
+    // The open session would write its copy of the journal over the page.
+    if let Ok(Some(holder)) = lockfile::holder(&file_path)
+        && !holder.stale
+    {
+        return Err(format!(
+            "'{}' is open in clog-tui; add the page there or close it first",
+            user
+        ));
+    }
+    if settings::is_reserved_page(name) {
+        return Err(format!("'{}' is reserved, choose another name", name));
+    }
+
+    let mut content = Zeroizing::new(String::new());
+    io::stdin()
+        .read_to_string(&mut content)
+        .map_err(|e| format!("Cannot read standard input: {}", e))?;
+    if content.trim().is_empty() {
+        return Err("Nothing to add: standard input is empty".to_string());
+    }
+    let content = Zeroizing::new(app.config.trailing_newline.apply(&content));
+    let password = Zeroizing::new(match std::env::var("CLOG_PASSWORD") {
+        Ok(password) => password,
+        Err(_) => rpassword::prompt_password(format!("Password for {}: ", user))
+            .map_err(|e| format!("Cannot read the password: {}", e))?,
+    });
+
+    let metadata = clog::read_metadata(&password, &file_path).map_err(|e| match e {
+        ClogError::WrongPassword => "Incorrect password!".to_string(),
+        e => format!("Cannot open {}: {}", user_path, e),
+    })?;
+    let loaded = settings::load_settings(&password, &file_path, &metadata)
+        .map_err(|e| settings_error(&user_path, e))?;
+    let today = today_str();
+    if let Some(owner) = page_name_owner(&metadata, &loaded, &today, name, &today) {
+        return Err(format!(
+            "'{}' is already the name of a page in {}, choose another name",
+            name,
+            folder_label(&owner)
+        ));
+    }
+    let mut journal = loaded.clone();
+    settings::add_or_reuse_page(
+        &password,
+        &file_path,
+        &mut journal,
+        name,
+        &content,
+        &today,
+        app.config.compress,
+    )
+    .and_then(|()| {
+        if journal == loaded {
+            return Ok(());
+        }
+        let metadata = clog::read_metadata(&password, &file_path)?;
+        settings::save_settings(&password, &file_path, &metadata, &journal)
+    })
+    .map_err(|e| format!("Error creating page: {}", e))?;
+    Ok(format!(
+        "Added '{}' to {} ({})",
+        name,
+        folder_label(&today),
+        stats::summary(&content)
+    ))
+}
+
+fn restore_terminal() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), DisableMouseCapture, LeaveAlternateScreen)
+}
+
+/// Restores the terminal before the panic message is printed, so a crash
+/// doesn't leave the shell in raw mode on the alternate screen, and removes
+/// the journal's lock file. Headless commands never took the terminal
+/// over, so there is nothing to restore.
+fn install_panic_hook(tui: bool) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if clog::catching_panic() {
+            return;
+        }
+        lockfile::release();
+        if tui {
+            restore_terminal().ok();
+        }
+        default_hook(info);
+    }));
+}
+
+fn run<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    user: Option<String>,
+) -> Result<(), AppError> {
+    clog::set_backup_count(app.config.backups);
+    app::set_display_formats(&app.config.date_format, &app.config.datetime_format);
+    if app.config.mouse {
+        execute!(io::stdout(), EnableMouseCapture)?;
+    }
+    app.resume_last_page = app.config.reopen_last_page;
+    app.startup = app.config.startup;
+    let user = user.or_else(|| match app.startup {
+        Startup::UserList => None,
+        Startup::Today | Startup::Timeline => app.config.default_user.clone(),
+    });
+    // The name becomes a path in the data directory, so it's held to the
+    // same rules as one typed into New User.
+    let user = user.filter(
+        |name| match validate_username(name.trim_end_matches(".clog")) {
+            Ok(()) => true,
+            Err(reason) => {
+                app.show_message("Error", &reason);
+                false
+            }
+        },
+    );
+    let mut pending = if app.has_profiles() {
+        // The last journal is looked for once a profile is picked; a named
+        // one is looked for in every profile, in the order they're listed.
+        match user {
+            Some(name) => {
+                let user_file = format!("{}.clog", name.trim_end_matches(".clog"));
+                match profile_with_journal(app, &user_file) {
+                    Some(index) => {
+                        app.use_profile(index)?;
+                        ask_password(app, &name)
+                    }
+                    None => {
+                        app.show_message(
+                            "Error",
+                            &format!("No profile has a journal named '{}'", name),
+                        );
+                        app.enter(AppState::SelectProfile)
+                    }
+                }
+            }
+            None => app.enter(AppState::SelectProfile),
+        }
+    } else {
+        match user.or_else(|| app.resume_last_page.then(|| last_user(app)).flatten()) {
+            Some(name) => ask_password(app, &name),
+            None => app.enter(AppState::SelectUser),
+        }
+    };
 
     loop {
+        while let Some(effect) = pending.take() {
+            if effect == SideEffect::Quit {
+                return Ok(());
+            }
+            let writes = effect.writes();
+            // The screens don't offer writes in a read-only session; this
+            // catches any path that slips through.
+            if writes && app.readonly {
+                app.show_message("Error", "The journal is open read-only");
+                app.request_redraw();
+                continue;
+            }
+            pending = perform(terminal, app, effect)?;
+            // Invalidation point: whatever was written, the cached metadata
+            // no longer matches the file. Dropped even when the write failed
+            // part way, since it may still have changed something.
+            if writes {
+                app.store.invalidate();
+                lockfile::refresh();
+            }
+            // Time spent in an external editor isn't idle time.
+            app.touch();
+            app.request_redraw();
+        }
+        app.check_auto_lock();
+        // Drafts are written in the background of typing: not a write to
+        // the journal, and not activity that holds off auto-lock.
+        if let Some(effect) = app.due_draft() {
+            pending = perform(terminal, app, effect)?;
+            continue;
+        }
+        if let Some(effect) = app.due_preview() {
+            pending = perform(terminal, app, effect)?;
+            continue;
+        }
+
         if app.should_render() {
-            terminal.clear()?;
+            terminal.draw(|f| ui::draw(f, app))?;
         }
 
-        let current_state = app.state.clone();
-        match current_state {
-            AppState::SelectUser => {
-                let user_files = list_clog_files(&app.data_dir);
-                let mut display_items: Vec<(String, String)> = user_files
-                    .iter()
-                    .map(|file| {
-                        let file_path = app.data_dir.join(file);
-                        let date = get_user_creation_date(&file_path).unwrap_or_default();
-                        (file.clone(), date)
-                    })
-                    .collect();
-
-                display_items.push(("Add New User".to_string(), String::new()));
-
-                let help_text = app.get_help_text();
-                let mut selected_index = app.selected_index;
-                if let Some(selection) = select_menu_with_metadata(
-                    &mut terminal,
-                    "Select User",
-                    &display_items,
-                    &mut selected_index,
-                    &help_text,
-                    &mut app,
-                )? {
-                    app.selected_index = selected_index;
-                    if selection == "Add New User" {
-                        app.input_buffer.clear();
-                        app.state = AppState::InputPrompt(
-                            "Enter new username:".to_string(),
-                            Box::new(AppState::EnterNewUser),
-                        );
-                    } else {
-                        app.input_buffer = selection.clone();
-                        app.state = AppState::InputPrompt(
-                            "Enter password:".to_string(),
-                            Box::new(AppState::EnterPassword(selection)),
-                        );
+        // Sleeps until input arrives or the auto-lock countdown ticks;
+        // nothing is read or decrypted while idle.
+        if event::poll(app.idle_timeout())? {
+            let event = event::read()?;
+            if !matches!(
+                event,
+                Event::Mouse(MouseEvent {
+                    kind: MouseEventKind::Moved,
+                    ..
+                })
+            ) {
+                app.request_redraw();
+            }
+            pending = app.handle_event(event);
+        }
+    }
+}
+
+/// Executes the I/O behind a side effect and feeds the outcome back into the
+/// app. Returns a follow-up effect when the outcome moved the app to a screen
+/// that needs loading.
+fn perform<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    effect: SideEffect,
+) -> Result<Option<SideEffect>, AppError> {
+    // Undo reaches back one change only, so any write drops it; the writes
+    // that can be undone put their own in its place.
+    let undo = if effect.writes() {
+        app.undo.take()
+    } else {
+        None
+    };
+    match effect {
+        SideEffect::LoadProfiles => {
+            close_journal(app);
+            app.items = app
+                .config
+                .profiles
+                .iter()
+                .map(|profile| (profile.name.clone(), profile.data_dir.clone()))
+                .collect();
+            if let Some(current) = &app.profile
+                && let Some(index) = app.items.iter().position(|(name, _)| name == current)
+            {
+                app.selected_index = index;
+            }
+            Ok(None)
+        }
+        SideEffect::SwitchProfile(index) => {
+            if let Err(e) = app.use_profile(index) {
+                app.show_message("Error", &e.to_string());
+                return Ok(None);
+            }
+            // A profile has its own recent journals, so reopening the last
+            // page waits until one is picked.
+            if app.resume_last_page
+                && let Some(name) = last_user(app)
+            {
+                return Ok(ask_password(app, &name));
+            }
+            app.resume_last_page = false;
+            Ok(app.enter(AppState::SelectUser))
+        }
+        SideEffect::LoadDraft(user_path, password) => {
+            let file_path = app.data_dir.join(&user_path);
+            match settings::load_draft(&password, &file_path) {
+                Ok(Some(draft)) => {
+                    let saved =
+                        if draft.new_page || app.journal.is_deleted(&draft.folder, &draft.file) {
+                            None
+                        } else {
+                            let stored = app.journal.stored_in(&draft.folder, &draft.file);
+                            clog::read_page(&password, &file_path, &draft.file, stored).ok()
+                        };
+                    app.recovery = Some(Recovery {
+                        draft,
+                        saved,
+                        compare: false,
+                        scroll: 0,
+                    });
+                    Ok(None)
+                }
+                Ok(None) => Ok(app.enter(AppState::SelectFolder(user_path, password))),
+                Err(e) => {
+                    app.show_message(
+                        "Error",
+                        &format!("The unsaved draft couldn't be opened: {}", e),
+                    );
+                    Ok(app.enter(AppState::SelectFolder(user_path, password)))
+                }
+            }
+        }
+        SideEffect::SaveDraft(write) => {
+            let draft = Draft {
+                folder: write.folder,
+                file: write.file,
+                new_page: write.new_page,
+                tags: write.tags,
+                content: write.content,
+                drafted: chrono::Local::now().format(TIMESTAMP_FORMAT).to_string(),
+            };
+            let file_path = app.data_dir.join(&write.user_path);
+            match settings::save_draft(&write.password, &file_path, &draft) {
+                Ok(()) => app.draft_written = true,
+                Err(e) => app.announce(format!("Draft not saved: {}", e)),
+            }
+            Ok(None)
+        }
+        SideEffect::DiscardDraft(user_path, next) => {
+            app.draft_written = false;
+            if let Err(e) = settings::discard_draft(&app.data_dir.join(&user_path)) {
+                app.show_message("Error", &format!("The draft couldn't be deleted: {}", e));
+            }
+            Ok(app.enter(*next))
+        }
+        SideEffect::LoadUsers => {
+            close_journal(app);
+            assign_journal_colors(app);
+            let titles = settings::load_title_cache(&app.state_dir());
+            let mut user_files = list_clog_files(&app.data_dir);
+            sort_users(&mut user_files, &app.data_dir, app.user_sort);
+            let mut display_items: Vec<(String, String)> = user_files
+                .iter()
+                .map(|file| {
+                    let file_path = app.data_dir.join(file);
+                    if !looks_like_clog(&file_path) {
+                        return (file.clone(), UNRECOGNIZED_BADGE.to_string());
+                    }
+                    if app.damaged.contains(file) || is_truncated(&file_path) {
+                        return (file.clone(), CORRUPT_BADGE.to_string());
+                    }
+                    let date = get_user_creation_date(&file_path).unwrap_or_default();
+                    match titles.get(file) {
+                        Some(title) => (file.clone(), format!("{} · {}", title, date)),
+                        None => (file.clone(), date),
                     }
-                    app.reset_selection();
-                }
-            }
-            AppState::InputPrompt(prompt, next_state) => {
-                let help_text = app.get_help_text();
-                let mut input_buffer = app.input_buffer.clone();
-                if let Some(input) = prompt_input_in_app(
-                    &mut terminal,
-                    &prompt,
-                    &mut input_buffer,
-                    help_text,
-                    &mut app,
-                )? {
-                    app.input_buffer = input_buffer;
-                    match *next_state {
-                        AppState::EnterNewUser => {
-                            let username = input;
-                            app.state = AppState::InputPrompt(
-                                "Enter password:".to_string(),
-                                Box::new(AppState::SelectFolder(
-                                    format!("{}.clog", username),
-                                    String::new(),
-                                )),
-                            );
+                })
+                .collect();
+
+            // Recently unlocked journals are pinned above the full list; a
+            // damaged or unrecognized one keeps that badge instead.
+            let recent: Vec<(String, String)> = settings::load_recent_users(&app.state_dir())
+                .iter()
+                .filter_map(|file| display_items.iter().find(|(item, _)| item == file))
+                .map(|(file, badge)| {
+                    let badge = match badge.as_str() {
+                        UNRECOGNIZED_BADGE | CORRUPT_BADGE => badge.clone(),
+                        "" => RECENT_BADGE.to_string(),
+                        _ => format!("{} · {}", RECENT_BADGE, badge),
+                    };
+                    (file.clone(), badge)
+                })
+                .collect();
+            display_items.splice(0..0, recent);
+
+            display_items.push((ADD_USER_ITEM.to_string(), String::new()));
+            app.set_items(display_items);
+            Ok(None)
+        }
+        SideEffect::Unlock(user_path, password) => {
+            let file_path = app.data_dir.join(&user_path);
+            match journal_metadata(terminal, app, &password, &file_path) {
+                Ok(metadata) => {
+                    let journal = match settings::load_settings(&password, &file_path, &metadata) {
+                        Ok(journal) => journal,
+                        Err(e) => {
+                            app.show_message("Error", &settings_error(&user_path, e));
+                            return Ok(app.enter(AppState::SelectUser));
                         }
-                        AppState::EnterPassword(user_path) => {
-                            let password = input;
-                            let file_path = app.data_dir.join(&user_path);
-                            match std::panic::catch_unwind(|| {
-                                get_json_metadata(&password, file_path.to_str().unwrap())
-                            }) {
-                                Ok(_) => {
-                                    app.state = AppState::SelectFolder(user_path, password);
-                                    app.reset_selection();
-                                }
-                                Err(_) => {
-                                    show_message(
-                                        &mut terminal,
-                                        "Incorrect password!",
-                                        "Error",
-                                        &mut app,
-                                    )?;
-                                    app.state = AppState::SelectUser;
-                                    app.reset_selection();
-                                }
+                    };
+                    // A read-only session writes nothing, so it needs no lock.
+                    if !app.readonly {
+                        match lockfile::acquire(&file_path) {
+                            Ok(None) => {}
+                            Ok(Some(holder)) => {
+                                let notice = in_use_notice(&user_path, &holder);
+                                return Ok(
+                                    app.enter(AppState::JournalInUse(user_path, password, notice))
+                                );
                             }
+                            Err(e) => app.announce(format!("Could not lock the journal: {}", e)),
                         }
-                        AppState::SelectFolder(user_path, _) => {
-                            let username = user_path.trim_end_matches(".clog");
-                            let file_path = app.data_dir.join(&user_path);
-                            add_new_user(&input, file_path.to_str().unwrap());
-                            app.state = AppState::SelectFolder(user_path, input);
-                            app.reset_selection();
+                    }
+                    app.journal = journal;
+                    purge_expired_trash(app, &password, &file_path);
+                    settings::remember_title(&app.state_dir(), &user_path, &app.journal.title).ok();
+                    settings::remember_recent_user(&app.state_dir(), &user_path).ok();
+                    // An editing session that never finished comes first.
+                    if settings::draft_path(&file_path).exists() {
+                        app.resume_last_page = false;
+                        app.startup = Startup::UserList;
+                        return Ok(app.enter(AppState::RecoverDraft(user_path, password)));
+                    }
+                    app.enter(AppState::SelectFolder(user_path.clone(), password.clone()));
+                    app.set_items(top_folder_items(
+                        &metadata,
+                        &app.journal,
+                        &app.writable_day(),
+                    ));
+                    let startup = std::mem::take(&mut app.startup);
+                    // Only a page that can still be edited is reopened: one
+                    // of today or of a topic, not deleted since.
+                    if std::mem::take(&mut app.resume_last_page)
+                        && let Some(page) = settings::load_last_page(&app.state_dir(), &user_path)
+                        && (page.folder == today_str() || app.journal.is_chapter(&page.folder))
+                        && app.is_page_editable(&page.folder, &page.file)
+                        && file_items(&metadata, &page.folder, "", &app.journal)
+                            .iter()
+                            .any(|(file, _)| *file == page.file)
+                    {
+                        let PageRef { folder, file } = page;
+                        app.enter(AppState::SelectFile(
+                            user_path.clone(),
+                            password.clone(),
+                            folder.clone(),
+                        ));
+                        app.state = AppState::EditOrViewFile(
+                            user_path.clone(),
+                            password.clone(),
+                            folder.clone(),
+                            file.clone(),
+                        );
+                        return Ok(Some(SideEffect::OpenPage(
+                            user_path, password, folder, file,
+                        )));
+                    }
+                    match startup {
+                        Startup::UserList => Ok(None),
+                        Startup::Today => {
+                            Ok(app.enter(AppState::SelectFile(user_path, password, today_str())))
                         }
-                        _ => {}
+                        Startup::Timeline => Ok(app.enter(AppState::Timeline(user_path, password))),
                     }
-                    app.input_buffer.clear();
-                } else {
-                    app.input_buffer = input_buffer;
-                    app.state = AppState::SelectUser;
-                    app.reset_selection();
-                    app.input_buffer.clear();
-                }
-            }
-            AppState::AddPagePrompt(user_path, password, folder) => {
-                let help_text = app.get_help_text();
-                let mut input_buffer = app.input_buffer.clone();
-                if let Some(filename) = prompt_input_in_app(
-                    &mut terminal,
-                    "Enter page name:",
-                    &mut input_buffer,
-                    help_text,
-                    &mut app,
-                )? {
-                    app.input_buffer = input_buffer;
-                    match edit_file_with_editor("") {
-                        Ok(content) => {
-                            if !content.trim().is_empty() {
-                                let file_path = app.data_dir.join(&user_path);
-                                add_file(
-                                    &password,
-                                    file_path.to_str().unwrap(),
-                                    &filename,
-                                    &content,
-                                );
-                                show_message(
-                                    &mut terminal,
-                                    &format!("Page '{}' added successfully!", filename),
-                                    "Success",
-                                    &mut app,
-                                )?;
-                            } else {
-                                show_message(
-                                    &mut terminal,
-                                    "Page creation cancelled (empty content)",
-                                    "Info",
-                                    &mut app,
-                                )?;
-                            }
+                }
+                Err(e) => {
+                    let message = unlock_error(app, &user_path, e);
+                    app.show_message("Error", &message);
+                    Ok(app.enter(AppState::SelectUser))
+                }
+            }
+        }
+        SideEffect::ForceUnlock(user_path, password) => {
+            match lockfile::force(&app.data_dir.join(&user_path)) {
+                Ok(()) => Ok(Some(SideEffect::Unlock(user_path, password))),
+                Err(e) => {
+                    app.show_message("Error", &format!("Could not remove the lock: {}", e));
+                    Ok(app.enter(AppState::SelectUser))
+                }
+            }
+        }
+        SideEffect::LoadRecoveries => {
+            app.recovery_files = settings::list_recoveries(&app.state_dir());
+            if app.recovery_files.is_empty() {
+                app.show_message("Info", "Nothing from the editor was kept in the last day");
+                return Ok(app.enter(AppState::SelectUser));
+            }
+            let items = app
+                .recovery_files
+                .iter()
+                .map(|recovery| {
+                    (
+                        recovery.user_file.trim_end_matches(".clog").to_string(),
+                        recovery.saved.map(time_label).unwrap_or_default(),
+                    )
+                })
+                .collect();
+            app.set_items(items);
+            Ok(None)
+        }
+        SideEffect::OpenRecovery(name, mut password) => {
+            let config_dir = app.state_dir();
+            let opened = decrypting(terminal, app, || {
+                settings::load_recovery(&password, &config_dir, &name)
+            })?;
+            password.zeroize();
+            match opened {
+                Ok(draft) => {
+                    app.enter(AppState::ViewRecovery(name));
+                    app.recovery = Some(Recovery {
+                        draft,
+                        saved: None,
+                        compare: false,
+                        scroll: 0,
+                    });
+                    Ok(None)
+                }
+                Err(ClogError::WrongPassword) => {
+                    app.show_message("Error", "Incorrect password!");
+                    Ok(app.enter(AppState::Recoveries))
+                }
+                Err(e) => {
+                    app.show_message("Error", &format!("Could not open the recovery: {}", e));
+                    Ok(app.enter(AppState::Recoveries))
+                }
+            }
+        }
+        SideEffect::DiscardRecovery(name) => {
+            if let Err(e) = settings::discard_recovery(&app.state_dir(), &name) {
+                app.show_message("Error", &format!("Could not delete the recovery: {}", e));
+            }
+            Ok(app.enter(AppState::Recoveries))
+        }
+        SideEffect::CreateUser(user_path, password) => {
+            let file_path = app.data_dir.join(&user_path);
+            // clog_rs would write a fresh journal over the existing one.
+            if let Some(existing) = existing_journal(&app.data_dir, &user_path) {
+                app.show_message(
+                    "Error",
+                    &format!(
+                        "A journal named '{}' already exists. Choose another username.",
+                        existing
+                    ),
+                );
+                return Ok(app.enter(AppState::SelectUser));
+            }
+            match clog::create_journal(&password, &file_path) {
+                Ok(()) => {
+                    if let Err(e) = lockfile::acquire(&file_path) {
+                        app.announce(format!("Could not lock the journal: {}", e));
+                    }
+                    settings::remember_recent_user(&app.state_dir(), &user_path).ok();
+                    Ok(app.enter(AppState::SelectFolder(user_path, password)))
+                }
+                Err(e) => {
+                    app.show_message("Error", &format!("Error creating user: {}", e));
+                    Ok(app.enter(AppState::SelectUser))
+                }
+            }
+        }
+        SideEffect::LoadFolders(user_path, password) => {
+            let file_path = app.data_dir.join(&user_path);
+            match journal_metadata(terminal, app, &password, &file_path) {
+                Ok(metadata) => {
+                    app.set_items(top_folder_items(
+                        &metadata,
+                        &app.journal,
+                        &app.writable_day(),
+                    ));
+                    Ok(None)
+                }
+                Err(e) => {
+                    app.show_message("Error", &e.to_string());
+                    Ok(app.enter(AppState::SelectUser))
+                }
+            }
+        }
+        SideEffect::LoadFiles(user_path, password, folder) => {
+            let file_path = app.data_dir.join(&user_path);
+            match journal_metadata(terminal, app, &password, &file_path) {
+                Ok(metadata) => {
+                    let mut items =
+                        file_items(&metadata, &folder, &app.writable_day(), &app.journal);
+                    for (file, badge) in items.iter_mut().filter(|(file, _)| file != ADD_PAGE_ITEM)
+                    {
+                        if let Some(at) = app.journal.edited_at(&folder, file) {
+                            *badge = format!("{} · edited {}", badge, timestamp_label(at));
                         }
-                        Err(e) => {
-                            show_message(
-                                &mut terminal,
-                                &format!("Error creating page: {}", e),
-                                "Error",
-                                &mut app,
-                            )?;
+                        for tag in app.journal.tags_of(&folder, file) {
+                            badge.push_str(&format!(" #{}", tag));
+                        }
+                        if app.journal.is_locked(&folder, file) {
+                            *badge = format!("🔒 {}", badge);
                         }
                     }
-                    app.state = AppState::SelectFile(user_path, password, folder);
-                    app.reset_selection();
-                    app.input_buffer.clear();
-                } else {
-                    app.input_buffer = input_buffer;
-                    app.state = AppState::SelectFile(user_path, password, folder);
-                    app.reset_selection();
-                    app.input_buffer.clear();
-                }
-            }
-            AppState::SelectFolder(user_path, password) => {
-                let file_path = app.data_dir.join(&user_path);
-                let metadata_str = get_json_metadata(&password, file_path.to_str().unwrap());
-                let metadata: Value = match serde_json::from_str(&metadata_str) {
-                    Ok(m) => m,
-                    Err(_) => {
-                        show_message(&mut terminal, "Error parsing metadata", "Error", &mut app)?;
-                        app.state = AppState::SelectUser;
-                        app.reset_selection();
-                        continue;
+                    let subs = app.journal.sub_chapters_of(&folder);
+                    items.splice(
+                        0..0,
+                        subs.iter()
+                            .map(|sub| (sub.to_string(), SUB_CHAPTER_BADGE.to_string())),
+                    );
+                    if app.journal.is_chapter(&folder) && !app.writable_day().is_empty() {
+                        items.push((NEW_CHAPTER_ITEM.to_string(), String::new()));
                     }
-                };
-
-                let mut folders: Vec<String> = metadata["folders"]
-                    .as_object()
-                    .map(|obj| obj.keys().cloned().collect())
-                    .unwrap_or_default();
-                folders.sort();
-
-                let display_items: Vec<(String, String)> = folders
+                    app.set_items(items);
+                    Ok(None)
+                }
+                Err(e) => {
+                    app.show_message("Error", &e.to_string());
+                    Ok(app.enter(AppState::SelectFolder(user_path, password)))
+                }
+            }
+        }
+        SideEffect::LoadPreview(user_path, password, folder, file) => {
+            let file_path = app.data_dir.join(&user_path);
+            let page = PageRef { folder, file };
+            let content = read_pages(
+                terminal,
+                app,
+                &password,
+                &file_path,
+                std::slice::from_ref(&page),
+            )?
+            .pop()
+            .and_then(Result::ok);
+            app.previews.insert(page.file, content);
+            app.request_redraw();
+            Ok(None)
+        }
+        SideEffect::AddPage(user_path, password, folder, filename, tags) => {
+            let file_path = app.data_dir.join(&user_path);
+            // New pages are stored in today's folder whichever chapter lists
+            // them, so a name used there by another chapter can't be reused.
+            let owner = journal_metadata(terminal, app, &password, &file_path)
+                .ok()
+                .and_then(|metadata| {
+                    page_name_owner(&metadata, &app.journal, &folder, &filename, &today_str())
+                });
+            if let Some(owner) = owner.as_ref().filter(|owner| **owner != folder) {
+                app.show_message(
+                    "Error",
+                    &format!(
+                        "'{}' is already the name of a page in {}, choose another name",
+                        filename,
+                        folder_label(owner)
+                    ),
+                );
+                app.set_input(&filename);
+                app.state = AppState::AddPagePrompt(user_path, password, folder);
+                return Ok(None);
+            }
+            if owner.is_some() && app.journal.is_locked(&folder, &filename) {
+                app.show_message(
+                    "Error",
+                    &format!(
+                        "'{}' is locked, so it can't be replaced. Choose another name.",
+                        filename
+                    ),
+                );
+                app.set_input(&filename);
+                app.state = AppState::AddPagePrompt(user_path, password, folder);
+                return Ok(None);
+            }
+            if owner.is_some() {
+                let question = format!(
+                    "A page named '{}' already exists in {}.\n\n\
+                     Replace its content? It keeps its creation time.\n\
+                     No goes back to choose another name.",
+                    filename,
+                    folder_label(&folder)
+                );
+                app.set_input(&filename);
+                app.announce(format!(
+                    "Will overwrite '{}' in {}",
+                    filename,
+                    folder_label(&folder)
+                ));
+                app.state = AppState::Confirm(
+                    question,
+                    Box::new(SideEffect::ReplacePage(
+                        user_path.clone(),
+                        password.clone(),
+                        folder.clone(),
+                        filename,
+                        tags,
+                    )),
+                    Box::new(AppState::AddPagePrompt(user_path, password, folder)),
+                );
+                return Ok(None);
+            }
+            let write = PageWrite {
+                user_path,
+                password,
+                folder,
+                file: filename,
+                content: String::new(),
+                new_page: true,
+                tags,
+            };
+            write_new_page(terminal, app, write)
+        }
+        SideEffect::AddFolder(user_path, password, name, parent) => {
+            let file_path = app.data_dir.join(&user_path);
+            let mut journal = app.journal.clone();
+            let added = clog::read_metadata(&password, &file_path).and_then(|metadata| {
+                if metadata["folders"].get(&name).is_some() || journal.is_chapter(&name) {
+                    return Ok(false);
+                }
+                settings::add_folder(
+                    &password,
+                    &file_path,
+                    &mut journal,
+                    &name,
+                    parent.as_deref(),
+                )
+                .map(|()| true)
+            });
+            match added {
+                Ok(true) => {
+                    app.journal = journal;
+                    app.show_message("Success", &format!("Chapter '{}' created", name));
+                }
+                Ok(false) => app.show_message(
+                    "Error",
+                    &format!("A chapter named '{}' already exists", name),
+                ),
+                Err(e) => app.show_message("Error", &format!("Error creating chapter: {}", e)),
+            }
+            match parent {
+                Some(parent) => Ok(app.enter(AppState::SelectFile(user_path, password, parent))),
+                None => Ok(app.enter(AppState::SelectFolder(user_path, password))),
+            }
+        }
+        SideEffect::ReplacePage(user_path, password, folder, filename, tags) => {
+            let write = PageWrite {
+                user_path,
+                password,
+                folder,
+                file: filename,
+                content: String::new(),
+                new_page: false,
+                tags,
+            };
+            write_new_page(terminal, app, write)
+        }
+        SideEffect::OpenPage(user_path, password, folder, file) => {
+            let file_path = app.data_dir.join(&user_path);
+            let stored = app.journal.stored_in(&folder, &file);
+            let read = decrypting(terminal, app, || {
+                clog::read_page(&password, &file_path, &file, stored)
+            })?;
+            let content = match read {
+                Ok(content) => content,
+                Err(e) => {
+                    app.show_message("Error", &format!("Error opening page: {}", e));
+                    return Ok(app.enter(AppState::SelectFile(user_path, password, folder)));
+                }
+            };
+
+            if !app.is_page_editable(&folder, &file) {
+                let mut viewer = Viewer::new(&file, &content);
+                let created = journal_metadata(terminal, app, &password, &file_path)
+                    .ok()
+                    .and_then(|metadata| {
+                        let stored = app.journal.stored_in(&folder, &file);
+                        let created_at = metadata["folders"][stored][&file]["created_at"]
+                            .as_str()?
+                            .to_string();
+                        let time = chrono::NaiveTime::parse_from_str(&created_at, "%H:%M:%S")
+                            .map_or(created_at, |time| time.format("%H:%M").to_string());
+                        Some(format!("created {} {}", folder_label(stored), time))
+                    });
+                let edited = app
+                    .journal
+                    .edited_at(&folder, &file)
+                    .map(|at| format!("edited {}", timestamp_label(at)));
+                let locked = app
+                    .journal
+                    .is_locked(&folder, &file)
+                    .then(|| "🔒 locked".to_string());
+                viewer.dates = locked
                     .into_iter()
-                    .map(|folder| (folder, String::new()))
-                    .collect();
-
-                let help_text = app.get_help_text();
-                let mut selected_index = app.selected_index;
-                if let Some(NavigationResult::Selected(folder)) =
-                    select_menu_with_back_and_metadata(
-                        &mut terminal,
-                        "Select Chapter",
-                        &display_items,
-                        &mut selected_index,
-                        help_text,
-                        &mut app,
-                    )?
-                {
-                    app.selected_index = selected_index;
-                    app.state = AppState::SelectFile(user_path, password, folder);
-                    app.reset_selection();
-                } else {
+                    .chain(created)
+                    .chain(edited)
+                    .collect::<Vec<_>>()
+                    .join(" · ");
+                app.viewer = Some(viewer);
+                app.state = AppState::ViewPage(user_path, password, folder, file);
+                return Ok(None);
+            }
+            if app.config.reopen_last_page {
+                let page = PageRef {
+                    folder: folder.clone(),
+                    file: file.clone(),
+                };
+                settings::remember_last_page(&app.state_dir(), &user_path, page).ok();
+            }
+            let write = PageWrite {
+                user_path,
+                password,
+                folder,
+                file,
+                content: String::new(),
+                new_page: false,
+                tags: Vec::new(),
+            };
+            if app.config.inline_editor {
+                app.open_editor(Editor::new(&content), write);
+                return Ok(None);
+            }
+            let edited = edit_file_with_editor(&write.file, &content, app.config.mouse);
+            terminal.clear()?;
+            match edited {
+                Ok(Some(new_content)) => {
+                    let write = PageWrite {
+                        content: new_content,
+                        ..write
+                    };
+                    keep_recovery(terminal, app, &write, &content)?;
+                    Ok(finish_external_edit(app, write, &content))
+                }
+                Ok(None) => {
+                    app.show_message("Info", "Edit discarded");
+                    Ok(app.enter(AppState::SelectFile(
+                        write.user_path,
+                        write.password,
+                        write.folder,
+                    )))
+                }
+                Err(e) => {
+                    app.show_message("Error", &format!("Error editing page: {}", e));
+                    Ok(app.enter(AppState::SelectFile(
+                        write.user_path,
+                        write.password,
+                        write.folder,
+                    )))
+                }
+            }
+        }
+        SideEffect::SaveInlineEdit(write) => {
+            let Some(editor) = app.editor.take() else {
+                return Ok(None);
+            };
+            let write = PageWrite {
+                content: editor.content(),
+                ..write
+            };
+            Ok(finish_edit(app, write, editor.original()))
+        }
+        SideEffect::WritePage(write) => {
+            let file_path = app.data_dir.join(&write.user_path);
+            let today = today_str();
+            let stored = if write.new_page {
+                today.as_str()
+            } else {
+                app.journal.stored_in(&write.folder, &write.file)
+            }
+            .to_string();
+            let owner = app.journal.listed_in(&today, &write.file).to_string();
+            let reused = write.new_page && app.journal.is_deleted(&owner, &write.file);
+            let before = if write.new_page {
+                None
+            } else {
+                clog::read_page(&write.password, &file_path, &write.file, &stored)
+                    .ok()
+                    .map(|content| undo_page(app, &write.folder, &write.file, content))
+            };
+            let written = if reused {
+                // The deleted page is still in the clog, so it is brought back
+                // with the new content instead of being added twice.
+                let mut journal = app.journal.clone();
+                journal
+                    .deleted
+                    .retain(|page| page.folder != owner || page.file != write.file);
+                journal.file_under(&write.folder, &today, &write.file);
+                clog::update_page(
+                    &write.password,
+                    &file_path,
+                    &write.file,
+                    &today,
+                    &write.content,
+                    app.config.compress,
+                )
+                .and_then(|()| clog::read_metadata(&write.password, &file_path))
+                .and_then(|metadata| {
+                    settings::save_settings(&write.password, &file_path, &metadata, &journal)?;
+                    app.journal = journal;
+                    Ok(())
+                })
+            } else if write.new_page {
+                clog::add_page(
+                    &write.password,
+                    &file_path,
+                    &write.file,
+                    &write.content,
+                    app.config.compress,
+                )
+            } else {
+                clog::update_page(
+                    &write.password,
+                    &file_path,
+                    &write.file,
+                    &stored,
+                    &write.content,
+                    app.config.compress,
+                )
+            };
+            let filed = write.new_page && !reused && app.journal.is_chapter(&write.folder);
+            let written = written.and_then(|()| {
+                if write.new_page && write.tags.is_empty() && !filed {
+                    return Ok(());
+                }
+                let mut journal = app.journal.clone();
+                if filed {
+                    journal.file_under(&write.folder, &today, &write.file);
+                }
+                if !write.tags.is_empty() {
+                    journal.set_tags(&write.folder, &write.file, write.tags.clone());
+                }
+                if !write.new_page {
+                    let now = chrono::Local::now().format(TIMESTAMP_FORMAT).to_string();
+                    journal.set_edited(&write.folder, &write.file, Some(now));
+                }
+                let metadata = clog::read_metadata(&write.password, &file_path)?;
+                settings::save_settings(&write.password, &file_path, &metadata, &journal)?;
+                app.journal = journal;
+                Ok(())
+            });
+            if written.is_ok()
+                && let Some(page) = before
+            {
+                app.undo = Some(Undo {
+                    folder: write.folder.clone(),
+                    pages: vec![page],
+                    deleted: false,
+                });
+            }
+            if written.is_ok() && std::mem::take(&mut app.draft_written) {
+                settings::discard_draft(&file_path).ok();
+            }
+            match (written, write.new_page) {
+                (Ok(()), true) => app.show_message(
+                    "Success",
+                    &format!(
+                        "Page '{}' added successfully! ({})",
+                        write.file,
+                        stats::summary(&write.content)
+                    ),
+                ),
+                (Ok(()), false) => app.show_message(
+                    "Success",
+                    &format!(
+                        "Page '{}' updated successfully! ({})",
+                        write.file,
+                        stats::summary(&write.content)
+                    ),
+                ),
+                (Err(e), true) => app.show_message("Error", &format!("Error creating page: {}", e)),
+                (Err(e), false) => app.show_message("Error", &format!("Error saving page: {}", e)),
+            }
+            Ok(app.enter(AppState::SelectFile(
+                write.user_path,
+                write.password,
+                write.folder,
+            )))
+        }
+        SideEffect::SaveJournalInfo(user_path, password, title, description) => {
+            let file_path = app.data_dir.join(&user_path);
+            let journal = JournalSettings {
+                title,
+                description,
+                ..app.journal.clone()
+            };
+            let saved = clog::read_metadata(&password, &file_path)
+                .and_then(|metadata| {
+                    settings::save_settings(&password, &file_path, &metadata, &journal)
+                })
+                .map_err(AppError::from)
+                .and_then(|()| {
+                    settings::remember_title(&app.state_dir(), &user_path, &journal.title)
+                });
+            match saved {
+                Ok(()) => {
+                    app.journal = journal;
+                    app.show_message("Success", "Journal info updated");
+                }
+                Err(e) => app.show_message("Error", &format!("Error saving journal info: {}", e)),
+            }
+            Ok(app.enter(AppState::SelectFolder(user_path, password)))
+        }
+        SideEffect::Resume(mut resume, selected_index) => {
+            let (Some(user_path), Some(password)) = (
+                resume.user_path().map(str::to_string),
+                resume.password_mut(),
+            ) else {
+                return Ok(app.enter(AppState::SelectUser));
+            };
+            let file_path = app.data_dir.join(&user_path);
+            match journal_metadata(terminal, app, password, &file_path) {
+                Ok(metadata) => {
+                    app.journal = match settings::load_settings(password, &file_path, &metadata) {
+                        Ok(journal) => journal,
+                        Err(e) => {
+                            password.zeroize();
+                            app.show_message("Error", &settings_error(&user_path, e));
+                            app.state = AppState::Locked(resume, selected_index);
+                            return Ok(None);
+                        }
+                    };
+                    // The viewer's content went with the lock, so a page that
+                    // was being viewed is read again.
+                    let next = match *resume {
+                        AppState::ViewPage(user_path, password, folder, file) => {
+                            app.enter(AppState::SelectFile(
+                                user_path.clone(),
+                                password.clone(),
+                                folder.clone(),
+                            ));
+                            Some(SideEffect::OpenPage(user_path, password, folder, file))
+                        }
+                        other => app.enter(other),
+                    };
                     app.selected_index = selected_index;
-                    app.state = AppState::SelectUser;
-                    app.reset_selection();
-                }
-            }
-            AppState::SelectFile(user_path, password, folder) => {
-                let file_path = app.data_dir.join(&user_path);
-                let metadata_str = get_json_metadata(&password, file_path.to_str().unwrap());
-                let metadata: Value = match serde_json::from_str(&metadata_str) {
-                    Ok(m) => m,
-                    Err(_) => {
-                        show_message(&mut terminal, "Error parsing metadata", "Error", &mut app)?;
-                        app.state = AppState::SelectFolder(user_path, password);
-                        app.reset_selection();
+                    Ok(next)
+                }
+                Err(e) => {
+                    password.zeroize();
+                    let message = unlock_error(app, &user_path, e);
+                    app.show_message("Error", &message);
+                    app.state = AppState::Locked(resume, selected_index);
+                    Ok(None)
+                }
+            }
+        }
+        SideEffect::SearchChapter(user_path, password, folder, query) => {
+            let file_path = app.data_dir.join(&user_path);
+            let metadata = match journal_metadata(terminal, app, &password, &file_path) {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    app.show_message("Error", &e.to_string());
+                    return Ok(app.enter(AppState::SelectFile(user_path, password, folder)));
+                }
+            };
+            let mut results = Vec::new();
+            let pages: Vec<PageRef> = file_items(&metadata, &folder, "", &app.journal)
+                .into_iter()
+                .map(|(file, _)| PageRef {
+                    folder: folder.clone(),
+                    file,
+                })
+                .collect();
+            let contents = read_pages(terminal, app, &password, &file_path, &pages)?;
+            for (PageRef { file, .. }, content) in pages.into_iter().zip(contents) {
+                let content = match content {
+                    Ok(content) => content,
+                    Err(e) => {
+                        app.show_message("Error", &format!("Error reading page '{}': {}", file, e));
                         continue;
                     }
                 };
-
-                let mut display_items = Vec::new();
-                if let Some(files_obj) = metadata["folders"][folder.as_str()].as_object() {
-                    for (filename, file_data) in files_obj {
-                        let created_at = file_data["created_at"].as_str().unwrap_or("").to_string();
-                        display_items.push((filename.clone(), created_at));
+                for (line, snippet) in search::matching_lines(&content, &query) {
+                    results.push((file.clone(), format!("{}: {}", line, snippet)));
+                }
+            }
+            if results.is_empty() {
+                app.show_message(
+                    "Search",
+                    &format!("No pages in {} mention '{}'", folder_label(&folder), query),
+                );
+                return Ok(app.enter(AppState::SelectFile(user_path, password, folder)));
+            }
+            let next = app.enter(AppState::ChapterResults(user_path, password, folder, query));
+            app.set_items(results);
+            Ok(next)
+        }
+        SideEffect::SearchJournal(user_path, password, query) => {
+            let file_path = app.data_dir.join(&user_path);
+            let metadata = match journal_metadata(terminal, app, &password, &file_path) {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    app.show_message("Error", &e.to_string());
+                    return Ok(app.enter(AppState::SelectFolder(user_path, password)));
+                }
+            };
+            let mut results = Vec::new();
+            let mut hits = Vec::new();
+            let pages: Vec<PageRef> = folder_items(&metadata, &app.journal, "")
+                .into_iter()
+                .flat_map(|(folder, _)| {
+                    file_items(&metadata, &folder, "", &app.journal)
+                        .into_iter()
+                        .map(move |(file, _)| PageRef {
+                            folder: folder.clone(),
+                            file,
+                        })
+                })
+                .collect();
+            let contents = read_pages(terminal, app, &password, &file_path, &pages)?;
+            for (page, content) in pages.into_iter().zip(contents) {
+                let content = match content {
+                    Ok(content) => content,
+                    Err(e) => {
+                        app.show_message(
+                            "Error",
+                            &format!("Error reading page '{}': {}", page_label(&page), e),
+                        );
+                        continue;
                     }
+                };
+                if let Some((_, snippet)) =
+                    search::matching_lines(&content, &query).into_iter().next()
+                {
+                    results.push((page_label(&page), snippet));
+                    hits.push(page);
+                }
+            }
+            if results.is_empty() {
+                app.show_message("Search", &format!("No pages mention '{}'", query));
+                return Ok(app.enter(AppState::SelectFolder(user_path, password)));
+            }
+            let next = app.enter(AppState::SearchResults(user_path, password, query));
+            app.set_items(results);
+            app.search_hits = hits;
+            Ok(next)
+        }
+        SideEffect::SaveTags(user_path, password, folder, file, tags) => {
+            let file_path = app.data_dir.join(&user_path);
+            let mut journal = app.journal.clone();
+            journal.set_tags(&folder, &file, tags);
+            let saved = clog::read_metadata(&password, &file_path).and_then(|metadata| {
+                settings::save_settings(&password, &file_path, &metadata, &journal)
+            });
+            match saved {
+                Ok(()) => app.journal = journal,
+                Err(e) => app.show_message("Error", &format!("Error saving tags: {}", e)),
+            }
+            Ok(app.enter(AppState::SelectFile(user_path, password, folder)))
+        }
+        SideEffect::SetLocked(user_path, password, folder, file, locked) => {
+            let file_path = app.data_dir.join(&user_path);
+            let mut journal = app.journal.clone();
+            journal.set_locked(&folder, &file, locked);
+            let saved = clog::read_metadata(&password, &file_path).and_then(|metadata| {
+                settings::save_settings(&password, &file_path, &metadata, &journal)
+            });
+            match saved {
+                Ok(()) => {
+                    app.journal = journal;
+                    app.announce(if locked {
+                        format!("Locked '{}'", file)
+                    } else {
+                        format!("Unlocked '{}'", file)
+                    });
+                }
+                Err(e) => app.show_message("Error", &format!("Error saving the lock: {}", e)),
+            }
+            Ok(app.enter(AppState::SelectFile(user_path, password, folder)))
+        }
+        SideEffect::SetChapterColor(user_path, password, folder, color) => {
+            let file_path = app.data_dir.join(&user_path);
+            let mut journal = app.journal.clone();
+            journal.set_color(&folder, color);
+            let saved = clog::read_metadata(&password, &file_path).and_then(|metadata| {
+                settings::save_settings(&password, &file_path, &metadata, &journal)
+            });
+            let selected = app.selected_index;
+            let next = app.enter(AppState::SelectFolder(user_path, password));
+            match saved {
+                Ok(()) => {
+                    app.journal = journal;
+                    app.selected_index = selected;
+                }
+                Err(e) => app.show_message("Error", &format!("Error saving the colour: {}", e)),
+            }
+            Ok(next)
+        }
+        SideEffect::ReorderPages(user_path, password, folder, pages, selected) => {
+            let file_path = app.data_dir.join(&user_path);
+            let mut journal = app.journal.clone();
+            journal.reorder(&folder, &pages);
+            let saved = clog::read_metadata(&password, &file_path).and_then(|metadata| {
+                settings::save_settings(&password, &file_path, &metadata, &journal)
+            });
+            let next = app.enter(AppState::SelectFile(user_path, password, folder));
+            match saved {
+                Ok(()) => {
+                    app.journal = journal;
+                    app.selected_index = selected;
+                }
+                Err(e) => app.show_message("Error", &format!("Error saving the order: {}", e)),
+            }
+            Ok(next)
+        }
+        SideEffect::LoadTags(user_path, password) => {
+            let counts = app.journal.tag_counts();
+            if counts.is_empty() {
+                app.show_message("Tags", "No tagged pages yet. Press t on a page to tag it.");
+                return Ok(app.enter(AppState::SelectFolder(user_path, password)));
+            }
+            app.set_items(
+                counts
+                    .into_iter()
+                    .map(|(tag, count)| {
+                        let pages = if count == 1 { "page" } else { "pages" };
+                        (tag, format!("{} {}", count, pages))
+                    })
+                    .collect(),
+            );
+            Ok(None)
+        }
+        SideEffect::LoadTagged(user_path, password, tag) => {
+            let pages = app.journal.tagged(&tag);
+            if pages.is_empty() {
+                return Ok(app.enter(AppState::SelectTag(user_path, password)));
+            }
+            app.set_items(
+                pages
+                    .iter()
+                    .map(|page| (page_label(page), String::new()))
+                    .collect(),
+            );
+            app.search_hits = pages;
+            Ok(None)
+        }
+        SideEffect::LoadTimeline(user_path, password) => {
+            let file_path = app.data_dir.join(&user_path);
+            let metadata = match journal_metadata(terminal, app, &password, &file_path) {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    app.show_message("Error", &e.to_string());
+                    return Ok(app.enter(AppState::SelectFolder(user_path, password)));
+                }
+            };
+            let (pages, items) = all_pages(&metadata, &app.journal)
+                .into_iter()
+                .map(|(page, created_at)| {
+                    let item = (
+                        format!("{}  {}", folder_label(&page.folder), page.file),
+                        created_at,
+                    );
+                    (page, item)
+                })
+                .unzip();
+            app.set_items(items);
+            app.search_hits = pages;
+            Ok(None)
+        }
+        SideEffect::LoadTrash(user_path, password) => {
+            let items = app
+                .journal
+                .trash
+                .iter()
+                .rev()
+                .map(|page| {
+                    (
+                        format!("{}  {}", folder_label(&page.folder), page.file),
+                        format!("deleted {}", timestamp_label(&page.deleted_at)),
+                    )
+                })
+                .collect();
+            app.set_items(items);
+            if app.items.is_empty() {
+                return Ok(app.enter(AppState::SelectFolder(user_path, password)));
+            }
+            Ok(None)
+        }
+        SideEffect::RestoreTrashed(user_path, password, index) => {
+            let file_path = app.data_dir.join(&user_path);
+            let mut journal = app.journal.clone();
+            let file = journal.trash[index].file.clone();
+            let restored = settings::restore_page(
+                &password,
+                &file_path,
+                &mut journal,
+                index,
+                &today_str(),
+                app.config.compress,
+            );
+            match restored {
+                Ok(Ok(chapter)) => {
+                    app.journal = journal;
+                    app.show_message(
+                        "Success",
+                        &format!("Page '{}' restored to {}", file, folder_label(&chapter)),
+                    );
+                }
+                Ok(Err(owner)) => app.show_message(
+                    "Error",
+                    &format!(
+                        "'{}' is already the name of a page in {}; rename it to restore this one",
+                        file,
+                        folder_label(&owner)
+                    ),
+                ),
+                Err(e) => app.show_message("Error", &format!("Error restoring page: {}", e)),
+            }
+            Ok(app.enter(AppState::Trash(user_path, password)))
+        }
+        SideEffect::PurgeTrashed(user_path, password, index) => {
+            let file_path = app.data_dir.join(&user_path);
+            let mut journal = app.journal.clone();
+            let page = journal.trash.remove(index);
+            let purged = settings::erase_attachments(&password, &file_path, &page.attachments)
+                .and_then(|()| clog::read_metadata(&password, &file_path))
+                .and_then(|metadata| {
+                    settings::save_settings(&password, &file_path, &metadata, &journal)
+                });
+            match purged {
+                Ok(()) => {
+                    app.journal = journal;
+                    app.announce(format!("Purged '{}'", page.file));
+                }
+                Err(e) => app.show_message("Error", &format!("Error purging page: {}", e)),
+            }
+            Ok(app.enter(AppState::Trash(user_path, password)))
+        }
+        SideEffect::LoadAttachments(user_path, password, folder, file) => {
+            let mut items: Vec<(String, String)> = app
+                .journal
+                .attachments_of(&folder, &file)
+                .iter()
+                .map(|attachment| {
+                    (
+                        attachment.name.clone(),
+                        format!(
+                            "{} · added {}",
+                            size_label(attachment.size),
+                            timestamp_label(&attachment.added)
+                        ),
+                    )
+                })
+                .collect();
+            if !app.readonly {
+                items.push((ADD_ATTACHMENT_ITEM.to_string(), String::new()));
+            } else if items.is_empty() {
+                app.show_message("Attachments", &format!("'{}' has no attachments", file));
+                return Ok(app.enter(AppState::SelectFile(user_path, password, folder)));
+            }
+            app.set_items(items);
+            Ok(None)
+        }
+        SideEffect::PrepareAttach(user_path, password, folder, file, source) => {
+            let source = export::resolve_destination(&source);
+            let back = AppState::Attachments(
+                user_path.clone(),
+                password.clone(),
+                folder.clone(),
+                file.clone(),
+            );
+            let limit = app.config.attachment_limit_mb.saturating_mul(1024 * 1024);
+            match fs::metadata(&source) {
+                Ok(metadata) if !metadata.is_file() => {
+                    app.show_message("Error", &format!("{} is not a file", source.display()));
+                    Ok(app.enter(back))
+                }
+                Ok(metadata) if metadata.len() > limit => {
+                    app.show_message(
+                        "Error",
+                        &format!(
+                            "{} is {}, over the {} MB attachment limit \
+                             (attachment_limit_mb in config.toml)",
+                            source.display(),
+                            size_label(metadata.len()),
+                            app.config.attachment_limit_mb
+                        ),
+                    );
+                    Ok(app.enter(back))
+                }
+                Ok(metadata) if metadata.len() >= LARGE_ATTACHMENT => {
+                    let question = format!(
+                        "Attach {} ({})?\n\nThe whole journal is rewritten on every save, \
+                         so large attachments make saving slower.",
+                        source.display(),
+                        size_label(metadata.len())
+                    );
+                    app.state = AppState::Confirm(
+                        question,
+                        Box::new(SideEffect::Attach(
+                            user_path, password, folder, file, source,
+                        )),
+                        Box::new(back),
+                    );
+                    Ok(None)
+                }
+                Ok(_) => Ok(Some(SideEffect::Attach(
+                    user_path, password, folder, file, source,
+                ))),
+                Err(e) => {
+                    app.show_message(
+                        "Error",
+                        &format!("Error reading {}: {}", source.display(), e),
+                    );
+                    Ok(app.enter(back))
+                }
+            }
+        }
+        SideEffect::Attach(user_path, password, folder, file, source) => {
+            let file_path = app.data_dir.join(&user_path);
+            let name = source.file_name().map_or_else(
+                || "attachment".to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            );
+            let page = PageRef {
+                folder: folder.clone(),
+                file: file.clone(),
+            };
+            let mut journal = app.journal.clone();
+            let added = match fs::read(&source) {
+                Ok(bytes) => {
+                    let bytes = Zeroizing::new(bytes);
+                    let today = today_str();
+                    let now = chrono::Local::now().format(TIMESTAMP_FORMAT).to_string();
+                    let compress = app.config.compress;
+                    decrypting(terminal, app, || {
+                        settings::add_attachment(
+                            &password,
+                            &file_path,
+                            &mut journal,
+                            &page,
+                            &name,
+                            &bytes,
+                            &today,
+                            &now,
+                            compress,
+                        )
+                        .map_err(AppError::from)
+                    })?
+                }
+                Err(e) => Err(AppError::from(e)),
+            };
+            match added {
+                Ok(()) => {
+                    app.journal = journal;
+                    app.announce(format!("Attached '{}' to '{}'", name, file));
+                }
+                Err(e) => app.show_message("Error", &format!("Error attaching file: {}", e)),
+            }
+            Ok(app.enter(AppState::Attachments(user_path, password, folder, file)))
+        }
+        SideEffect::ExtractAttachment(user_path, password, folder, file, page, directory) => {
+            let file_path = app.data_dir.join(&user_path);
+            let directory = export::resolve_destination(&directory);
+            let Some(attachment) = app
+                .journal
+                .attachments
+                .iter()
+                .find(|attachment| attachment.page == page)
+                .cloned()
+            else {
+                return Ok(app.enter(AppState::Attachments(user_path, password, folder, file)));
+            };
+            // Only the last part of the name, whoever wrote the journal.
+            let name = Path::new(&attachment.name)
+                .file_name()
+                .map_or_else(|| "attachment".into(), |name| name.to_os_string());
+            let destination = directory.join(name);
+            let extracted = decrypting(terminal, app, || {
+                let bytes = Zeroizing::new(clog::read_binary_page(
+                    &password,
+                    &file_path,
+                    &attachment.page,
+                    &attachment.stored,
+                )?);
+                fs::create_dir_all(&directory)?;
+                let mut out = fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&destination)?;
+                out.write_all(&bytes)?;
+                Ok::<(), AppError>(())
+            })?;
+            match extracted {
+                Ok(()) => app.show_message(
+                    "Success",
+                    &format!(
+                        "'{}' written to\n{}",
+                        attachment.name,
+                        destination.display()
+                    ),
+                ),
+                Err(AppError::Io(e)) if e.kind() == io::ErrorKind::AlreadyExists => app
+                    .show_message(
+                        "Error",
+                        &format!("{} already exists", destination.display()),
+                    ),
+                Err(e) => app.show_message("Error", &format!("Error extracting attachment: {}", e)),
+            }
+            Ok(app.enter(AppState::Attachments(user_path, password, folder, file)))
+        }
+        SideEffect::RemoveAttachment(user_path, password, folder, file, page) => {
+            let file_path = app.data_dir.join(&user_path);
+            let mut journal = app.journal.clone();
+            let (removed, kept) = std::mem::take(&mut journal.attachments)
+                .into_iter()
+                .partition::<Vec<_>, _>(|attachment| attachment.page == page);
+            journal.attachments = kept;
+            let erased = settings::erase_attachments(&password, &file_path, &removed)
+                .and_then(|()| clog::read_metadata(&password, &file_path))
+                .and_then(|metadata| {
+                    settings::save_settings(&password, &file_path, &metadata, &journal)
+                });
+            match erased {
+                Ok(()) => {
+                    app.journal = journal;
+                    if let Some(attachment) = removed.first() {
+                        app.announce(format!("Removed '{}'", attachment.name));
+                    }
+                }
+                Err(e) => app.show_message("Error", &format!("Error removing attachment: {}", e)),
+            }
+            Ok(app.enter(AppState::Attachments(user_path, password, folder, file)))
+        }
+        SideEffect::LoadStats(user_path, password) => {
+            let file_path = app.data_dir.join(&user_path);
+            let metadata = match journal_metadata(terminal, app, &password, &file_path) {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    app.show_message("Error", &e.to_string());
+                    return Ok(app.enter(AppState::SelectFolder(user_path, password)));
+                }
+            };
+            let mut journal_stats = JournalStats::default();
+            let mut dates = Vec::new();
+            let mut pages = Vec::new();
+            for (folder, _) in folder_items(&metadata, &app.journal, "") {
+                let files = file_items(&metadata, &folder, "", &app.journal);
+                if files.is_empty() {
+                    continue;
+                }
+                journal_stats.days += 1;
+                dates.extend(parse_folder_date(&folder));
+                pages.extend(files.into_iter().map(|(file, _)| PageRef {
+                    folder: folder.clone(),
+                    file,
+                }));
+            }
+            let contents = read_pages(terminal, app, &password, &file_path, &pages)?;
+            for (page, content) in pages.iter().zip(contents) {
+                journal_stats.entries += 1;
+                match content {
+                    Ok(mut content) => {
+                        journal_stats.words += stats::word_count(&content);
+                        content.zeroize();
+                    }
+                    Err(e) => app.show_message(
+                        "Error",
+                        &format!("Error reading page '{}': {}", page_label(page), e),
+                    ),
+                }
+            }
+            journal_stats.longest_streak = stats::longest_streak(&dates);
+            app.journal_stats = Some(journal_stats);
+            Ok(None)
+        }
+        SideEffect::LoadQuickOpen(user_path, password) => {
+            let file_path = app.data_dir.join(&user_path);
+            match journal_metadata(terminal, app, &password, &file_path) {
+                Ok(metadata) => {
+                    app.all_pages = all_pages(&metadata, &app.journal)
+                        .into_iter()
+                        .map(|(page, _)| page)
+                        .collect();
+                    app.filter_quick_open();
+                    Ok(None)
+                }
+                Err(e) => {
+                    app.show_message("Error", &e.to_string());
+                    Ok(app.enter(AppState::SelectFolder(user_path, password)))
+                }
+            }
+        }
+        SideEffect::DeletePages(user_path, password, folder, files) => {
+            let file_path = app.data_dir.join(&user_path);
+            let before: Vec<UndoPage> = files
+                .iter()
+                .filter_map(|file| {
+                    let stored = app.journal.stored_in(&folder, file);
+                    let content = clog::read_page(&password, &file_path, file, stored).ok()?;
+                    Some(undo_page(app, &folder, file, content))
+                })
+                .collect();
+            let mut journal = app.journal.clone();
+            let now = chrono::Local::now().format(TIMESTAMP_FORMAT).to_string();
+            let deleted = files.iter().try_for_each(|file| {
+                settings::trash_page(&password, &file_path, &mut journal, file, &folder, &now)
+            });
+            if deleted.is_ok() || journal.deleted.len() > app.journal.deleted.len() {
+                app.journal = journal;
+            }
+            let pages: Vec<UndoPage> = before
+                .into_iter()
+                .filter(|page| app.journal.is_deleted(&folder, &page.file))
+                .collect();
+            if !pages.is_empty() {
+                app.undo = Some(Undo {
+                    folder: folder.clone(),
+                    pages,
+                    deleted: true,
+                });
+            }
+            match deleted {
+                Ok(()) if files.len() == 1 => {
+                    app.show_message("Success", &format!("Page '{}' deleted", files[0]))
+                }
+                Ok(()) => app.show_message("Success", &format!("Deleted {} pages", files.len())),
+                Err(e) => app.show_message("Error", &format!("Error deleting page: {}", e)),
+            }
+            Ok(app.enter(AppState::SelectFile(user_path, password, folder)))
+        }
+        SideEffect::Undo(user_path, password) => {
+            let Some(undo) = undo else {
+                return Ok(None);
+            };
+            let file_path = app.data_dir.join(&user_path);
+            let mut journal = app.journal.clone();
+            // Deleted pages are still in the clog with their creation time,
+            // so both kinds of change are undone by writing the old content
+            // back and restoring what the settings held for the page.
+            let restored = undo
+                .pages
+                .iter()
+                .try_for_each(|page| {
+                    let stored = journal.stored_in(&undo.folder, &page.file).to_string();
+                    clog::update_page(
+                        &password,
+                        &file_path,
+                        &page.file,
+                        &stored,
+                        &page.content,
+                        app.config.compress,
+                    )?;
+                    journal.deleted.retain(|deleted| {
+                        deleted.folder != undo.folder || deleted.file != page.file
+                    });
+                    if undo.deleted
+                        && let Some(trashed) = journal.untrash(&undo.folder, &page.file)
+                    {
+                        journal.attachments.extend(trashed.attachments);
+                    }
+                    journal.set_tags(&undo.folder, &page.file, page.tags.clone());
+                    journal.set_edited(&undo.folder, &page.file, page.edited.clone());
+                    Ok(())
+                })
+                .and_then(|()| {
+                    let metadata = clog::read_metadata(&password, &file_path)?;
+                    settings::save_settings(&password, &file_path, &metadata, &journal)
+                });
+            let folder = undo.folder.clone();
+            match restored {
+                Ok(()) => {
+                    app.journal = journal;
+                    app.show_message("Success", &format!("Undid {}", undo.describe()));
+                }
+                Err(e) => {
+                    app.show_message(
+                        "Error",
+                        &format!("Error undoing {}: {}", undo.describe(), e),
+                    );
+                    app.undo = Some(undo);
+                }
+            }
+            Ok(app.enter(AppState::SelectFile(user_path, password, folder)))
+        }
+        SideEffect::RenamePage(user_path, password, folder, old_name, new_name) => {
+            let file_path = app.data_dir.join(&user_path);
+            let mut journal = app.journal.clone();
+            let today = today_str();
+            let renamed = clog::read_metadata(&password, &file_path).and_then(|metadata| {
+                // The copy is stored in today's folder, so the name has to be
+                // free there as well as in the chapter.
+                if let Some(owner) =
+                    page_name_owner(&metadata, &journal, &folder, &new_name, &today)
+                {
+                    return Ok(Err(owner));
+                }
+                settings::rename_page(
+                    &password,
+                    &file_path,
+                    &mut journal,
+                    &folder,
+                    &old_name,
+                    &new_name,
+                    &today,
+                    app.config.compress,
+                )
+                .map(Ok)
+            });
+            match renamed {
+                Ok(Ok(())) => {
+                    app.journal = journal;
+                    app.show_message(
+                        "Success",
+                        &format!("Page '{}' renamed to '{}'", old_name, new_name),
+                    );
+                }
+                Ok(Err(owner)) => app.show_message(
+                    "Error",
+                    &format!(
+                        "A page named '{}' already exists in {}",
+                        new_name,
+                        folder_label(&owner)
+                    ),
+                ),
+                Err(e) => app.show_message("Error", &format!("Error renaming page: {}", e)),
+            }
+            Ok(app.enter(AppState::SelectFile(user_path, password, folder)))
+        }
+        SideEffect::DuplicatePage(user_path, password, folder, file, name) => {
+            let file_path = app.data_dir.join(&user_path);
+            let mut journal = app.journal.clone();
+            let today = today_str();
+            // The copy is a new page, so it goes where Add Page would put it:
+            // under the topic being looked at, otherwise into today.
+            let chapter = if journal.is_chapter(&folder) {
+                folder.clone()
+            } else {
+                today.clone()
+            };
+            let copied = clog::read_metadata(&password, &file_path).and_then(|metadata| {
+                if let Some(owner) = page_name_owner(&metadata, &journal, &chapter, &name, &today) {
+                    return Ok(Err(owner));
+                }
+                let stored = journal.stored_in(&folder, &file);
+                let mut content = clog::read_page(&password, &file_path, &file, stored)?;
+                let added = settings::add_or_reuse_page(
+                    &password,
+                    &file_path,
+                    &mut journal,
+                    &name,
+                    &content,
+                    &today,
+                    app.config.compress,
+                );
+                content.zeroize();
+                added?;
+                journal.file_under(&chapter, &today, &name);
+                if journal != app.journal {
+                    let metadata = clog::read_metadata(&password, &file_path)?;
+                    settings::save_settings(&password, &file_path, &metadata, &journal)?;
+                }
+                Ok(Ok(()))
+            });
+            match copied {
+                Ok(Ok(())) => {
+                    app.journal = journal;
+                    app.show_message(
+                        "Success",
+                        &format!(
+                            "Copied '{}' to '{}' in {}",
+                            file,
+                            name,
+                            folder_label(&chapter)
+                        ),
+                    );
+                    Ok(app.enter(AppState::SelectFile(user_path, password, chapter)))
+                }
+                Ok(Err(owner)) => {
+                    app.show_message(
+                        "Error",
+                        &format!(
+                            "'{}' is already the name of a page in {}, choose another name",
+                            name,
+                            folder_label(&owner)
+                        ),
+                    );
+                    let next = app.enter(AppState::InputPrompt(
+                        format!("Name for the copy of '{}':", file),
+                        Box::new(AppState::DuplicatePage(user_path, password, folder, file)),
+                    ));
+                    app.set_input(&name);
+                    Ok(next)
+                }
+                Err(e) => {
+                    app.show_message("Error", &format!("Error copying page: {}", e));
+                    Ok(app.enter(AppState::SelectFile(user_path, password, folder)))
+                }
+            }
+        }
+        SideEffect::PrepareExport(user_path, password, destination) => {
+            let file_path = app.data_dir.join(&user_path);
+            match journal_metadata(terminal, app, &password, &file_path) {
+                Ok(metadata) => {
+                    let destination = export::resolve_destination(&destination);
+                    let (days, pages) = export::journal_size(&metadata, &app.journal);
+                    let question = format!(
+                        "Export {} pages from {} days as Markdown to\n{}\n\nExisting day files there are overwritten.",
+                        pages,
+                        days,
+                        destination.display()
+                    );
+                    app.announce(format!(
+                        "Will write {} day files to {}",
+                        days,
+                        destination.display()
+                    ));
+                    app.state = AppState::Confirm(
+                        question,
+                        Box::new(SideEffect::Export(
+                            user_path.clone(),
+                            password.clone(),
+                            destination,
+                        )),
+                        Box::new(AppState::SelectFolder(user_path, password)),
+                    );
+                    Ok(None)
+                }
+                Err(e) => {
+                    app.show_message("Error", &format!("Error exporting journal: {}", e));
+                    Ok(app.enter(AppState::SelectFolder(user_path, password)))
+                }
+            }
+        }
+        SideEffect::Export(user_path, password, destination) => {
+            let file_path = app.data_dir.join(&user_path);
+            match journal_metadata(terminal, app, &password, &file_path) {
+                Ok(metadata) => {
+                    let report = export::export_markdown(
+                        &password,
+                        &file_path,
+                        &metadata,
+                        &app.journal,
+                        &destination,
+                    );
+                    let summary = with_failures(
+                        format!(
+                            "Exported {} pages to {}",
+                            report.exported,
+                            destination.display()
+                        ),
+                        &report.failed,
+                    );
+                    let title = if report.failed.is_empty() {
+                        "Success"
+                    } else {
+                        "Export finished with errors"
+                    };
+                    app.show_message(title, &summary);
+                }
+                Err(e) => app.show_message("Error", &format!("Error exporting journal: {}", e)),
+            }
+            Ok(app.enter(AppState::SelectFolder(user_path, password)))
+        }
+        SideEffect::PrepareRestore(user_path) => {
+            let backup = clog::backup_path(&app.data_dir.join(&user_path));
+            if !backup.is_file() {
+                app.show_message(
+                    "Error",
+                    &format!(
+                        "'{}' is damaged and there is no backup ({}) to restore it from.",
+                        user_path,
+                        backup.display()
+                    ),
+                );
+                return Ok(None);
+            }
+            app.announce(format!(
+                "Will replace '{}' with {}",
+                user_path,
+                backup.display()
+            ));
+            app.state = AppState::Confirm(
+                format!(
+                    "'{}' is damaged.\n\nRestore it from\n{}?\n\nThe damaged file is kept.",
+                    user_path,
+                    backup.display()
+                ),
+                Box::new(SideEffect::RestoreBackup(user_path)),
+                Box::new(AppState::SelectUser),
+            );
+            Ok(None)
+        }
+        SideEffect::RestoreBackup(user_path) => {
+            match clog::restore_backup(&app.data_dir.join(&user_path)) {
+                Ok(corrupt) => {
+                    app.damaged.remove(&user_path);
+                    app.show_message(
+                        "Success",
+                        &format!(
+                            "Restored '{}' from its backup.\nThe damaged file was moved to {}",
+                            user_path,
+                            corrupt.display()
+                        ),
+                    );
                 }
-
-                let today_string = today_str();
-                if folder == today_string {
-                    display_items.push(("Add Page".to_string(), String::new()));
+                Err(e) => app.show_message("Error", &format!("Error restoring backup: {}", e)),
+            }
+            Ok(app.enter(AppState::SelectUser))
+        }
+        SideEffect::PrepareImport(user_path, password, source) => {
+            let source = export::resolve_destination(&source);
+            match import::importable_files(&source) {
+                Ok(files) if files.is_empty() => {
+                    app.show_message(
+                        "Import",
+                        &format!("No .txt or .md files in {}", source.display()),
+                    );
+                    Ok(app.enter(AppState::SelectFolder(user_path, password)))
                 }
-
-                let help_text = app.get_help_text();
-                let mut selected_index = app.selected_index;
-                if let Some(NavigationResult::Selected(file)) = select_menu_with_back_and_metadata(
-                    &mut terminal,
-                    "Select Page",
-                    &display_items,
-                    &mut selected_index,
-                    help_text,
-                    &mut app,
-                )? {
-                    app.selected_index = selected_index;
-                    if file == "Add Page" {
-                        app.state = AppState::AddPagePrompt(user_path, password, folder);
-                        app.input_buffer.clear();
-                    } else {
-                        app.state = AppState::EditOrViewFile(user_path, password, folder, file);
-                    }
-                } else {
-                    app.selected_index = selected_index;
-                    app.state = AppState::SelectFolder(user_path, password);
-                    app.reset_selection();
-                }
-            }
-            AppState::EditOrViewFile(user_path, password, folder, file) => {
-                let file_path = app.data_dir.join(&user_path);
-                let content =
-                    get_file_content(&password, file_path.to_str().unwrap(), &file, &folder);
-
-                let today_string = today_str();
-                if folder != today_string {
-                    show_message(
-                        &mut terminal,
-                        &format!("[READ-ONLY] Content of {}:\n\n{}", file, content),
-                        "View Page",
-                        &mut app,
-                    )?;
-                } else {
-                    match edit_file_with_editor(&content) {
-                        Ok(new_content) => {
-                            if new_content != content {
-                                let file_path = app.data_dir.join(&user_path);
-                                update_file_content(
-                                    &password,
-                                    file_path.to_str().unwrap(),
-                                    &file,
-                                    &folder,
-                                    &new_content,
-                                );
-                                show_message(
-                                    &mut terminal,
-                                    &format!("Page '{}' updated successfully!", file),
-                                    "Success",
-                                    &mut app,
-                                )?;
-                            } else {
-                                show_message(
-                                    &mut terminal,
-                                    "No changes made to page",
-                                    "Info",
-                                    &mut app,
-                                )?;
-                            }
-                        }
-                        Err(e) => {
-                            show_message(
-                                &mut terminal,
-                                &format!("Error editing page: {}", e),
-                                "Error",
-                                &mut app,
-                            )?;
+                Ok(files) => {
+                    let question = format!(
+                        "Import {} files from\n{}\n\ninto today's chapter ({})?",
+                        files.len(),
+                        source.display(),
+                        folder_label(&today_str())
+                    );
+                    app.announce(format!(
+                        "Will add {} pages to {}",
+                        files.len(),
+                        folder_label(&today_str())
+                    ));
+                    app.state = AppState::Confirm(
+                        question,
+                        Box::new(SideEffect::Import(
+                            user_path.clone(),
+                            password.clone(),
+                            source,
+                        )),
+                        Box::new(AppState::SelectFolder(user_path, password)),
+                    );
+                    Ok(None)
+                }
+                Err(e) => {
+                    app.show_message(
+                        "Error",
+                        &format!("Error reading {}: {}", source.display(), e),
+                    );
+                    Ok(app.enter(AppState::SelectFolder(user_path, password)))
+                }
+            }
+        }
+        SideEffect::Import(user_path, password, source) => {
+            let file_path = app.data_dir.join(&user_path);
+            let imported = import::importable_files(&source)
+                .map_err(AppError::from)
+                .and_then(|files| {
+                    let metadata = clog::read_metadata(&password, &file_path)?;
+                    Ok(import::import_files(
+                        &password,
+                        &file_path,
+                        &metadata,
+                        &today_str(),
+                        &files,
+                        &app.config,
+                    ))
+                });
+            match imported {
+                Ok(report) => {
+                    let mut summary = format!(
+                        "Imported {} files into {}",
+                        report.imported,
+                        folder_label(&today_str())
+                    );
+                    if !report.skipped.is_empty() {
+                        summary.push_str(&format!("\n\n{} skipped:", report.skipped.len()));
+                        for skipped in &report.skipped {
+                            summary.push_str(&format!("\n- {}", skipped));
                         }
                     }
+                    app.show_message("Import", &summary);
                 }
-                app.state = AppState::SelectFile(user_path, password, folder);
-                app.reset_selection();
+                Err(e) => app.show_message("Error", &format!("Error importing files: {}", e)),
             }
-            AppState::Done => {
-                show_message(
-                    &mut terminal,
-                    "Operation completed. Press any key to exit.",
-                    "Done",
-                    &mut app,
-                )?;
-                break;
+            Ok(app.enter(AppState::SelectFolder(user_path, password)))
+        }
+        SideEffect::SharePage(user_path, password, folder, file, destination) => {
+            // Caught before a passphrase is made up for a file that can't
+            // be written.
+            if destination.exists() {
+                app.show_message(
+                    "Error",
+                    &format!(
+                        "Error sharing page: {} already exists",
+                        destination.display()
+                    ),
+                );
+                return Ok(app.enter(AppState::SelectFile(user_path, password, folder)));
             }
-            _ => unreachable!(),
+            Ok(app.enter(AppState::InputPrompt(
+                "Passphrase for the shared page:".to_string(),
+                Box::new(AppState::ExportPage(
+                    user_path,
+                    password,
+                    folder,
+                    file,
+                    Some(destination),
+                )),
+            )))
         }
-    }
-    disable_raw_mode()?;
-    execute!(io::stdout(), LeaveAlternateScreen)?;
-    Ok(())
-}
-
-fn list_clog_files(dir: &std::path::Path) -> Vec<String> {
-    let mut result = vec![];
-    if let Ok(paths) = fs::read_dir(dir) {
-        for path in paths.flatten() {
-            let path = path.path();
-            if path.extension().map_or(false, |ext| ext == "clog") {
-                if let Some(filename) = path.file_name() {
-                    result.push(filename.to_string_lossy().to_string());
-                }
+        SideEffect::ExportPage(user_path, password, folder, file, destination, mut passphrase) => {
+            let file_path = app.data_dir.join(&user_path);
+            let stored = app.journal.stored_in(&folder, &file).to_string();
+            let shared = decrypting(terminal, app, || {
+                let mut content = clog::read_page(&password, &file_path, &file, &stored)?;
+                let written = clog::write_shared_page(&passphrase, &destination, &file, &content);
+                content.zeroize();
+                written
+            })?;
+            passphrase.zeroize();
+            match shared {
+                Ok(()) => app.show_message(
+                    "Success",
+                    &format!(
+                        "'{}' written to\n{}\n\nIt opens with the passphrase only.",
+                        file,
+                        destination.display()
+                    ),
+                ),
+                Err(e) => app.show_message("Error", &format!("Error sharing page: {}", e)),
             }
+            Ok(app.enter(AppState::SelectFile(user_path, password, folder)))
         }
-    }
-    result
-}
-
-fn get_user_creation_date(file_path: &std::path::Path) -> Option<String> {
-    if !file_path.exists() {
-        return None;
-    }
-
-    let metadata = fs::metadata(file_path).ok()?;
-    let time = metadata.created().or_else(|_| metadata.modified()).ok()?;
-    let datetime = time.duration_since(std::time::UNIX_EPOCH).ok()?;
-    let timestamp = datetime.as_secs();
-    let naive_datetime = chrono::NaiveDateTime::from_timestamp_opt(timestamp as i64, 0)?;
-    let datetime: chrono::DateTime<chrono::Local> =
-        chrono::DateTime::from_naive_utc_and_offset(naive_datetime, *chrono::Local::now().offset());
-    Some(datetime.format("%d/%m/%Y %H:%M").to_string())
-}
-
-fn today_str() -> String {
-    Local::now().format("%d/%m/%Y").to_string()
-}
-
-#[derive(Debug)]
-enum NavigationResult {
-    Selected(String),
-    Back,
-}
-
-fn render_menu_ui(
-    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
-    title: &str,
-    items: &[(String, String)],
-    selected_index: usize,
-    help_text: &str,
-    show_back: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
-    terminal.draw(|f| {
-        let size = f.area();
-        let main_chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Min(1), Constraint::Length(3)])
-            .split(size);
-
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .margin(1)
-            .constraints([Constraint::Length(3), Constraint::Min(1)])
-            .split(main_chunks[0]);
-
-        let title_widget = Paragraph::new(title)
-            .style(
-                Style::default()
-                    .fg(Color::Cyan)
-                    .add_modifier(Modifier::BOLD),
-            )
-            .alignment(Alignment::Center)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Cyan)),
-            );
-        f.render_widget(title_widget, chunks[0]);
-
-        if !items.is_empty() {
-            let list_items: Vec<ListItem> = items
-                .iter()
-                .enumerate()
-                .map(|(i, (item, metadata))| {
-                    let line = if metadata.is_empty() {
-                        Line::from(vec![Span::raw(item)])
-                    } else {
-                        Line::from(vec![
-                            Span::raw(item),
-                            Span::raw(" "),
-                            Span::styled(
-                                format!("[{}]", metadata),
-                                Style::default()
-                                    .fg(Color::Gray)
-                                    .add_modifier(Modifier::ITALIC),
+        SideEffect::CopyChapter(user_path, password, folder) => {
+            match read_chapter(terminal, app, &user_path, &password, &folder)? {
+                Ok(chapter) => match clipboard::copy(&chapter.markdown) {
+                    Ok(via) => app.show_message(
+                        "Copied!",
+                        &with_failures(
+                            format!(
+                                "{} page{} of {} copied with {}",
+                                chapter.pages.len(),
+                                if chapter.pages.len() == 1 { "" } else { "s" },
+                                folder_label(&folder),
+                                via
                             ),
-                        ])
-                    };
-
-                    if i == selected_index {
-                        ListItem::new(line).style(
-                            Style::default()
-                                .bg(Color::Blue)
-                                .fg(Color::White)
-                                .add_modifier(Modifier::BOLD),
-                        )
-                    } else {
-                        ListItem::new(line).style(Style::default().fg(Color::White))
+                            &chapter.failed,
+                        ),
+                    ),
+                    Err(e) => {
+                        app.show_message("Error", &format!("Could not copy the chapter: {}", e))
                     }
-                })
-                .collect();
-
-            let list = List::new(list_items)
-                .block(
-                    Block::default()
-                        .title("Options")
-                        .borders(Borders::ALL)
-                        .border_style(Style::default().fg(Color::Green)),
-                )
-                .highlight_style(Style::default().add_modifier(Modifier::BOLD))
-                .highlight_symbol("► ");
-
-            let mut state = ListState::default();
-            state.select(Some(selected_index));
-            f.render_stateful_widget(list, chunks[1], &mut state);
-        } else if show_back {
-            let empty_msg = Paragraph::new("No items available")
-                .style(Style::default().fg(Color::Gray))
-                .alignment(Alignment::Center)
-                .block(
-                    Block::default()
-                        .title("Options")
-                        .borders(Borders::ALL)
-                        .border_style(Style::default().fg(Color::Green)),
-                );
-            f.render_widget(empty_msg, chunks[1]);
+                },
+                Err(e) => app.show_message("Error", &format!("Error reading chapter: {}", e)),
+            }
+            Ok(None)
         }
-
-        let help_widget = Paragraph::new(help_text)
-            .style(Style::default().fg(Color::Yellow))
-            .alignment(Alignment::Center)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title("Controls")
-                    .border_style(Style::default().fg(Color::Yellow)),
-            );
-        f.render_widget(help_widget, main_chunks[1]);
-    })?;
-    Ok(())
-}
-
-fn handle_menu_input(
-    selected_index: &mut usize,
-    items_len: usize,
-    allow_back: bool,
-) -> Result<Option<MenuAction>, Box<dyn std::error::Error>> {
-    if event::poll(Duration::from_millis(16))? {
-        if let Event::Key(key) = event::read()? {
-            // Fix Windows double keypress issue
-            if key.kind != KeyEventKind::Press {
-                return Ok(None);
+        SideEffect::PrepareExportChapter(user_path, password, folder, destination) => {
+            let file_path = app.data_dir.join(&user_path);
+            match journal_metadata(terminal, app, &password, &file_path) {
+                Ok(metadata) => {
+                    let destination = export::resolve_destination(&destination);
+                    let pages = file_items(&metadata, &folder, "", &app.journal).len();
+                    let question = format!(
+                        "Export {} page{} of {} as Markdown to\n{}",
+                        pages,
+                        if pages == 1 { "" } else { "s" },
+                        folder_label(&folder),
+                        destination.display()
+                    );
+                    app.state = AppState::Confirm(
+                        question,
+                        Box::new(SideEffect::ExportChapter(
+                            user_path.clone(),
+                            password.clone(),
+                            folder.clone(),
+                            destination,
+                        )),
+                        Box::new(AppState::SelectFile(user_path, password, folder)),
+                    );
+                    Ok(None)
+                }
+                Err(e) => {
+                    app.show_message("Error", &format!("Error exporting chapter: {}", e));
+                    Ok(app.enter(AppState::SelectFile(user_path, password, folder)))
+                }
             }
-
-            match key.code {
-                KeyCode::Up | KeyCode::Char('k') => {
-                    if *selected_index > 0 {
-                        *selected_index -= 1;
-                    } else {
-                        *selected_index = items_len.saturating_sub(1);
+        }
+        SideEffect::ExportChapter(user_path, password, folder, destination) => {
+            let written = read_chapter(terminal, app, &user_path, &password, &folder)?
+                .map_err(|e| e.to_string())
+                .and_then(|chapter| {
+                    fs::OpenOptions::new()
+                        .write(true)
+                        .create_new(true)
+                        .open(&destination)
+                        .and_then(|mut out| out.write_all(chapter.markdown.as_bytes()))
+                        .map(|()| chapter)
+                        .map_err(|e| match e.kind() {
+                            io::ErrorKind::AlreadyExists => {
+                                format!("{} already exists", destination.display())
+                            }
+                            _ => e.to_string(),
+                        })
+                });
+            match written {
+                Ok(chapter) => app.show_message(
+                    "Success",
+                    &with_failures(
+                        format!(
+                            "{} page{} of {} written to\n{}",
+                            chapter.pages.len(),
+                            if chapter.pages.len() == 1 { "" } else { "s" },
+                            folder_label(&folder),
+                            destination.display()
+                        ),
+                        &chapter.failed,
+                    ),
+                ),
+                Err(e) => app.show_message("Error", &format!("Error exporting chapter: {}", e)),
+            }
+            Ok(app.enter(AppState::SelectFile(user_path, password, folder)))
+        }
+        SideEffect::ImportPage(user_path, password, folder, source, mut passphrase) => {
+            let file_path = app.data_dir.join(&user_path);
+            let source = export::resolve_destination(&source);
+            let mut journal = app.journal.clone();
+            let today = today_str();
+            // Like Add Page: under the topic being looked at, otherwise today.
+            let chapter = if journal.is_chapter(&folder) {
+                folder.clone()
+            } else {
+                today.clone()
+            };
+            let opened = decrypting(terminal, app, || {
+                clog::read_shared_page(&passphrase, &source)
+            })?;
+            passphrase.zeroize();
+            let imported = opened.and_then(|(name, mut content)| {
+                let added = clog::read_metadata(&password, &file_path).and_then(|metadata| {
+                    if let Some(owner) =
+                        page_name_owner(&metadata, &journal, &chapter, &name, &today)
+                    {
+                        return Ok(Err(owner));
                     }
-                }
-                KeyCode::Down | KeyCode::Char('j') => {
-                    if *selected_index < items_len.saturating_sub(1) {
-                        *selected_index += 1;
-                    } else {
-                        *selected_index = 0;
+                    settings::add_or_reuse_page(
+                        &password,
+                        &file_path,
+                        &mut journal,
+                        &name,
+                        &content,
+                        &today,
+                        app.config.compress,
+                    )?;
+                    journal.file_under(&chapter, &today, &name);
+                    if journal != app.journal {
+                        let metadata = clog::read_metadata(&password, &file_path)?;
+                        settings::save_settings(&password, &file_path, &metadata, &journal)?;
                     }
+                    Ok(Ok(()))
+                });
+                content.zeroize();
+                added.map(|added| (name, added))
+            });
+            match imported {
+                Ok((name, Ok(()))) => {
+                    app.journal = journal;
+                    app.show_message(
+                        "Success",
+                        &format!("Shared page '{}' added to {}", name, folder_label(&chapter)),
+                    );
+                    return Ok(app.enter(AppState::SelectFile(user_path, password, chapter)));
                 }
-                KeyCode::Enter => {
-                    if items_len > 0 {
-                        return Ok(Some(MenuAction::Select));
+                Ok((name, Err(owner))) => app.show_message(
+                    "Error",
+                    &format!(
+                        "'{}' is already the name of a page in {}; rename that page first",
+                        name,
+                        folder_label(&owner)
+                    ),
+                ),
+                Err(ClogError::WrongPassword) => app.show_message(
+                    "Error",
+                    &format!("Wrong passphrase for {}", source.display()),
+                ),
+                Err(ClogError::Corrupt(reason)) => app.show_message(
+                    "Error",
+                    &format!("{} can't be imported: {}", source.display(), reason),
+                ),
+                Err(e) => app.show_message("Error", &format!("Error importing page: {}", e)),
+            }
+            Ok(app.enter(AppState::SelectFile(user_path, password, folder)))
+        }
+        SideEffect::Copy(text) => {
+            match clipboard::copy(&text) {
+                Ok(via) => app.show_message("Copied!", &format!("Page copied with {}", via)),
+                Err(e) => app.show_message("Error", &format!("Could not copy the page: {}", e)),
+            }
+            Ok(None)
+        }
+        SideEffect::LoadDictionary => {
+            match viewer::load_dictionary(app.config.dictionary.as_deref()) {
+                Ok(words) => app.dictionary = Some(words),
+                Err(e) => {
+                    if let Some(viewer) = app.viewer.as_mut() {
+                        viewer.spellcheck = false;
                     }
+                    app.show_message("Error", &format!("Spellcheck unavailable: {}", e));
                 }
-                KeyCode::Char('b') | KeyCode::Esc if allow_back => {
-                    return Ok(Some(MenuAction::Back));
-                }
-                KeyCode::Char('q') => std::process::exit(0),
-                _ => {}
             }
+            Ok(None)
         }
+        SideEffect::Quit => Ok(None),
     }
-    Ok(None)
 }
 
-enum MenuAction {
-    Select,
-    Back,
+/// The pages of a chapter as Markdown, decrypted with the spinner up.
+fn read_chapter<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    user_path: &str,
+    password: &str,
+    folder: &str,
+) -> io::Result<Result<export::ChapterMarkdown, ClogError>> {
+    let file_path = app.data_dir.join(user_path);
+    let metadata = match journal_metadata(terminal, app, password, &file_path) {
+        Ok(metadata) => metadata,
+        Err(e) => return Ok(Err(e)),
+    };
+    let journal = app.journal.clone();
+    let metadata: &Value = &metadata;
+    decrypting(terminal, app, || {
+        Ok(export::chapter_markdown(
+            password, &file_path, metadata, &journal, folder,
+        ))
+    })
 }
 
-fn select_menu_with_metadata(
-    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
-    title: &str,
-    items: &[(String, String)],
-    selected_index: &mut usize,
-    help_text: &str,
-    app: &mut App,
-) -> Result<Option<String>, Box<dyn std::error::Error>> {
-    loop {
-        if app.should_render() {
-            render_menu_ui(terminal, title, items, *selected_index, help_text, false)?;
+/// The summary followed by the pages that couldn't be read, if any.
+fn with_failures(mut summary: String, failed: &[String]) -> String {
+    if !failed.is_empty() {
+        summary.push_str(&format!("\n\n{} failed:", failed.len()));
+        for failure in failed {
+            summary.push_str(&format!("\n- {}", failure));
         }
+    }
+    summary
+}
 
-        if let Some(action) = handle_menu_input(selected_index, items.len(), false)? {
-            match action {
-                MenuAction::Select => {
-                    if !items.is_empty() {
-                        return Ok(Some(items[*selected_index].0.clone()));
-                    }
-                }
-                MenuAction::Back => {} // Not used in this function
-            }
+/// Opens an empty editor for a page that is about to be written, either a
+/// new one or one whose content is being replaced.
+fn write_new_page<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    write: PageWrite,
+) -> Result<Option<SideEffect>, AppError> {
+    let start = match app.writing_prompt() {
+        Some(prompt) if app.config.writing_prompt == WritingPrompt::Prefill => {
+            prompts::comment(prompt)
+        }
+        _ => String::new(),
+    };
+    if app.config.inline_editor {
+        let mut editor = Editor::new(&start);
+        // Writing starts under the prompt.
+        editor.row = editor.lines.len() - 1;
+        app.open_editor(editor, write);
+        return Ok(None);
+    }
+    let edited = edit_file_with_editor(&write.file, &start, app.config.mouse);
+    terminal.clear()?;
+    match edited {
+        Ok(Some(content)) => {
+            let write = PageWrite { content, ..write };
+            keep_recovery(terminal, app, &write, &start)?;
+            Ok(finish_external_edit(app, write, &start))
+        }
+        Ok(None) => {
+            app.show_message("Info", "New page discarded");
+            Ok(app.enter(AppState::SelectFile(
+                write.user_path,
+                write.password,
+                write.folder,
+            )))
+        }
+        Err(e) => {
+            app.show_message("Error", &format!("Error creating page: {}", e));
+            Ok(app.enter(AppState::SelectFile(
+                write.user_path,
+                write.password,
+                write.folder,
+            )))
         }
     }
 }
 
-fn select_menu_with_back_and_metadata(
-    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
-    title: &str,
-    items: &[(String, String)],
-    selected_index: &mut usize,
-    help_text: &str,
+/// The journal's metadata: from the store when it is there, otherwise
+/// decrypted behind the spinner and kept in the store.
+fn journal_metadata<B: Backend>(
+    terminal: &mut Terminal<B>,
     app: &mut App,
-) -> Result<Option<NavigationResult>, Box<dyn std::error::Error>> {
-    loop {
-        if app.should_render() {
-            render_menu_ui(terminal, title, items, *selected_index, help_text, true)?;
+    password: &str,
+    file_path: &Path,
+) -> Result<Rc<Value>, ClogError> {
+    if let Some(metadata) = app.store.cached(password, file_path) {
+        return Ok(metadata);
+    }
+    let metadata = decrypting(terminal, app, || clog::read_metadata(password, file_path))??;
+    Ok(app.store.keep(password, file_path, metadata))
+}
+
+/// What to tell someone whose journal didn't open. A damaged journal is
+/// remembered so the user list offers to restore its backup.
+fn unlock_error(app: &mut App, user_path: &str, e: ClogError) -> String {
+    match e {
+        ClogError::WrongPassword => "Incorrect password!".to_string(),
+        ClogError::Corrupt(_) | ClogError::Serde(_) => {
+            app.damaged.insert(user_path.to_string());
+            format!("Cannot open {}: {}", user_path, e)
         }
+        ClogError::Io(_) => e.to_string(),
+    }
+}
 
-        if let Some(action) = handle_menu_input(selected_index, items.len(), true)? {
-            match action {
-                MenuAction::Select => {
-                    if !items.is_empty() {
-                        return Ok(Some(NavigationResult::Selected(
-                            items[*selected_index].0.clone(),
-                        )));
-                    }
-                }
-                MenuAction::Back => {
-                    return Ok(Some(NavigationResult::Back));
+/// A journal whose settings page can't be read isn't opened: on empty
+/// settings the first save would write over it, losing the trash and the
+/// deleted, filed and locked pages.
+fn settings_error(user_path: &str, e: ClogError) -> String {
+    format!("Cannot read the settings of {}: {}", user_path, e)
+}
+
+/// Runs a decryption on a worker thread. The app can't take input meanwhile,
+/// but if it takes longer than `SPINNER_DELAY` the screen shows that it is
+/// busy instead of looking frozen. Errors come back in `T` as usual.
+fn decrypting<B: Backend, T: Send>(
+    terminal: &mut Terminal<B>,
+    app: &App,
+    work: impl FnOnce() -> T + Send,
+) -> io::Result<T> {
+    std::thread::scope(|scope| {
+        let (done, result) = mpsc::channel();
+        let worker = scope.spawn(move || done.send(work()).ok());
+        let mut wait = SPINNER_DELAY;
+        let mut frame = 0;
+        loop {
+            match result.recv_timeout(wait) {
+                Ok(value) => return Ok(value),
+                Err(RecvTimeoutError::Timeout) => {
+                    terminal.draw(|f| {
+                        ui::draw(f, app);
+                        ui::render_busy(f, frame, &app.config.theme);
+                    })?;
+                    frame += 1;
+                    wait = SPINNER_FRAME;
                 }
+                Err(RecvTimeoutError::Disconnected) => break,
             }
         }
-    }
+        // The worker died without a result. A panic clog_rs raised was
+        // already caught there, so this is one that would have ended the
+        // app on this thread too.
+        match worker.join() {
+            Err(panic) => std::panic::resume_unwind(panic),
+            Ok(_) => unreachable!("the worker always sends its result"),
+        }
+    })
 }
 
-fn prompt_input_in_app(
-    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
-    prompt: &str,
-    input_buffer: &mut String,
-    help_text: &str,
-    app: &mut App,
-) -> Result<Option<String>, Box<dyn std::error::Error>> {
-    input_buffer.clear();
+/// Decrypts pages, named by the chapter they are listed in, on the worker
+/// behind the spinner. Each read succeeds or fails on its own.
+fn read_pages<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &App,
+    password: &str,
+    file_path: &Path,
+    pages: &[PageRef],
+) -> io::Result<Vec<Result<String, ClogError>>> {
+    let journal = &app.journal;
+    decrypting(terminal, app, || {
+        pages
+            .iter()
+            .map(|page| {
+                let stored = journal.stored_in(&page.folder, &page.file);
+                clog::read_page(password, file_path, &page.file, stored)
+            })
+            .collect()
+    })
+}
 
-    // Check if this is a password prompt
-    let is_password = prompt.to_lowercase().contains("password");
+/// Purges pages deleted more than `trash_days` ago from the open journal's
+/// trash. A read-only session leaves them for the next one.
+fn purge_expired_trash(app: &mut App, password: &str, file_path: &Path) {
+    if app.readonly || app.config.trash_days == 0 {
+        return;
+    }
+    let cutoff = chrono::Local::now().naive_local()
+        - chrono::Duration::days(app.config.trash_days.min(i64::MAX as u64 / 86_400) as i64);
+    let mut journal = app.journal.clone();
+    let (kept, expired): (Vec<_>, Vec<_>) = std::mem::take(&mut journal.trash)
+        .into_iter()
+        .partition(|page| {
+            chrono::NaiveDateTime::parse_from_str(&page.deleted_at, TIMESTAMP_FORMAT)
+                .map_or(true, |deleted_at| deleted_at >= cutoff)
+        });
+    journal.trash = kept;
+    let purged = expired.len();
+    if purged == 0 {
+        return;
+    }
+    let attachments: Vec<_> = expired
+        .into_iter()
+        .flat_map(|page| page.attachments)
+        .collect();
+    let saved = settings::erase_attachments(password, file_path, &attachments)
+        .and_then(|()| clog::read_metadata(password, file_path))
+        .and_then(|metadata| settings::save_settings(password, file_path, &metadata, &journal));
+    // The cached metadata still holds the old settings page.
+    app.store.invalidate();
+    if saved.is_ok() {
+        app.journal = journal;
+        app.announce(format!(
+            "Purged {} page{} deleted over {} days ago from the trash",
+            purged,
+            if purged == 1 { "" } else { "s" },
+            app.config.trash_days
+        ));
+    }
+}
 
-    loop {
-        if app.should_render() {
-            terminal.draw(|f| {
-                let size = f.area();
-                let popup_area = centered_rect(80, 80, size);
-                f.render_widget(Clear, popup_area);
-
-                let chunks = Layout::default()
-                    .direction(Direction::Vertical)
-                    .constraints([
-                        Constraint::Length(3),
-                        Constraint::Length(3),
-                        Constraint::Length(3),
-                    ])
-                    .split(popup_area);
-
-                let prompt_widget = Paragraph::new(prompt)
-                    .style(
-                        Style::default()
-                            .fg(Color::Cyan)
-                            .add_modifier(Modifier::BOLD),
-                    )
-                    .alignment(Alignment::Center)
-                    .block(
-                        Block::default()
-                            .borders(Borders::ALL)
-                            .border_style(Style::default().fg(Color::Cyan)),
-                    );
-                f.render_widget(prompt_widget, chunks[0]);
+/// A byte count the way the attachments list shows it.
+fn size_label(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1_048_576 => format!("{} KB", bytes.div_ceil(1024)),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
 
-                // Display asterisks for password, normal text otherwise
-                let display_text = if is_password {
-                    "*".repeat(input_buffer.len())
-                } else {
-                    input_buffer.clone()
-                };
+/// A page as it is before a change that can be undone.
+fn undo_page(app: &App, folder: &str, file: &str, content: String) -> UndoPage {
+    UndoPage {
+        file: file.to_string(),
+        content: Zeroizing::new(content),
+        tags: app.journal.tags_of(folder, file).to_vec(),
+        edited: app.journal.edited_at(folder, file).map(str::to_string),
+    }
+}
 
-                let input_widget = Paragraph::new(display_text.as_str())
-                    .style(Style::default().fg(Color::White))
-                    .block(
-                        Block::default()
-                            .borders(Borders::ALL)
-                            .title("Input")
-                            .border_style(Style::default().fg(Color::Green)),
-                    );
-                f.render_widget(input_widget, chunks[1]);
-
-                let help_widget = Paragraph::new(help_text)
-                    .style(Style::default().fg(Color::Yellow))
-                    .alignment(Alignment::Center)
-                    .block(
-                        Block::default()
-                            .borders(Borders::ALL)
-                            .title("Controls")
-                            .border_style(Style::default().fg(Color::Yellow)),
-                    );
-                f.render_widget(help_widget, chunks[2]);
-            })?;
+/// Decides what happens to content coming back from an editor: empty new
+/// pages and unchanged pages are dropped with a note, anything else is
+/// written.
+fn finish_edit(app: &mut App, mut write: PageWrite, original: &str) -> Option<SideEffect> {
+    let policy = app.config.trailing_newline;
+    // A new page starts with the writing prompt when it is prefilled; left
+    // as it was, it isn't part of the page.
+    if write.new_page
+        && !original.is_empty()
+        && let Some(rest) = write.content.strip_prefix(original.trim_end())
+    {
+        write.content = rest.trim_start_matches(['\n', '\r']).to_string();
+    }
+    let cancelled = if write.new_page && write.content.trim().is_empty() {
+        Some("Page creation cancelled (empty content)")
+    } else if !write.new_page && policy.apply(&write.content) == policy.apply(original) {
+        Some("No changes made to page")
+    } else {
+        None
+    };
+    if let Some(note) = cancelled {
+        if std::mem::take(&mut app.draft_written) {
+            settings::discard_draft(&app.data_dir.join(&write.user_path)).ok();
         }
+        app.show_message("Info", note);
+        return app.enter(AppState::SelectFile(
+            write.user_path,
+            write.password,
+            write.folder,
+        ));
+    }
+    let content = policy.apply(&write.content);
+    confirm_write(app, PageWrite { content, ..write })
+}
 
-        if event::poll(Duration::from_millis(16))? {
-            if let Event::Key(key) = event::read()? {
-                // Fix Windows double keypress issue
-                if key.kind != KeyEventKind::Press {
-                    continue;
-                }
+/// Keeps a copy of what the external editor returned, sealed with the
+/// journal's password, before it is saved: the temporary file is gone by
+/// now, and a save that fails would otherwise lose it. Content left as it
+/// was isn't kept.
+fn keep_recovery<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    write: &PageWrite,
+    original: &str,
+) -> io::Result<()> {
+    if write.content == original || write.content.trim().is_empty() {
+        return Ok(());
+    }
+    let mut draft = Draft {
+        folder: write.folder.clone(),
+        file: write.file.clone(),
+        new_page: write.new_page,
+        tags: write.tags.clone(),
+        content: write.content.clone(),
+        drafted: chrono::Local::now().format(TIMESTAMP_FORMAT).to_string(),
+    };
+    let config_dir = app.state_dir();
+    let kept = decrypting(terminal, app, || {
+        settings::save_recovery(&write.password, &config_dir, &write.user_path, &draft)
+    })?;
+    draft.content.zeroize();
+    if let Err(e) = kept {
+        app.announce(format!("Could not keep a recovery copy: {}", e));
+    }
+    Ok(())
+}
 
-                match key.code {
-                    KeyCode::Char(c) => {
-                        input_buffer.push(c);
-                    }
-                    KeyCode::Backspace => {
-                        input_buffer.pop();
-                    }
-                    KeyCode::Enter => {
-                        if !input_buffer.is_empty() {
-                            return Ok(Some(input_buffer.clone()));
-                        }
-                    }
-                    KeyCode::Esc => {
-                        return Ok(None);
-                    }
-                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                        return Ok(None);
-                    }
-                    KeyCode::Char('q') => std::process::exit(0),
-                    _ => {}
-                }
-            }
+/// `finish_edit` for content from the external editor, which with
+/// `save_mode = "confirm"` asks before saving. Unchanged and empty content
+/// is dropped with its note either way.
+fn finish_external_edit(app: &mut App, write: PageWrite, original: &str) -> Option<SideEffect> {
+    match finish_edit(app, write, original) {
+        Some(SideEffect::WritePage(write)) if app.config.save_mode == SaveMode::Confirm => {
+            let question = if write.new_page {
+                format!(
+                    "Add page '{}' to {}?\n\nNo discards it.",
+                    write.file,
+                    folder_label(&write.folder)
+                )
+            } else {
+                format!("Save changes to '{}'?\n\nNo discards them.", write.file)
+            };
+            let back = AppState::SelectFile(
+                write.user_path.clone(),
+                write.password.clone(),
+                write.folder.clone(),
+            );
+            app.state = AppState::Confirm(
+                question,
+                Box::new(SideEffect::WritePage(write)),
+                Box::new(back),
+            );
+            None
         }
+        effect => effect,
     }
 }
-fn centered_rect(
-    percent_x: u16,
-    percent_y: u16,
-    r: ratatui::layout::Rect,
-) -> ratatui::layout::Rect {
-    let popup_layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage((100 - percent_y) / 2),
-            Constraint::Percentage(percent_y),
-            Constraint::Percentage((100 - percent_y) / 2),
-        ])
-        .split(r);
 
-    Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage((100 - percent_x) / 2),
-            Constraint::Percentage(percent_x),
-            Constraint::Percentage((100 - percent_x) / 2),
-        ])
-        .split(popup_layout[1])[1]
+/// Returns the effect that performs `write`, or asks first when the disk
+/// holding the journal looks too full for it.
+fn confirm_write(app: &mut App, write: PageWrite) -> Option<SideEffect> {
+    let file_path = app.data_dir.join(&write.user_path);
+    // clog_rs rewrites the whole journal, so it needs room for a second copy.
+    let needed = fs::metadata(&file_path).map_or(0, |m| m.len()) + write.content.len() as u64;
+    match fs2::available_space(&app.data_dir) {
+        Ok(available) if available < needed => {
+            let back = AppState::SelectFile(
+                write.user_path.clone(),
+                write.password.clone(),
+                write.folder.clone(),
+            );
+            app.state = AppState::Confirm(
+                format!(
+                    "Saving needs about {} KB but only {} KB is free on this disk. \
+                     The write may fail partway. Save anyway?",
+                    needed.div_ceil(1024),
+                    available / 1024
+                ),
+                Box::new(SideEffect::WritePage(write)),
+                Box::new(back),
+            );
+            None
+        }
+        _ => Some(SideEffect::WritePage(write)),
+    }
 }
 
-fn edit_file_with_editor(content: &str) -> Result<String, Box<dyn std::error::Error>> {
-    // Create temp file but keep it persistent
-    let mut temp_file = NamedTempFile::new()?;
-    let temp_path = temp_file.path().to_path_buf();
-
-    // Write content and flush to ensure it's written
-    write!(temp_file, "{}", content)?;
-    temp_file.flush()?;
+fn list_clog_files(dir: &std::path::Path) -> Vec<String> {
+    let mut result = vec![];
+    if let Ok(paths) = fs::read_dir(dir) {
+        for path in paths.flatten() {
+            let path = path.path();
+            if path.is_file()
+                && path.extension().is_some_and(|ext| ext == "clog")
+                && let Some(filename) = path.file_name()
+            {
+                result.push(filename.to_string_lossy().to_string());
+            }
+        }
+    }
+    result
+}
 
-    // Convert temp file to persistent file to avoid handle issues
-    let persistent_path = temp_file.into_temp_path();
+/// The first profile, in the order they're listed, with a journal named
+/// `user_file`.
+fn profile_with_journal(app: &App, user_file: &str) -> Option<usize> {
+    app.config.profiles.iter().position(|profile| {
+        let dir = export::resolve_destination(&profile.data_dir);
+        existing_journal(&dir, user_file).is_some()
+    })
+}
 
-    disable_raw_mode()?;
-    execute!(io::stdout(), LeaveAlternateScreen)?;
+/// Forgets the journal that was open: its settings, its previews, its lock
+/// and the read-only mode it may have been opened in.
+fn close_journal(app: &mut App) {
+    app.clear_previews();
+    app.journal = JournalSettings::default();
+    lockfile::release();
+    if std::mem::take(&mut app.readonly_journal) {
+        app.readonly = false;
+    }
+}
 
-    let editors = if cfg!(windows) {
-        // Use full path for notepad and add more Windows editors
-        vec!["notepad.exe", "code", "notepad++", "vim", "nano"]
-    } else {
-        vec!["vim", "nano", "vi", "emacs"]
+/// What the journal's lock says about the session holding it.
+fn in_use_notice(user_path: &str, holder: &lockfile::Holder) -> String {
+    let mut session = match holder.pid {
+        Some(pid) => format!("process {}", pid),
+        None => "an unknown process".to_string(),
     };
+    if !holder.host.is_empty() {
+        session.push_str(&format!(" on {}", holder.host));
+    }
+    if let Some(since) = holder.since {
+        session.push_str(&format!(
+            ", last active {}",
+            time_label(since.naive_local())
+        ));
+    }
+    if holder.stale {
+        format!(
+            "'{}' is locked by a session that seems to be gone ({}).\n\n\
+             If it is, force-unlock to remove the lock.",
+            user_path, session
+        )
+    } else {
+        format!(
+            "'{}' is open in another session ({}).\n\n\
+             Two sessions writing the same journal overwrite each other's changes.",
+            user_path, session
+        )
+    }
+}
 
-    let mut editor_found = false;
-    let mut status = None;
+/// The most recently unlocked journal that still exists, for
+/// `reopen_last_page`.
+fn last_user(app: &App) -> Option<String> {
+    settings::load_recent_users(&app.state_dir())
+        .into_iter()
+        .find(|file| {
+            validate_username(file.trim_end_matches(".clog")).is_ok()
+                && app.data_dir.join(file).exists()
+        })
+}
 
-    for editor in &editors {
-        // Special handling for notepad
-        if editor == &"notepad.exe" {
-            status = Some(Command::new("notepad.exe").arg(&persistent_path).status()?);
-            editor_found = true;
-            break;
-        } else {
-            // Check if other editors exist
-            if Command::new(editor).arg("--version").output().is_ok() {
-                status = Some(Command::new(editor).arg(&persistent_path).status()?);
-                editor_found = true;
-                break;
-            }
+/// Hands the palette's colours out to the journals of the data directory
+/// in alphabetical order, so adding one only shifts those after it.
+fn assign_journal_colors(app: &mut App) {
+    let mut user_files = list_clog_files(&app.data_dir);
+    user_files.sort();
+    app.journal_colors = user_files
+        .into_iter()
+        .zip(PALETTE.iter().cycle().copied())
+        .collect();
+}
+
+/// Starts on the password prompt of the journal named on the command line,
+/// or on the user list when there is no such journal.
+fn ask_password(app: &mut App, name: &str) -> Option<SideEffect> {
+    let user_file = format!("{}.clog", name.trim_end_matches(".clog"));
+    match existing_journal(&app.data_dir, &user_file) {
+        Some(user_path) => {
+            assign_journal_colors(app);
+            app.clear_input();
+            app.enter(AppState::InputPrompt(
+                "Enter password:".to_string(),
+                Box::new(AppState::EnterPassword(user_path)),
+            ))
+        }
+        None => {
+            app.show_message("Error", &format!("There is no journal named '{}'", name));
+            app.enter(AppState::SelectUser)
         }
     }
+}
 
-    // Fallback to environment variable or default
-    if !editor_found {
-        let editor = std::env::var("EDITOR").unwrap_or_else(|_| {
-            if cfg!(windows) {
-                "notepad.exe".to_string()
-            } else {
-                "vi".to_string()
-            }
-        });
-        status = Some(Command::new(&editor).arg(&persistent_path).status()?);
+/// The journal file a new `user_file` would clash with. Names are compared
+/// ignoring case: on Windows and macOS `Bob.clog` is the same file as
+/// `bob.clog`, and journals are meant to be copied between systems.
+fn existing_journal(dir: &std::path::Path, user_file: &str) -> Option<String> {
+    if dir.join(user_file).exists() {
+        return Some(user_file.to_string());
     }
+    list_clog_files(dir)
+        .into_iter()
+        .find(|file| file.eq_ignore_ascii_case(user_file))
+}
 
-    execute!(io::stdout(), EnterAlternateScreen)?;
-    enable_raw_mode()?;
-
-    if let Some(status) = status {
-        if !status.success() {
-            return Err("Editor exited with non-zero status".into());
+fn sort_users(user_files: &mut [String], dir: &std::path::Path, sort: UserSort) {
+    match sort {
+        UserSort::Name => user_files.sort(),
+        UserSort::Created | UserSort::Modified => {
+            let time = |file: &String| {
+                let metadata = fs::metadata(dir.join(file)).ok()?;
+                if sort == UserSort::Created {
+                    metadata.created().or_else(|_| metadata.modified()).ok()
+                } else {
+                    metadata.modified().ok()
+                }
+            };
+            user_files.sort_by_cached_key(|file| std::cmp::Reverse(time(file)));
         }
     }
+}
 
-    // Read the modified content
-    let mut new_content = String::new();
-    std::fs::File::open(&persistent_path)?.read_to_string(&mut new_content)?;
+/// Quick structural check that doesn't need the password: a clog is always a
+/// readable file.
+fn looks_like_clog(file_path: &std::path::Path) -> bool {
+    fs::File::open(file_path)
+        .and_then(|file| file.metadata())
+        .is_ok_and(|metadata| metadata.is_file())
+}
 
-    // Clean up the temporary file
-    std::fs::remove_file(&persistent_path).ok(); // Ignore errors on cleanup
+/// Even a new journal has metadata, so an empty one was cut short.
+fn is_truncated(file_path: &std::path::Path) -> bool {
+    fs::metadata(file_path).is_ok_and(|metadata| metadata.len() == 0)
+}
+
+fn get_user_creation_date(file_path: &std::path::Path) -> Option<String> {
+    if !file_path.exists() {
+        return None;
+    }
 
-    Ok(new_content)
+    let metadata = fs::metadata(file_path).ok()?;
+    let time = metadata.created().or_else(|_| metadata.modified()).ok()?;
+    let datetime: chrono::DateTime<chrono::Local> = time.into();
+    Some(time_label(datetime.naive_local()))
 }
 
-// Alternative approach using a regular file in temp directory
-fn edit_file_with_editor_alt(content: &str) -> Result<String, Box<dyn std::error::Error>> {
-    use std::time::{SystemTime, UNIX_EPOCH};
+/// The editor to launch and its leading arguments: `$VISUAL`, then
+/// `$EDITOR` (split on whitespace, so `code --wait` works), then the first
+/// known editor on `PATH`.
+fn editor_command() -> Option<(String, Vec<String>)> {
+    for var in ["VISUAL", "EDITOR"] {
+        if let Ok(value) = std::env::var(var) {
+            let mut words = value.split_whitespace().map(str::to_string);
+            if let Some(program) = words.next() {
+                return Some((program, words.collect()));
+            }
+        }
+    }
+    let editors: &[&str] = if cfg!(windows) {
+        &["notepad.exe", "code", "notepad++", "vim", "nano"]
+    } else {
+        &["vim", "nano", "vi", "emacs"]
+    };
+    editors
+        .iter()
+        .find(|editor| on_path(editor))
+        .map(|editor| (editor.to_string(), Vec::new()))
+}
 
-    // Create a unique filename in temp directory
-    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos();
-    let temp_dir = std::env::temp_dir();
-    let temp_file_path = temp_dir.join(format!("rust_editor_{}.txt", timestamp));
+fn on_path(program: &str) -> bool {
+    let Some(path) = std::env::var_os("PATH") else {
+        return false;
+    };
+    std::env::split_paths(&path).any(|dir| {
+        let candidate = dir.join(program);
+        candidate.is_file() || (cfg!(windows) && candidate.with_extension("exe").is_file())
+    })
+}
 
-    // Write content to file
-    std::fs::write(&temp_file_path, content)?;
+/// Opens `content` in the external editor as `<page>.md`, so the editor
+/// shows the page's name and picks Markdown mode, and returns what was
+/// saved, or `None` when the editor exits with a failure status, which is
+/// how `:cq` in vim and its equivalents ask to throw the edit away. An
+/// editor that can't be started is an error.
+fn edit_file_with_editor(
+    page: &str,
+    content: &str,
+    mouse: bool,
+) -> Result<Option<String>, AppError> {
+    let (program, args) = editor_command()
+        .ok_or_else(|| AppError::Editor("No editor found. Set $VISUAL or $EDITOR.".to_string()))?;
+
+    // The page's name is only unique within its chapter, so the file gets a
+    // directory of its own, removed with everything in it when dropped. It
+    // holds the decrypted page, so only the user may look inside; on Windows
+    // the temp directory is already private to the user.
+    let mut builder = tempfile::Builder::new();
+    builder.prefix("clog-tui-");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        builder.permissions(fs::Permissions::from_mode(0o700));
+    }
+    let temp_dir = builder.tempdir()?;
+    let temp_path = temp_dir.path().join(format!("{}.md", temp_file_stem(page)));
+    fs::write(&temp_path, content)?;
 
     disable_raw_mode()?;
-    execute!(io::stdout(), LeaveAlternateScreen)?;
+    execute!(io::stdout(), DisableMouseCapture, LeaveAlternateScreen)?;
 
-    // Launch notepad
-    let status = if cfg!(windows) {
-        Command::new("notepad.exe").arg(&temp_file_path).status()?
-    } else {
-        // Unix fallback
-        let editor = std::env::var("EDITOR").unwrap_or_else(|_| "vi".to_string());
-        Command::new(&editor).arg(&temp_file_path).status()?
-    };
+    let status = Command::new(&program).args(&args).arg(&temp_path).status();
 
     execute!(io::stdout(), EnterAlternateScreen)?;
+    if mouse {
+        execute!(io::stdout(), EnableMouseCapture)?;
+    }
     enable_raw_mode()?;
 
+    let status =
+        status.map_err(|e| AppError::Editor(format!("Could not start {}: {}", program, e)))?;
     if !status.success() {
-        std::fs::remove_file(&temp_file_path).ok();
-        return Err("Editor exited with non-zero status".into());
+        return Ok(None);
     }
 
-    // Read modified content
-    let new_content = std::fs::read_to_string(&temp_file_path)?;
+    // Read the modified content
+    let mut new_content = String::new();
+    std::fs::File::open(&temp_path)?.read_to_string(&mut new_content)?;
 
-    // Clean up
-    std::fs::remove_file(&temp_file_path).ok();
+    // Clean up the temporary file
+    temp_dir.close().ok(); // Ignore errors on cleanup
 
-    Ok(new_content)
+    Ok(Some(new_content))
 }
-fn show_message(
-    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
-    message: &str,
-    title: &str,
-    app: &mut App,
-) -> Result<(), Box<dyn std::error::Error>> {
-    loop {
-        if app.should_render() {
-            terminal.draw(|f| {
-                let size = f.area();
-                let popup_area = centered_rect(80, 60, size);
-                f.render_widget(Clear, popup_area);
-
-                let block = Paragraph::new(message)
-                    .style(Style::default().fg(Color::White))
-                    .alignment(Alignment::Left)
-                    .block(
-                        Block::default()
-                            .borders(Borders::ALL)
-                            .title(title)
-                            .border_style(Style::default().fg(Color::Magenta)),
-                    )
-                    .wrap(ratatui::widgets::Wrap { trim: true });
-                f.render_widget(block, popup_area);
-            })?;
-        }
 
-        if event::poll(Duration::from_millis(16))? {
-            if let Event::Key(key) = event::read()? {
-                // Fix Windows double keypress issue
-                if key.kind == KeyEventKind::Press {
-                    break;
-                }
+/// `page` made safe as a file name on any system: path separators and other
+/// reserved characters become `_`, and it can't be hidden or empty.
+fn temp_file_stem(page: &str) -> String {
+    let stem: String = page
+        .chars()
+        .map(|c| {
+            if c.is_control() || "/\\:*?\"<>|".contains(c) {
+                '_'
+            } else {
+                c
             }
-        }
+        })
+        .take(64)
+        .collect();
+    let stem = stem.trim().trim_start_matches('.');
+    if stem.is_empty() {
+        "page".to_string()
+    } else {
+        stem.to_string()
     }
-    Ok(())
 }
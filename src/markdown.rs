@@ -0,0 +1,165 @@
+//! Markdown styling for the viewer. Only headings, emphasis, lists, quotes
+//! and code are drawn differently; anything else is shown as its text.
+
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+
+/// Renders `content` line by line. Line breaks inside a paragraph are kept,
+/// since a journal's lines are usually meant as written.
+pub fn render(content: &str, heading: Color) -> Vec<Line<'static>> {
+    let mut renderer = Renderer::default();
+    for event in Parser::new_ext(content, Options::empty()) {
+        renderer.event(event, heading);
+    }
+    renderer.end_line();
+    while renderer.lines.last().is_some_and(|line| line.width() == 0) {
+        renderer.lines.pop();
+    }
+    renderer.lines
+}
+
+#[derive(Default)]
+struct Renderer {
+    lines: Vec<Line<'static>>,
+    spans: Vec<Span<'static>>,
+    styles: Vec<Style>,
+    /// Open lists, innermost last, with the next number of ordered ones.
+    lists: Vec<Option<u64>>,
+    /// Marker for the first line of the list item just started.
+    bullet: Option<String>,
+    quote_depth: usize,
+    in_code_block: bool,
+    /// A line has been started, even if nothing is on it yet.
+    open: bool,
+}
+
+impl Renderer {
+    fn event(&mut self, event: Event, heading: Color) {
+        match event {
+            Event::Start(Tag::Heading { .. }) => self
+                .styles
+                .push(Style::default().fg(heading).add_modifier(Modifier::BOLD)),
+            Event::Start(Tag::Strong) => self
+                .styles
+                .push(Style::default().add_modifier(Modifier::BOLD)),
+            Event::Start(Tag::Emphasis) => self
+                .styles
+                .push(Style::default().add_modifier(Modifier::ITALIC)),
+            Event::Start(Tag::BlockQuote(_)) => self.quote_depth += 1,
+            Event::Start(Tag::CodeBlock(_)) => self.in_code_block = true,
+            Event::Start(Tag::List(first)) => {
+                self.end_line();
+                self.lists.push(first);
+            }
+            Event::Start(Tag::Item) => {
+                self.end_line();
+                let bullet = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "• ".to_string(),
+                };
+                self.bullet = Some(bullet);
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                self.styles.pop();
+                self.end_block();
+            }
+            Event::End(TagEnd::Strong | TagEnd::Emphasis) => {
+                self.styles.pop();
+            }
+            Event::End(TagEnd::Paragraph) => self.end_block(),
+            Event::End(TagEnd::CodeBlock) => {
+                self.end_block();
+                self.in_code_block = false;
+            }
+            Event::End(TagEnd::BlockQuote(_)) => {
+                self.end_block();
+                self.quote_depth -= 1;
+            }
+            Event::End(TagEnd::List(_)) => {
+                self.lists.pop();
+                self.end_block();
+            }
+            Event::End(TagEnd::Item) => self.end_line(),
+            Event::Text(text)
+            | Event::Code(text)
+            | Event::Html(text)
+            | Event::InlineHtml(text)
+            | Event::InlineMath(text)
+            | Event::DisplayMath(text) => self.text(&text),
+            Event::SoftBreak | Event::HardBreak => self.end_line(),
+            Event::Rule => {
+                self.text("────────");
+                self.end_block();
+            }
+            Event::TaskListMarker(done) => self.text(if done { "[x] " } else { "[ ] " }),
+            Event::FootnoteReference(name) => self.text(&format!("[^{}]", name)),
+            Event::Start(_) | Event::End(_) => {}
+        }
+    }
+
+    fn style(&self) -> Style {
+        self.styles
+            .iter()
+            .fold(Style::default(), |style, patch| style.patch(*patch))
+    }
+
+    /// Puts the quote bar, list indent and code indent in front of a new
+    /// line.
+    fn start_line(&mut self) {
+        if self.open {
+            return;
+        }
+        self.open = true;
+        if self.quote_depth > 0 {
+            self.spans.push(Span::styled(
+                "│ ".repeat(self.quote_depth),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+        match self.bullet.take() {
+            Some(bullet) => self.spans.push(Span::raw(format!("{}{}", indent, bullet))),
+            None if !self.lists.is_empty() => self.spans.push(Span::raw(format!("{}  ", indent))),
+            None => {}
+        }
+        if self.in_code_block {
+            self.spans.push(Span::raw("    "));
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        for piece in text.split_inclusive('\n') {
+            self.start_line();
+            let line = piece.strip_suffix('\n');
+            let part = line.unwrap_or(piece);
+            if !part.is_empty() {
+                self.spans
+                    .push(Span::styled(part.to_string(), self.style()));
+            }
+            if line.is_some() {
+                self.end_line();
+            }
+        }
+    }
+
+    fn end_line(&mut self) {
+        if std::mem::take(&mut self.open) {
+            self.lines.push(Line::from(std::mem::take(&mut self.spans)));
+        }
+    }
+
+    /// Ends a block with the blank line that separated it in the source.
+    /// Items of a list are kept together.
+    fn end_block(&mut self) {
+        self.end_line();
+        if self.lists.is_empty() && self.lines.last().is_some_and(|line| line.width() > 0) {
+            self.lines.push(Line::default());
+        }
+    }
+}
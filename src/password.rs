@@ -0,0 +1,49 @@
+//! Rough strength of a new journal password, shown while it is typed.
+
+/// How hard a password looks to guess, judged only by its length and the
+/// kinds of characters in it. A nudge, not a guarantee.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strength {
+    Weak,
+    Fair,
+    Strong,
+}
+
+impl Strength {
+    pub fn label(self) -> &'static str {
+        match self {
+            Strength::Weak => "weak",
+            Strength::Fair => "fair",
+            Strength::Strong => "strong",
+        }
+    }
+
+    /// Filled cells of the three-cell meter.
+    pub fn level(self) -> usize {
+        match self {
+            Strength::Weak => 1,
+            Strength::Fair => 2,
+            Strength::Strong => 3,
+        }
+    }
+}
+
+/// Counts lowercase, uppercase, digits and everything else as the four
+/// character classes; long passwords need fewer of them.
+pub fn strength(password: &str) -> Strength {
+    let length = password.chars().count();
+    let classes = [
+        password.chars().any(|c| c.is_lowercase()),
+        password.chars().any(|c| c.is_uppercase()),
+        password.chars().any(|c| c.is_ascii_digit()),
+        password.chars().any(|c| !c.is_alphanumeric()),
+    ]
+    .into_iter()
+    .filter(|&present| present)
+    .count();
+    match (length, classes) {
+        (16.., 2..) | (12.., 3..) => Strength::Strong,
+        (12.., _) | (8.., 2..) => Strength::Fair,
+        _ => Strength::Weak,
+    }
+}
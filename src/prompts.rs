@@ -0,0 +1,73 @@
+//! Writing prompts offered when a page is added, one a day.
+
+use chrono::{Datelike, NaiveDate};
+use std::fs;
+use std::path::Path;
+
+use crate::error::AppError;
+
+const BUNDLED: &[&str] = &[
+    "What is one thing you noticed today that you usually walk past?",
+    "Describe the last conversation that stayed with you.",
+    "What are you looking forward to this week, and why?",
+    "Write about a smell that takes you back somewhere.",
+    "What did you change your mind about recently?",
+    "Describe your morning as if it happened to a stranger.",
+    "What is something you keep putting off? What would the first step be?",
+    "Who made your day easier lately, and how?",
+    "Write a letter to yourself a year from now.",
+    "What would you do with a free afternoon tomorrow?",
+    "Describe a place you feel calm in, down to the details.",
+    "What is worrying you right now? Write it all down, then read it back.",
+    "What did you learn this week that you want to remember?",
+    "Write about a small win nobody else noticed.",
+    "What does a good day look like for you at the moment?",
+    "Describe something you made or fixed with your hands.",
+    "What habit would you like to start, and what stands in the way?",
+    "Write about a book, song or film that has been on your mind.",
+    "What would you tell yourself from ten years ago?",
+    "List three things you are grateful for and why each matters.",
+    "Describe the view from where you are sitting right now.",
+    "What is a question you would like someone to ask you?",
+    "Write about a time you were surprised by your own courage.",
+    "What drained your energy today, and what gave it back?",
+    "Describe someone you admire without naming them.",
+    "What would you like to spend less time on?",
+    "Write about a meal you remember well.",
+    "What is something you are proud of that you rarely mention?",
+    "Where do you want to be this time next year?",
+    "Write about the weather, and how it matched your mood, or didn't.",
+];
+
+/// The prompts in the configured file, one per line, or the bundled ones.
+/// Blank lines and lines starting with `#` are skipped.
+pub fn load(configured: Option<&Path>) -> Result<Vec<String>, AppError> {
+    let Some(path) = configured else {
+        return Ok(BUNDLED.iter().map(|prompt| prompt.to_string()).collect());
+    };
+    let prompts: Vec<String> = fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect();
+    if prompts.is_empty() {
+        return Err(AppError::Config(format!(
+            "{} has no writing prompts",
+            path.display()
+        )));
+    }
+    Ok(prompts)
+}
+
+/// The prompt of the day: the same all day, the next one tomorrow.
+pub fn for_day(prompts: &[String], day: NaiveDate) -> Option<&str> {
+    let index = day.num_days_from_ce().unsigned_abs() as usize % prompts.len().max(1);
+    prompts.get(index).map(String::as_str)
+}
+
+/// The prompt as the first line of a new page, commented out so Markdown
+/// leaves it unrendered.
+pub fn comment(prompt: &str) -> String {
+    format!("<!-- {} -->\n\n", prompt)
+}
@@ -0,0 +1,71 @@
+//! Plain-text matching over decrypted page content.
+
+/// Longest snippet shown for a matching line, in chars.
+const SNIPPET_LEN: usize = 60;
+/// Chars of context kept in front of the match when a line is shortened.
+const SNIPPET_LEAD: usize = 20;
+
+/// Case-insensitive matches of `query` in `content`, as 1-based line numbers
+/// with a snippet of the line around the match.
+pub fn matching_lines(content: &str, query: &str) -> Vec<(usize, String)> {
+    let query = query.to_lowercase();
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let line = line.trim();
+            let lower = line.to_lowercase();
+            let at = lower.find(&query)?;
+            Some((i + 1, snippet(line, lower[..at].chars().count())))
+        })
+        .collect()
+}
+
+/// Scores `candidate` against `query` when the query's chars appear in it in
+/// order, ignoring case. Runs of adjacent chars and matches at the start of
+/// a word score higher, skipped chars cost a little. `None` if it doesn't
+/// match at all.
+pub fn fuzzy_score(candidate: &str, query: &str) -> Option<i64> {
+    let mut query = query.chars().flat_map(char::to_lowercase).peekable();
+    let mut score = 0;
+    let mut last_match: Option<usize> = None;
+    let mut prev = None;
+    for (i, c) in candidate.chars().flat_map(char::to_lowercase).enumerate() {
+        let Some(&wanted) = query.peek() else {
+            break;
+        };
+        if c == wanted {
+            query.next();
+            score += 1;
+            match last_match {
+                Some(last) if last + 1 == i => score += 5,
+                Some(last) => score -= (i - last - 1).min(5) as i64,
+                None => score -= i.min(5) as i64,
+            }
+            if prev.is_none_or(|p: char| !p.is_alphanumeric()) {
+                score += 3;
+            }
+            last_match = Some(i);
+        }
+        prev = Some(c);
+    }
+    query.peek().is_none().then_some(score)
+}
+
+/// Shortens `line` to `SNIPPET_LEN` chars, keeping the match that starts at
+/// char `hit` in view.
+fn snippet(line: &str, hit: usize) -> String {
+    let len = line.chars().count();
+    if len <= SNIPPET_LEN {
+        return line.to_string();
+    }
+    let start = hit.saturating_sub(SNIPPET_LEAD).min(len - SNIPPET_LEN);
+    let mut short: String = line.chars().skip(start).take(SNIPPET_LEN).collect();
+    if start > 0 {
+        short.insert(0, '…');
+    }
+    if start + SNIPPET_LEN < len {
+        short.push('…');
+    }
+    short
+}
@@ -0,0 +1,933 @@
+//! Per-journal settings stored as a hidden page inside the clog itself, so
+//! they stay encrypted and travel with the `.clog` file.
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::clog;
+use crate::clog::ClogError;
+use crate::error::AppError;
+
+/// Name of the hidden page holding the settings. It is never listed as a page.
+pub const SETTINGS_PAGE: &str = ".clog-tui";
+/// Hidden pages holding attachments are named this followed by a number.
+pub const ATTACHMENT_PAGE_PREFIX: &str = ".clog-tui-attachment-";
+
+const TITLE_CACHE_FILE: &str = "journals.json";
+const RECENT_USERS_FILE: &str = "recent.json";
+const LAST_PAGES_FILE: &str = "last_pages.json";
+/// Directory in the config directory holding editor recoveries.
+const RECOVERY_DIR: &str = "recovery";
+const RECOVERY_EXTENSION: &str = "recovery";
+/// Recoveries older than this are pruned.
+const RECOVERY_KEPT: Duration = Duration::from_secs(24 * 60 * 60);
+/// Journals pinned to the top of the user list.
+const RECENT_USERS: usize = 5;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JournalSettings {
+    pub title: String,
+    pub description: String,
+    /// Pages deleted from the TUI. clog_rs can't remove a page, so its
+    /// content is blanked and it is left out of every listing.
+    pub deleted: Vec<PageRef>,
+    /// Tags of every tagged page. clog_rs stores nothing but content for a
+    /// page, so they live here.
+    pub tags: Vec<PageTags>,
+    /// When pages were last saved over. clog_rs only records when a page
+    /// was created.
+    pub edited: Vec<PageEdited>,
+    /// Chapters made from the TUI to group pages by topic. clog_rs only
+    /// keeps a folder per day, so a topic's pages are stored in the day they
+    /// were added and `filed` records which topic each belongs to.
+    pub chapters: Vec<String>,
+    pub filed: Vec<FiledPage>,
+    /// Topic chapters made inside another topic, which are listed there
+    /// instead of on the chapter list. Chapter names stay unique across the
+    /// journal, so pages are still filed by name alone.
+    pub sub_chapters: Vec<SubChapter>,
+    /// Pages locked against edits, deletes and renames until unlocked,
+    /// listed under the chapter showing them.
+    pub locked: Vec<PageRef>,
+    /// Places of pages moved by hand within the chapter listing them.
+    /// clog_rs keeps a day's pages in a JSON object, which has no order to
+    /// speak of.
+    pub order: Vec<PageOrder>,
+    /// Deleted pages with their content, oldest first, until restored or
+    /// purged. The page itself stays blanked in the clog and in `deleted`.
+    pub trash: Vec<TrashedPage>,
+    /// Files attached to pages. clog_rs only stores text, so each is kept
+    /// base64-encoded in a hidden page of its own.
+    pub attachments: Vec<Attachment>,
+    /// Colours given to chapters on the chapter list.
+    pub colors: Vec<ChapterColor>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PageRef {
+    pub folder: String,
+    pub file: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PageTags {
+    pub folder: String,
+    pub file: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PageEdited {
+    pub folder: String,
+    pub file: String,
+    /// Local time, formatted with `TIMESTAMP_FORMAT`.
+    pub at: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PageOrder {
+    pub folder: String,
+    pub file: String,
+    pub order: usize,
+}
+
+/// A deleted page as it was, kept so it can be put back from the trash.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrashedPage {
+    /// The chapter that listed the page.
+    pub folder: String,
+    pub file: String,
+    pub content: String,
+    pub tags: Vec<String>,
+    pub edited: Option<String>,
+    /// Local time, formatted with `TIMESTAMP_FORMAT`.
+    pub deleted_at: String,
+    /// Attachments of the page, put back with it.
+    pub attachments: Vec<Attachment>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Attachment {
+    /// The chapter listing the page it is attached to.
+    pub folder: String,
+    pub file: String,
+    /// File name it was attached under.
+    pub name: String,
+    /// The hidden page holding it, and the day that page is stored in.
+    pub page: String,
+    pub stored: String,
+    /// Size in bytes.
+    pub size: u64,
+    /// Local time, formatted with `TIMESTAMP_FORMAT`.
+    pub added: String,
+}
+
+/// A page stored in the day `folder` but listed under a topic chapter.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FiledPage {
+    pub chapter: String,
+    pub folder: String,
+    pub file: String,
+}
+
+/// Unsaved inline editor content, kept sealed with the journal's password
+/// beside the clog so a crash doesn't lose it. A journal has one at most.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Draft {
+    pub folder: String,
+    pub file: String,
+    pub new_page: bool,
+    pub tags: Vec<String>,
+    pub content: String,
+    /// Local time, formatted with `TIMESTAMP_FORMAT`.
+    pub drafted: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SubChapter {
+    pub chapter: String,
+    pub parent: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChapterColor {
+    pub folder: String,
+    /// A colour name ratatui parses, such as `LightGreen`.
+    pub color: String,
+}
+
+impl JournalSettings {
+    pub fn is_chapter(&self, folder: &str) -> bool {
+        self.chapters.iter().any(|chapter| chapter == folder)
+    }
+
+    /// The topic chapter `chapter` was made in, if it isn't top-level.
+    pub fn parent_of(&self, chapter: &str) -> Option<&str> {
+        self.sub_chapters
+            .iter()
+            .find(|sub| sub.chapter == chapter)
+            .map(|sub| sub.parent.as_str())
+    }
+
+    /// Sub-chapters of `parent`, by name.
+    pub fn sub_chapters_of(&self, parent: &str) -> Vec<&str> {
+        let mut subs: Vec<&str> = self
+            .sub_chapters
+            .iter()
+            .filter(|sub| sub.parent == parent)
+            .map(|sub| sub.chapter.as_str())
+            .collect();
+        subs.sort_unstable();
+        subs
+    }
+
+    /// The chapters from the top of the chapter list down to `folder`.
+    pub fn chapter_path<'a>(&'a self, folder: &'a str) -> Vec<&'a str> {
+        let mut path = vec![folder];
+        // Bounded, so a hand-edited loop in the settings can't hang.
+        while path.len() <= self.sub_chapters.len()
+            && let Some(parent) = self.parent_of(path[path.len() - 1])
+        {
+            path.push(parent);
+        }
+        path.reverse();
+        path
+    }
+
+    /// The day folder holding a page listed under `folder`. Pages of a day
+    /// are stored in it; pages of a topic chapter wherever they were added.
+    pub fn stored_in<'a>(&'a self, folder: &'a str, file: &str) -> &'a str {
+        self.filed
+            .iter()
+            .find(|page| page.chapter == folder && page.file == file)
+            .map_or(folder, |page| page.folder.as_str())
+    }
+
+    /// The chapter listing the page stored in the day `folder`: a topic it
+    /// was filed under, or the day itself.
+    pub fn listed_in<'a>(&'a self, folder: &'a str, file: &str) -> &'a str {
+        self.filed
+            .iter()
+            .find(|page| page.folder == folder && page.file == file)
+            .map_or(folder, |page| page.chapter.as_str())
+    }
+
+    /// Pages filed under a topic chapter, in the order they were added.
+    pub fn filed_in<'a>(&'a self, chapter: &'a str) -> impl Iterator<Item = &'a FiledPage> {
+        self.filed
+            .iter()
+            .filter(move |page| page.chapter == chapter)
+    }
+
+    /// Lists the page stored in the day `folder` under `chapter`, or under
+    /// its day again when `chapter` isn't a topic.
+    pub fn file_under(&mut self, chapter: &str, folder: &str, file: &str) {
+        self.filed
+            .retain(|page| page.folder != folder || page.file != file);
+        if self.is_chapter(chapter) {
+            self.filed.push(FiledPage {
+                chapter: chapter.to_string(),
+                folder: folder.to_string(),
+                file: file.to_string(),
+            });
+        }
+    }
+
+    pub fn is_deleted(&self, folder: &str, file: &str) -> bool {
+        self.deleted
+            .iter()
+            .any(|page| page.folder == folder && page.file == file)
+    }
+
+    pub fn tags_of(&self, folder: &str, file: &str) -> &[String] {
+        self.tags
+            .iter()
+            .find(|page| page.folder == folder && page.file == file)
+            .map_or(&[], |page| &page.tags)
+    }
+
+    /// Replaces the tags of a page; an empty list untags it.
+    pub fn set_tags(&mut self, folder: &str, file: &str, tags: Vec<String>) {
+        self.tags
+            .retain(|page| page.folder != folder || page.file != file);
+        if !tags.is_empty() {
+            self.tags.push(PageTags {
+                folder: folder.to_string(),
+                file: file.to_string(),
+                tags,
+            });
+        }
+    }
+
+    pub fn is_locked(&self, folder: &str, file: &str) -> bool {
+        self.locked
+            .iter()
+            .any(|page| page.folder == folder && page.file == file)
+    }
+
+    pub fn set_locked(&mut self, folder: &str, file: &str, locked: bool) {
+        self.locked
+            .retain(|page| page.folder != folder || page.file != file);
+        if locked {
+            self.locked.push(PageRef {
+                folder: folder.to_string(),
+                file: file.to_string(),
+            });
+        }
+    }
+
+    pub fn color_of(&self, folder: &str) -> Option<&str> {
+        self.colors
+            .iter()
+            .find(|color| color.folder == folder)
+            .map(|color| color.color.as_str())
+    }
+
+    pub fn set_color(&mut self, folder: &str, color: Option<String>) {
+        self.colors.retain(|chapter| chapter.folder != folder);
+        if let Some(color) = color {
+            self.colors.push(ChapterColor {
+                folder: folder.to_string(),
+                color,
+            });
+        }
+    }
+
+    pub fn order_of(&self, folder: &str, file: &str) -> Option<usize> {
+        self.order
+            .iter()
+            .find(|page| page.folder == folder && page.file == file)
+            .map(|page| page.order)
+    }
+
+    /// Records a page's place in its chapter; `None` forgets it.
+    pub fn set_order(&mut self, folder: &str, file: &str, order: Option<usize>) {
+        self.order
+            .retain(|page| page.folder != folder || page.file != file);
+        if let Some(order) = order {
+            self.order.push(PageOrder {
+                folder: folder.to_string(),
+                file: file.to_string(),
+                order,
+            });
+        }
+    }
+
+    /// Numbers the pages of `folder` in the order given, dropping the places
+    /// of pages no longer in it.
+    pub fn reorder(&mut self, folder: &str, files: &[String]) {
+        self.order.retain(|page| page.folder != folder);
+        for (order, file) in files.iter().enumerate() {
+            self.set_order(folder, file, Some(order));
+        }
+    }
+
+    pub fn edited_at(&self, folder: &str, file: &str) -> Option<&str> {
+        self.edited
+            .iter()
+            .find(|page| page.folder == folder && page.file == file)
+            .map(|page| page.at.as_str())
+    }
+
+    /// Records when a page was last saved; `None` forgets it.
+    pub fn set_edited(&mut self, folder: &str, file: &str, at: Option<String>) {
+        self.edited
+            .retain(|page| page.folder != folder || page.file != file);
+        if let Some(at) = at {
+            self.edited.push(PageEdited {
+                folder: folder.to_string(),
+                file: file.to_string(),
+                at,
+            });
+        }
+    }
+
+    /// Takes the latest trashed copy of a page out of the trash.
+    pub fn untrash(&mut self, folder: &str, file: &str) -> Option<TrashedPage> {
+        let index = self
+            .trash
+            .iter()
+            .rposition(|page| page.folder == folder && page.file == file)?;
+        Some(self.trash.remove(index))
+    }
+
+    /// Attachments of a page, in the order they were added.
+    pub fn attachments_of(&self, folder: &str, file: &str) -> Vec<&Attachment> {
+        self.attachments
+            .iter()
+            .filter(|attachment| attachment.folder == folder && attachment.file == file)
+            .collect()
+    }
+
+    /// Takes the attachments of a page out of the list.
+    fn detach_all(&mut self, folder: &str, file: &str) -> Vec<Attachment> {
+        let (taken, kept) = std::mem::take(&mut self.attachments)
+            .into_iter()
+            .partition(|attachment| attachment.folder == folder && attachment.file == file);
+        self.attachments = kept;
+        taken
+    }
+
+    /// Every tag in use with the number of pages carrying it.
+    pub fn tag_counts(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for page in self
+            .tags
+            .iter()
+            .filter(|page| !self.is_deleted(&page.folder, &page.file))
+        {
+            for tag in &page.tags {
+                *counts.entry(tag.clone()).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    pub fn tagged(&self, tag: &str) -> Vec<PageRef> {
+        self.tags
+            .iter()
+            .filter(|page| {
+                page.tags.iter().any(|t| t == tag) && !self.is_deleted(&page.folder, &page.file)
+            })
+            .map(|page| PageRef {
+                folder: page.folder.clone(),
+                file: page.file.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Whether `name` is taken by the app's own hidden pages, so it can't be
+/// the name of a page.
+pub fn is_reserved_page(name: &str) -> bool {
+    name.starts_with(SETTINGS_PAGE)
+}
+
+/// Comma-separated tags as typed, lowercased, without a leading `#` and
+/// without duplicates.
+pub fn parse_tags(input: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in input.split(',') {
+        let tag = tag.trim().trim_start_matches('#').trim().to_lowercase();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// The folder the settings page was created in, if it exists yet.
+pub fn settings_folder(metadata: &Value) -> Option<String> {
+    metadata["folders"]
+        .as_object()?
+        .iter()
+        .find(|(_, files)| files.get(SETTINGS_PAGE).is_some())
+        .map(|(folder, _)| folder.clone())
+}
+
+pub fn load_settings(
+    password: &str,
+    file_path: &Path,
+    metadata: &Value,
+) -> Result<JournalSettings, ClogError> {
+    match settings_folder(metadata) {
+        Some(folder) => {
+            let content = clog::read_page(password, file_path, SETTINGS_PAGE, &folder)?;
+            Ok(serde_json::from_str(&content)?)
+        }
+        None => Ok(JournalSettings::default()),
+    }
+}
+
+pub fn save_settings(
+    password: &str,
+    file_path: &Path,
+    metadata: &Value,
+    settings: &JournalSettings,
+) -> Result<(), ClogError> {
+    let content = serde_json::to_string(settings)?;
+    match settings_folder(metadata) {
+        Some(folder) => {
+            clog::update_page(password, file_path, SETTINGS_PAGE, &folder, &content, false)
+        }
+        None => clog::add_page(password, file_path, SETTINGS_PAGE, &content, false),
+    }
+}
+
+/// Adds an empty topic chapter, inside the topic `parent` if given. clog_rs
+/// has no folders of its own beyond one per day, so the chapter is recorded
+/// in `journal`, which is saved right away.
+pub fn add_folder(
+    password: &str,
+    file_path: &Path,
+    journal: &mut JournalSettings,
+    name: &str,
+    parent: Option<&str>,
+) -> Result<(), ClogError> {
+    journal.chapters.push(name.to_string());
+    if let Some(parent) = parent {
+        journal.sub_chapters.push(SubChapter {
+            chapter: name.to_string(),
+            parent: parent.to_string(),
+        });
+    }
+    let metadata = clog::read_metadata(password, file_path)?;
+    save_settings(password, file_path, &metadata, journal)
+}
+
+/// Deletes a page the only way clog_rs allows: its content is erased and it
+/// is recorded in `journal` as deleted, which is saved right away.
+pub fn delete_page(
+    password: &str,
+    file_path: &Path,
+    journal: &mut JournalSettings,
+    file: &str,
+    folder: &str,
+) -> Result<(), ClogError> {
+    let stored = journal.stored_in(folder, file).to_string();
+    clog::update_page(password, file_path, file, &stored, "", false)?;
+    journal.set_tags(folder, file, Vec::new());
+    journal.set_edited(folder, file, None);
+    journal.set_order(folder, file, None);
+    journal.deleted.push(PageRef {
+        folder: folder.to_string(),
+        file: file.to_string(),
+    });
+    let metadata = clog::read_metadata(password, file_path)?;
+    save_settings(password, file_path, &metadata, journal)
+}
+
+/// Deletes a page like `delete_page`, keeping its content, tags and edit
+/// time in the trash first.
+pub fn trash_page(
+    password: &str,
+    file_path: &Path,
+    journal: &mut JournalSettings,
+    file: &str,
+    folder: &str,
+    deleted_at: &str,
+) -> Result<(), ClogError> {
+    let stored = journal.stored_in(folder, file);
+    let content = clog::read_page(password, file_path, file, stored)?;
+    let attachments = journal.detach_all(folder, file);
+    journal.trash.push(TrashedPage {
+        folder: folder.to_string(),
+        file: file.to_string(),
+        content,
+        tags: journal.tags_of(folder, file).to_vec(),
+        edited: journal.edited_at(folder, file).map(str::to_string),
+        deleted_at: deleted_at.to_string(),
+        attachments,
+    });
+    delete_page(password, file_path, journal, file, folder)
+}
+
+/// Puts the trashed page at `index` back under the chapter it was deleted
+/// from, which is made again if it was a topic removed since, and saves
+/// `journal`. A page still blanked in the clog gets its content back in
+/// place; one the clog no longer has is added to `today`, under its topic
+/// or otherwise under today. Gives the chapter it is listed under, or
+/// `Err` with the chapter whose page already has its name.
+pub fn restore_page(
+    password: &str,
+    file_path: &Path,
+    journal: &mut JournalSettings,
+    index: usize,
+    today: &str,
+    compress: bool,
+) -> Result<Result<String, String>, ClogError> {
+    let page = journal.trash[index].clone();
+    let metadata = clog::read_metadata(password, file_path)?;
+    let is_day = chrono::NaiveDate::parse_from_str(&page.folder, "%d/%m/%Y").is_ok();
+    if !is_day && !journal.is_chapter(&page.folder) {
+        journal.chapters.push(page.folder.clone());
+    }
+    let stored = journal.stored_in(&page.folder, &page.file).to_string();
+    let in_clog = metadata["folders"][&stored].get(&page.file).is_some();
+    let chapter = if in_clog && journal.is_deleted(&page.folder, &page.file) {
+        clog::update_page(
+            password,
+            file_path,
+            &page.file,
+            &stored,
+            &page.content,
+            compress,
+        )?;
+        journal
+            .deleted
+            .retain(|deleted| deleted.folder != page.folder || deleted.file != page.file);
+        page.folder.clone()
+    } else if in_clog {
+        return Ok(Err(page.folder));
+    } else {
+        let owner = journal.listed_in(today, &page.file).to_string();
+        if metadata["folders"][today].get(&page.file).is_some()
+            && !journal.is_deleted(&owner, &page.file)
+        {
+            return Ok(Err(owner));
+        }
+        add_or_reuse_page(
+            password,
+            file_path,
+            journal,
+            &page.file,
+            &page.content,
+            today,
+            compress,
+        )?;
+        let chapter = if is_day { today } else { &page.folder };
+        journal.file_under(chapter, today, &page.file);
+        chapter.to_string()
+    };
+    journal.set_tags(&chapter, &page.file, page.tags);
+    journal.set_edited(&chapter, &page.file, page.edited);
+    journal
+        .attachments
+        .extend(page.attachments.into_iter().map(|attachment| Attachment {
+            folder: chapter.clone(),
+            ..attachment
+        }));
+    journal.trash.remove(index);
+    let metadata = clog::read_metadata(password, file_path)?;
+    save_settings(password, file_path, &metadata, journal)?;
+    Ok(Ok(chapter))
+}
+
+/// Renames a page of today's folder or of a topic chapter by copying it
+/// under the new name into `today` and deleting the old one. Its tags, edit
+/// time, place in the list and attachments go with it. A deleted page
+/// already holding the new name is reused, since clog_rs keeps it around.
+#[allow(clippy::too_many_arguments)]
+pub fn rename_page(
+    password: &str,
+    file_path: &Path,
+    journal: &mut JournalSettings,
+    folder: &str,
+    old_name: &str,
+    new_name: &str,
+    today: &str,
+    compress: bool,
+) -> Result<(), ClogError> {
+    let stored = journal.stored_in(folder, old_name);
+    let content = clog::read_page(password, file_path, old_name, stored)?;
+    add_or_reuse_page(
+        password, file_path, journal, new_name, &content, today, compress,
+    )?;
+    journal.file_under(folder, today, new_name);
+    let tags = journal.tags_of(folder, old_name).to_vec();
+    journal.set_tags(folder, new_name, tags);
+    let edited = journal.edited_at(folder, old_name).map(str::to_string);
+    journal.set_edited(folder, new_name, edited);
+    let order = journal.order_of(folder, old_name);
+    journal.set_order(folder, new_name, order);
+    for attachment in &mut journal.attachments {
+        if attachment.folder == folder && attachment.file == old_name {
+            attachment.file = new_name.to_string();
+        }
+    }
+    delete_page(password, file_path, journal, old_name, folder)
+}
+
+/// Adds a page named `name` to `today`. A deleted page of that name stored
+/// there is brought back with the content instead, since clog_rs keeps it
+/// around and a day can't hold two pages of one name.
+pub fn add_or_reuse_page(
+    password: &str,
+    file_path: &Path,
+    journal: &mut JournalSettings,
+    name: &str,
+    content: &str,
+    today: &str,
+    compress: bool,
+) -> Result<(), ClogError> {
+    let listed = journal.listed_in(today, name).to_string();
+    if journal.is_deleted(&listed, name) {
+        clog::update_page(password, file_path, name, today, content, compress)?;
+        journal
+            .deleted
+            .retain(|page| page.folder != listed || page.file != name);
+    } else {
+        clog::add_page(password, file_path, name, content, compress)?;
+    }
+    Ok(())
+}
+
+/// Attaches `bytes` to a page under `name`, in a new hidden page of
+/// `today`, and saves `journal`.
+#[allow(clippy::too_many_arguments)]
+pub fn add_attachment(
+    password: &str,
+    file_path: &Path,
+    journal: &mut JournalSettings,
+    page: &PageRef,
+    name: &str,
+    bytes: &[u8],
+    today: &str,
+    added: &str,
+    compress: bool,
+) -> Result<(), ClogError> {
+    let metadata = clog::read_metadata(password, file_path)?;
+    let taken = |hidden: &str| {
+        metadata["folders"][today].get(hidden).is_some()
+            || journal.attachments.iter().any(|a| a.page == hidden)
+            || journal
+                .trash
+                .iter()
+                .flat_map(|t| &t.attachments)
+                .any(|a| a.page == hidden)
+    };
+    let mut number = 1;
+    while taken(&format!("{}{}", ATTACHMENT_PAGE_PREFIX, number)) {
+        number += 1;
+    }
+    let hidden = format!("{}{}", ATTACHMENT_PAGE_PREFIX, number);
+    clog::add_binary_page(password, file_path, &hidden, bytes, compress)?;
+    journal.attachments.push(Attachment {
+        folder: page.folder.clone(),
+        file: page.file.clone(),
+        name: name.to_string(),
+        page: hidden,
+        stored: today.to_string(),
+        size: bytes.len() as u64,
+        added: added.to_string(),
+    });
+    let metadata = clog::read_metadata(password, file_path)?;
+    save_settings(password, file_path, &metadata, journal)
+}
+
+/// Erases the hidden pages of `attachments`, the only way clog_rs has of
+/// getting rid of them. The settings still listing them are left to the
+/// caller.
+pub fn erase_attachments(
+    password: &str,
+    file_path: &Path,
+    attachments: &[Attachment],
+) -> Result<(), ClogError> {
+    attachments.iter().try_for_each(|attachment| {
+        clog::update_page(
+            password,
+            file_path,
+            &attachment.page,
+            &attachment.stored,
+            "",
+            false,
+        )
+    })
+}
+
+/// Where the draft of the journal at `file_path` is kept: `<user>.draft`.
+pub fn draft_path(file_path: &Path) -> PathBuf {
+    file_path.with_extension("draft")
+}
+
+pub fn save_draft(password: &str, file_path: &Path, draft: &Draft) -> Result<(), ClogError> {
+    clog::write_draft(
+        password,
+        &draft_path(file_path),
+        &serde_json::to_value(draft)?,
+    )
+}
+
+/// The journal's draft, if it has one.
+pub fn load_draft(password: &str, file_path: &Path) -> Result<Option<Draft>, ClogError> {
+    let path = draft_path(file_path);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let draft = clog::read_draft(password, &path)?;
+    Ok(Some(serde_json::from_value(draft)?))
+}
+
+pub fn discard_draft(file_path: &Path) -> Result<(), AppError> {
+    match fs::remove_file(draft_path(file_path)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// A recovery on disk, as its name tells: its journal and when it was
+/// kept. What it holds is sealed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecoveryFile {
+    pub name: String,
+    pub user_file: String,
+    pub saved: Option<NaiveDateTime>,
+}
+
+/// Keeps content that came back from the external editor, sealed with the
+/// journal's password like a draft, in case saving it to the journal
+/// fails. Each is `recovery/<user>.clog.<yyyymmdd-HHMMSS>.recovery` in the
+/// config directory, numbered when two come in the same second. Older ones
+/// are pruned first.
+pub fn save_recovery(
+    password: &str,
+    config_dir: &Path,
+    user_file: &str,
+    draft: &Draft,
+) -> Result<(), ClogError> {
+    prune_recoveries(config_dir);
+    let dir = config_dir.join(RECOVERY_DIR);
+    fs::create_dir_all(&dir)?;
+    let stem = format!(
+        "{}.{}",
+        user_file,
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    );
+    let path = (1..)
+        .map(|n| match n {
+            1 => dir.join(format!("{}.{}", stem, RECOVERY_EXTENSION)),
+            n => dir.join(format!("{}-{}.{}", stem, n, RECOVERY_EXTENSION)),
+        })
+        .find(|path| !path.exists())
+        .unwrap_or_else(|| dir.join(&stem));
+    clog::write_draft(password, &path, &serde_json::to_value(draft)?)
+}
+
+/// Recoveries kept for a day, newest first.
+pub fn list_recoveries(config_dir: &Path) -> Vec<RecoveryFile> {
+    prune_recoveries(config_dir);
+    let Ok(entries) = fs::read_dir(config_dir.join(RECOVERY_DIR)) else {
+        return Vec::new();
+    };
+    let mut recoveries: Vec<(String, RecoveryFile)> = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter_map(|name| {
+            let stem = name.strip_suffix(&format!(".{}", RECOVERY_EXTENSION))?;
+            let (user_file, stamp) = stem.rsplit_once('.')?;
+            let saved = stamp
+                .get(..15)
+                .and_then(|time| NaiveDateTime::parse_from_str(time, "%Y%m%d-%H%M%S").ok());
+            let recovery = RecoveryFile {
+                name: name.clone(),
+                user_file: user_file.to_string(),
+                saved,
+            };
+            Some((stamp.to_string(), recovery))
+        })
+        .collect();
+    recoveries.sort_by(|a, b| b.0.cmp(&a.0));
+    recoveries
+        .into_iter()
+        .map(|(_, recovery)| recovery)
+        .collect()
+}
+
+/// Opens a recovery with the password of its journal.
+pub fn load_recovery(password: &str, config_dir: &Path, name: &str) -> Result<Draft, ClogError> {
+    let draft = clog::read_draft(password, &config_dir.join(RECOVERY_DIR).join(name))?;
+    Ok(serde_json::from_value(draft)?)
+}
+
+pub fn discard_recovery(config_dir: &Path, name: &str) -> Result<(), AppError> {
+    match fs::remove_file(config_dir.join(RECOVERY_DIR).join(name)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+fn prune_recoveries(config_dir: &Path) {
+    let Ok(entries) = fs::read_dir(config_dir.join(RECOVERY_DIR)) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let expired = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age > RECOVERY_KEPT);
+        if expired {
+            fs::remove_file(entry.path()).ok();
+        }
+    }
+}
+
+/// Journal titles shown on the user list, keyed by clog file name. The list
+/// is drawn before any password is entered, so titles are remembered here in
+/// plain text once a journal has been unlocked; descriptions are not.
+pub fn load_title_cache(config_dir: &Path) -> BTreeMap<String, String> {
+    fs::read_to_string(config_dir.join(TITLE_CACHE_FILE))
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+pub fn remember_title(config_dir: &Path, user_file: &str, title: &str) -> Result<(), AppError> {
+    let mut titles = load_title_cache(config_dir);
+    let changed = if title.is_empty() {
+        titles.remove(user_file).is_some()
+    } else {
+        titles.insert(user_file.to_string(), title.to_string()) != Some(title.to_string())
+    };
+    if changed {
+        fs::create_dir_all(config_dir)?;
+        fs::write(
+            config_dir.join(TITLE_CACHE_FILE),
+            serde_json::to_string_pretty(&titles)?,
+        )?;
+    }
+    Ok(())
+}
+
+/// Clog file names of the journals unlocked last, most recent first.
+pub fn load_recent_users(config_dir: &Path) -> Vec<String> {
+    fs::read_to_string(config_dir.join(RECENT_USERS_FILE))
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+/// Moves `user_file` to the front of the recent journals, keeping
+/// `RECENT_USERS` of them.
+pub fn remember_recent_user(config_dir: &Path, user_file: &str) -> Result<(), AppError> {
+    let mut recent = load_recent_users(config_dir);
+    if recent.first().map(String::as_str) == Some(user_file) {
+        return Ok(());
+    }
+    recent.retain(|file| file != user_file);
+    recent.insert(0, user_file.to_string());
+    recent.truncate(RECENT_USERS);
+    fs::create_dir_all(config_dir)?;
+    fs::write(
+        config_dir.join(RECENT_USERS_FILE),
+        serde_json::to_string_pretty(&recent)?,
+    )?;
+    Ok(())
+}
+
+fn load_last_pages(config_dir: &Path) -> BTreeMap<String, PageRef> {
+    fs::read_to_string(config_dir.join(LAST_PAGES_FILE))
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+/// The page last opened for editing in a journal, kept only while
+/// `reopen_last_page` is on.
+pub fn load_last_page(config_dir: &Path, user_file: &str) -> Option<PageRef> {
+    load_last_pages(config_dir).remove(user_file)
+}
+
+pub fn remember_last_page(
+    config_dir: &Path,
+    user_file: &str,
+    page: PageRef,
+) -> Result<(), AppError> {
+    let mut pages = load_last_pages(config_dir);
+    if pages.get(user_file) == Some(&page) {
+        return Ok(());
+    }
+    pages.insert(user_file.to_string(), page);
+    fs::create_dir_all(config_dir)?;
+    fs::write(
+        config_dir.join(LAST_PAGES_FILE),
+        serde_json::to_string_pretty(&pages)?,
+    )?;
+    Ok(())
+}
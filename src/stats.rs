@@ -0,0 +1,56 @@
+//! Length of page content, for the writer rather than the storage.
+
+use chrono::NaiveDate;
+
+pub fn word_count(content: &str) -> usize {
+    content.split_whitespace().count()
+}
+
+pub fn char_count(content: &str) -> usize {
+    content.chars().count()
+}
+
+/// `"N words · M chars"`, shown wherever a page's length is displayed.
+pub fn summary(content: &str) -> String {
+    format!(
+        "{} words · {} chars",
+        word_count(content),
+        char_count(content)
+    )
+}
+
+/// Totals over every page of a journal, for the statistics screen.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JournalStats {
+    pub entries: usize,
+    pub days: usize,
+    pub words: usize,
+    pub longest_streak: usize,
+}
+
+impl JournalStats {
+    pub fn average_words(&self) -> usize {
+        (self.words + self.entries / 2)
+            .checked_div(self.entries)
+            .unwrap_or(0)
+    }
+}
+
+/// Most consecutive days in `dates` with something written.
+pub fn longest_streak(dates: &[NaiveDate]) -> usize {
+    let mut dates = dates.to_vec();
+    dates.sort();
+    dates.dedup();
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for date in dates {
+        run = match previous {
+            Some(previous) if previous.succ_opt() == Some(date) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(date);
+    }
+    longest
+}
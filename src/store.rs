@@ -0,0 +1,53 @@
+//! Decrypted metadata of the open journal, kept between screens so going
+//! back and forth between chapters and pages doesn't decrypt the file again.
+
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use zeroize::Zeroize;
+
+/// Holds the metadata of one journal at a time.
+///
+/// The cache is only as good as its invalidation. It is dropped:
+/// - after every effect that writes to a journal (`SideEffect::writes`),
+///   which covers adds, edits, deletes, renames, tags, imports and restores;
+/// - when the journal is locked, so nothing decrypted outlives the password
+///   on screen;
+/// - when a different journal or password is asked for.
+#[derive(Default)]
+pub struct DecryptedStore {
+    cached: Option<(PathBuf, String, Rc<Value>)>,
+}
+
+impl DecryptedStore {
+    /// The journal's metadata if it was decrypted since the cache was last
+    /// dropped.
+    pub fn cached(&self, password: &str, file_path: &Path) -> Option<Rc<Value>> {
+        match &self.cached {
+            Some((path, cached_password, metadata))
+                if path == file_path && cached_password == password =>
+            {
+                Some(Rc::clone(metadata))
+            }
+            _ => None,
+        }
+    }
+
+    /// Keeps freshly decrypted metadata in place of whatever was cached.
+    pub fn keep(&mut self, password: &str, file_path: &Path, metadata: Value) -> Rc<Value> {
+        self.invalidate();
+        let metadata = Rc::new(metadata);
+        self.cached = Some((
+            file_path.to_path_buf(),
+            password.to_string(),
+            Rc::clone(&metadata),
+        ));
+        metadata
+    }
+
+    pub fn invalidate(&mut self) {
+        if let Some((_, mut password, _)) = self.cached.take() {
+            password.zeroize();
+        }
+    }
+}
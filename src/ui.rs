@@ -0,0 +1,1643 @@
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{
+        Block, Borders, Clear, List, ListItem, ListState, Paragraph, Scrollbar,
+        ScrollbarOrientation, ScrollbarState, Wrap,
+    },
+};
+
+use chrono::{Datelike, Local, NaiveDate};
+use std::collections::{BTreeSet, HashSet};
+
+use crate::app::{
+    ADD_PAGE_ITEM, App, AppState, CORRUPT_BADGE, KeyHint, NEW_CHAPTER_ITEM, Recovery,
+    SUB_CHAPTER_BADGE, TODAY_BADGE, UNRECOGNIZED_BADGE, YESTERDAY_BADGE, folder_label,
+    parse_folder_date, relative_day, timestamp_label,
+};
+use crate::config::Theme;
+use crate::editor::{Editor, Mode};
+use crate::password::{self, Strength};
+use crate::stats::{self, JournalStats};
+use crate::viewer::{ViewOptions, Viewer};
+
+/// Narrowest list area that still gets a preview pane beside the list.
+const MIN_PREVIEW_WIDTH: u16 = 60;
+/// Smallest terminal the screens are laid out in; below it only a notice
+/// is drawn, since the fixed-height title and footer blocks don't fit.
+/// Twelve rows leave one list row between them and the breadcrumb.
+const MIN_WIDTH: u16 = 20;
+const MIN_HEIGHT: u16 = 12;
+
+fn too_small(area: Rect) -> bool {
+    area.width < MIN_WIDTH || area.height < MIN_HEIGHT
+}
+
+pub fn draw(f: &mut Frame, app: &App) {
+    let theme = &app.screen_theme();
+    if too_small(f.area()) {
+        // Nothing on screen can be clicked until the layout is back.
+        app.list_area.set(None);
+        render_too_small(f, theme);
+        return;
+    }
+    // Every screen is drawn below the breadcrumb; popups still cover the
+    // whole terminal.
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(1)])
+        .split(f.area());
+    render_breadcrumb(f, rows[0], &app.breadcrumb(), theme);
+    let area = rows[1];
+    let help_text = &app.get_help_text();
+    let journal_header = match (app.journal.title.as_str(), app.journal.description.as_str()) {
+        ("", _) => String::new(),
+        (title, "") => title.to_string(),
+        (title, description) => format!("{} — {}", title, description),
+    };
+    // The list keeps its scroll position between frames, so moving the
+    // selection only scrolls once it reaches an edge.
+    let offset = app.list_area.take().map_or(0, |(_, offset)| offset);
+    match &app.state {
+        AppState::SelectProfile => app.list_area.set(render_menu_ui(
+            f,
+            area,
+            "Select Profile",
+            "",
+            &app.items,
+            app.selected_index,
+            help_text,
+            false,
+            &BTreeSet::new(),
+            &[],
+            None,
+            offset,
+            theme,
+        )),
+        AppState::SelectUser => app.list_area.set(render_menu_ui(
+            f,
+            area,
+            "Select User",
+            &app.profile
+                .as_ref()
+                .filter(|_| app.has_profiles())
+                .map(|name| format!("Profile: {}", name))
+                .unwrap_or_default(),
+            &app.items,
+            app.selected_index,
+            help_text,
+            false,
+            &BTreeSet::new(),
+            &user_colors(app),
+            None,
+            offset,
+            theme,
+        )),
+        AppState::SelectFolder(_, _) => app.list_area.set(render_menu_ui(
+            f,
+            area,
+            "Select Chapter",
+            &journal_header,
+            &app.items
+                .iter()
+                .map(|(folder, badge)| {
+                    let badge = match relative_day(folder, Local::now().date_naive()) {
+                        Some(day) if badge.is_empty() => day,
+                        Some(day) => format!("{} · {}", day, badge),
+                        None => badge.clone(),
+                    };
+                    (folder_label(folder), badge)
+                })
+                .collect::<Vec<_>>(),
+            app.selected_index,
+            help_text,
+            true,
+            &BTreeSet::new(),
+            &chapter_colors(app),
+            None,
+            offset,
+            theme,
+        )),
+        AppState::SelectFile(_, _, folder) => app.list_area.set(render_menu_ui(
+            f,
+            area,
+            &format!(
+                "Select Page in {}",
+                app.journal
+                    .chapter_path(folder)
+                    .into_iter()
+                    .map(folder_label)
+                    .collect::<Vec<_>>()
+                    .join(" › ")
+            ),
+            &journal_header,
+            &app.items,
+            app.selected_index,
+            help_text,
+            true,
+            &app.marked,
+            &[],
+            Some(preview(app)),
+            offset,
+            theme,
+        )),
+        AppState::ChapterResults(_, _, folder, query) => app.list_area.set(render_menu_ui(
+            f,
+            area,
+            &format!("'{}' in {}", query, folder_label(folder)),
+            &journal_header,
+            &app.items,
+            app.selected_index,
+            help_text,
+            true,
+            &BTreeSet::new(),
+            &[],
+            None,
+            offset,
+            theme,
+        )),
+        AppState::Calendar(_, _, date) => {
+            let entry_days: HashSet<NaiveDate> = app
+                .items
+                .iter()
+                .filter_map(|(folder, _)| parse_folder_date(folder))
+                .collect();
+            render_calendar(
+                f,
+                area,
+                *date,
+                &entry_days,
+                &journal_header,
+                help_text,
+                theme,
+            )
+        }
+        AppState::SelectTag(_, _) => app.list_area.set(render_menu_ui(
+            f,
+            area,
+            "Tags",
+            &journal_header,
+            &app.items,
+            app.selected_index,
+            help_text,
+            true,
+            &BTreeSet::new(),
+            &[],
+            None,
+            offset,
+            theme,
+        )),
+        AppState::Timeline(_, _) => app.list_area.set(render_menu_ui(
+            f,
+            area,
+            "Timeline",
+            &journal_header,
+            &app.items,
+            app.selected_index,
+            help_text,
+            true,
+            &BTreeSet::new(),
+            &[],
+            None,
+            offset,
+            theme,
+        )),
+        AppState::Trash(_, _) => app.list_area.set(render_menu_ui(
+            f,
+            area,
+            "Trash",
+            &journal_header,
+            &app.items,
+            app.selected_index,
+            help_text,
+            true,
+            &BTreeSet::new(),
+            &[],
+            None,
+            offset,
+            theme,
+        )),
+        AppState::Attachments(_, _, _, file) => app.list_area.set(render_menu_ui(
+            f,
+            area,
+            &format!("Attachments of '{}'", file),
+            &journal_header,
+            &app.items,
+            app.selected_index,
+            help_text,
+            true,
+            &BTreeSet::new(),
+            &[],
+            None,
+            offset,
+            theme,
+        )),
+        AppState::TagResults(_, _, tag) => app.list_area.set(render_menu_ui(
+            f,
+            area,
+            &format!("#{}", tag),
+            &journal_header,
+            &app.items,
+            app.selected_index,
+            help_text,
+            true,
+            &BTreeSet::new(),
+            &[],
+            None,
+            offset,
+            theme,
+        )),
+        AppState::SearchResults(_, _, query) => app.list_area.set(render_menu_ui(
+            f,
+            area,
+            &format!("'{}' in all pages", query),
+            &journal_header,
+            &app.items,
+            app.selected_index,
+            help_text,
+            true,
+            &BTreeSet::new(),
+            &[],
+            None,
+            offset,
+            theme,
+        )),
+        AppState::Stats(_, _) => render_stats(
+            f,
+            area,
+            app.journal_stats.as_ref(),
+            &journal_header,
+            help_text,
+            theme,
+        ),
+        AppState::RecoverDraft(_, _) => render_draft(
+            f,
+            area,
+            &draft_title(app.recovery.as_ref()),
+            app.recovery.as_ref(),
+            &journal_header,
+            help_text,
+            theme,
+        ),
+        AppState::Recoveries => app.list_area.set(render_menu_ui(
+            f,
+            area,
+            "Editor Recoveries",
+            "",
+            &app.items,
+            app.selected_index,
+            help_text,
+            false,
+            &BTreeSet::new(),
+            &[],
+            None,
+            offset,
+            theme,
+        )),
+        AppState::ViewRecovery(_) => render_draft(
+            f,
+            area,
+            &recovery_title(app.recovery.as_ref()),
+            app.recovery.as_ref(),
+            &journal_header,
+            help_text,
+            theme,
+        ),
+        AppState::QuickOpen(_, _) => app.list_area.set(render_quick_open(
+            f,
+            area,
+            &app.input_buffer,
+            app.input_cursor,
+            &app.items,
+            app.selected_index,
+            offset,
+            help_text,
+            theme,
+        )),
+        AppState::InputPrompt(prompt, next) => render_prompt(
+            f,
+            area,
+            prompt,
+            PromptStyle {
+                masked: app.state.is_password_prompt() && !app.reveal_password,
+                // Only a new journal's password, which is still being chosen.
+                strength_meter: matches!(**next, AppState::SelectFolder(_, _)),
+                placeholder: app.input_default.as_deref(),
+                note: None,
+            },
+            &app.input_buffer,
+            app.input_cursor,
+            help_text,
+            theme,
+        ),
+        AppState::AddPagePrompt(_, _, _) => render_prompt(
+            f,
+            area,
+            "Enter page name:",
+            PromptStyle {
+                placeholder: app.input_default.as_deref(),
+                note: app
+                    .writing_prompt()
+                    .map(|prompt| ("Writing prompt of the day", prompt)),
+                ..PromptStyle::default()
+            },
+            &app.input_buffer,
+            app.input_cursor,
+            help_text,
+            theme,
+        ),
+        AppState::ViewPage(_, _, _, _) => {
+            if let Some(viewer) = &app.viewer {
+                render_viewer(
+                    f,
+                    area,
+                    viewer,
+                    app.view_options,
+                    app.dictionary.as_ref(),
+                    (&app.input_buffer, app.input_cursor),
+                    help_text,
+                    theme,
+                );
+            }
+        }
+        AppState::Locked(resume, _) => render_prompt(
+            f,
+            area,
+            &format!(
+                "Locked — enter password for {}:",
+                resume.user_path().unwrap_or_default()
+            ),
+            PromptStyle {
+                masked: !app.reveal_password,
+                strength_meter: false,
+                placeholder: None,
+                note: None,
+            },
+            &app.input_buffer,
+            app.input_cursor,
+            help_text,
+            theme,
+        ),
+        AppState::InlineEdit(write) => {
+            if let Some(editor) = &app.editor {
+                render_editor(f, area, &write.file, editor, help_text, theme);
+            }
+        }
+        AppState::Confirm(question, _, _) => {
+            render_confirm(f, area, "Confirm", question, help_text, theme)
+        }
+        AppState::JournalInUse(_, _, notice) => {
+            render_confirm(f, area, "Journal in use", notice, help_text, theme)
+        }
+        AppState::EditOrViewFile(_, _, _, _)
+        | AppState::EnterNewUser
+        | AppState::EnterPassword(_)
+        | AppState::NewChapter(_, _, _)
+        | AppState::EditJournalTitle(_, _)
+        | AppState::EditJournalDescription(_, _, _)
+        | AppState::SearchChapter(_, _, _)
+        | AppState::SearchPrompt(_, _)
+        | AppState::RenamePage(_, _, _, _)
+        | AppState::DuplicatePage(_, _, _, _)
+        | AppState::ExportPage(_, _, _, _, _)
+        | AppState::ExportChapter(_, _, _)
+        | AppState::ImportPage(_, _, _, _)
+        | AppState::ReplaceText(_, _)
+        | AppState::NewPageTags(_, _, _, _)
+        | AppState::EditTags(_, _, _, _)
+        | AppState::AttachFile(_, _, _, _)
+        | AppState::ExtractAttachment(_, _, _, _, _)
+        | AppState::OpenRecovery(_)
+        | AppState::Export(_, _)
+        | AppState::Import(_, _) => {}
+    }
+
+    if app.show_help {
+        render_help(f, &app.help_sections(), theme);
+    }
+    if let Some((title, message)) = &app.message {
+        render_message(f, message, title, theme);
+    }
+}
+
+/// The breadcrumb, indented to line up with the blocks below it. When it
+/// doesn't fit, the places furthest up give way to `…` first, then the end
+/// of the last one.
+fn render_breadcrumb(f: &mut Frame, area: Rect, crumbs: &[String], theme: &Theme) {
+    const SEPARATOR: &str = " › ";
+    let Some((last, path)) = crumbs.split_last() else {
+        return;
+    };
+    let width = usize::from(area.width.saturating_sub(2));
+    let fits = |path: &[String]| Line::from(path.join(SEPARATOR)).width() <= width;
+    let mut shown = crumbs.to_vec();
+    let mut dropped = 0;
+    while !fits(&shown) && dropped < path.len() {
+        dropped += 1;
+        shown = std::iter::once("…".to_string())
+            .chain(crumbs[dropped..].iter().cloned())
+            .collect();
+    }
+    let mut text = shown[..shown.len() - 1].join(SEPARATOR);
+    if !text.is_empty() {
+        text.push_str(SEPARATOR);
+    }
+    let room = width.saturating_sub(Line::from(text.as_str()).width());
+    let mut last = last.clone();
+    if Line::from(last.as_str()).width() > room {
+        while Line::from(last.as_str()).width() + 1 > room && last.pop().is_some() {}
+        last.push('…');
+    }
+    let breadcrumb = Paragraph::new(Line::from(vec![
+        Span::styled(text, Style::default().fg(Color::Gray)),
+        Span::styled(
+            last,
+            Style::default()
+                .fg(theme.title)
+                .add_modifier(Modifier::BOLD),
+        ),
+    ]));
+    f.render_widget(breadcrumb, area.inner(Margin::new(1, 0)));
+}
+
+/// Each journal on the user list in the colour of its screens.
+fn user_colors(app: &App) -> Vec<Option<Color>> {
+    app.items
+        .iter()
+        .map(|(file, _)| file.ends_with(".clog").then(|| app.journal_color(file)))
+        .collect()
+}
+
+/// Chapters on the chapter list in the colour given to them with `o`.
+fn chapter_colors(app: &App) -> Vec<Option<Color>> {
+    app.items
+        .iter()
+        .map(|(folder, _)| app.journal.color_of(folder)?.parse().ok())
+        .collect()
+}
+
+/// Title and text of the preview pane for the highlighted row of the page
+/// list. A page's title counts its words once it has been read.
+fn preview(app: &App) -> (String, String) {
+    let title = "Preview".to_string();
+    let text = match app.items.get(app.selected_index) {
+        Some((file, _)) if file == ADD_PAGE_ITEM => {
+            "Press Enter to add a new page to this chapter."
+        }
+        Some((file, _)) if file == NEW_CHAPTER_ITEM => {
+            "Press Enter to add a chapter inside this one."
+        }
+        Some((_, badge)) if badge == SUB_CHAPTER_BADGE => "Press Enter to open this chapter.",
+        Some((file, _)) => match app.previews.get(file) {
+            Some(Some(content)) => {
+                let title = format!("{} · {} words", title, stats::word_count(content));
+                return if content.trim().is_empty() {
+                    (title, "(empty page)".to_string())
+                } else {
+                    (title, content.clone())
+                };
+            }
+            Some(None) => "(this page couldn't be read)",
+            // Read before the next frame.
+            None => "",
+        },
+        None => "",
+    };
+    (title, text.to_string())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn render_menu_ui(
+    f: &mut Frame,
+    area: Rect,
+    title: &str,
+    header: &str,
+    items: &[(String, String)],
+    selected_index: usize,
+    help_text: &str,
+    show_back: bool,
+    marked: &BTreeSet<String>,
+    colors: &[Option<Color>],
+    preview: Option<(String, String)>,
+    offset: usize,
+    theme: &Theme,
+) -> Option<(Rect, usize)> {
+    let main_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(3)])
+        .split(area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([Constraint::Length(3), Constraint::Min(1)])
+        .split(main_chunks[0]);
+
+    let title_widget = Paragraph::new(title)
+        .style(
+            Style::default()
+                .fg(theme.title)
+                .add_modifier(Modifier::BOLD),
+        )
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .title(header)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.title)),
+        );
+    f.render_widget(title_widget, chunks[0]);
+
+    // The preview takes the right two thirds when there is room for it.
+    let mut list_chunk = chunks[1];
+    if let Some((preview_title, preview)) = preview
+        && list_chunk.width >= MIN_PREVIEW_WIDTH
+    {
+        let halves = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Ratio(1, 3), Constraint::Ratio(2, 3)])
+            .split(list_chunk);
+        list_chunk = halves[0];
+        let preview_widget = Paragraph::new(preview)
+            .style(Style::default().fg(theme.text))
+            .block(
+                Block::default()
+                    .title(preview_title)
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.border)),
+            )
+            .wrap(Wrap { trim: false });
+        f.render_widget(preview_widget, halves[1]);
+    }
+
+    let mut list_area = None;
+    if !items.is_empty() {
+        list_area = Some(render_list(
+            f,
+            list_chunk,
+            items,
+            selected_index,
+            marked,
+            colors,
+            offset,
+            theme,
+        ));
+    } else if show_back {
+        let empty_msg = Paragraph::new("No items available")
+            .style(Style::default().fg(Color::Gray))
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .title("Options")
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.border)),
+            );
+        f.render_widget(empty_msg, list_chunk);
+    }
+
+    let help_widget = Paragraph::new(help_text)
+        .style(Style::default().fg(theme.help))
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Controls")
+                .border_style(Style::default().fg(theme.help)),
+        );
+    f.render_widget(help_widget, main_chunks[1]);
+    list_area
+}
+
+/// Draws the rows of a list screen and returns where they went, with the
+/// index of the first visible row.
+#[allow(clippy::too_many_arguments)]
+fn render_list(
+    f: &mut Frame,
+    area: Rect,
+    items: &[(String, String)],
+    selected_index: usize,
+    marked: &BTreeSet<String>,
+    colors: &[Option<Color>],
+    offset: usize,
+    theme: &Theme,
+) -> (Rect, usize) {
+    let list_items: Vec<ListItem> = items
+        .iter()
+        .enumerate()
+        .map(|(i, (item, metadata))| {
+            let marker = match (marked.is_empty(), marked.contains(item)) {
+                (true, _) => "",
+                (false, true) => "✓ ",
+                (false, false) => "  ",
+            };
+            let badge_style =
+                if metadata.starts_with(TODAY_BADGE) || metadata.starts_with(YESTERDAY_BADGE) {
+                    Style::default()
+                        .fg(theme.title)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                        .fg(Color::Gray)
+                        .add_modifier(Modifier::ITALIC)
+                };
+            let line = if metadata.is_empty() {
+                Line::from(vec![Span::raw(marker), Span::raw(item)])
+            } else {
+                Line::from(vec![
+                    Span::raw(marker),
+                    Span::raw(item),
+                    Span::raw(" "),
+                    Span::styled(format!("[{}]", metadata), badge_style),
+                ])
+            };
+
+            if i == selected_index {
+                ListItem::new(line).style(
+                    Style::default()
+                        .bg(theme.highlight)
+                        .fg(Color::White)
+                        .add_modifier(Modifier::BOLD),
+                )
+            } else if metadata == UNRECOGNIZED_BADGE {
+                ListItem::new(line).style(Style::default().fg(Color::DarkGray))
+            } else if metadata == CORRUPT_BADGE {
+                ListItem::new(line).style(Style::default().fg(theme.error))
+            } else {
+                let color = colors.get(i).copied().flatten();
+                ListItem::new(line).style(Style::default().fg(color.unwrap_or(theme.text)))
+            }
+        })
+        .collect();
+
+    let list = List::new(list_items)
+        .block(
+            Block::default()
+                .title(format!("Options ({}/{})", selected_index + 1, items.len()))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.border)),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol("► ");
+
+    let mut state = ListState::default().with_offset(offset);
+    state.select(Some(selected_index));
+    f.render_stateful_widget(list, area, &mut state);
+    (area.inner(Margin::new(1, 1)), state.offset())
+}
+
+/// Input line with the pages matching it listed underneath.
+#[allow(clippy::too_many_arguments)]
+pub fn render_quick_open(
+    f: &mut Frame,
+    area: Rect,
+    query: &str,
+    cursor: usize,
+    items: &[(String, String)],
+    selected_index: usize,
+    offset: usize,
+    help_text: &str,
+    theme: &Theme,
+) -> Option<(Rect, usize)> {
+    let main_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(3)])
+        .split(area);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([Constraint::Length(3), Constraint::Min(1)])
+        .split(main_chunks[0]);
+
+    let input_widget = Paragraph::new(query)
+        .style(Style::default().fg(theme.text))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Go to page")
+                .border_style(Style::default().fg(theme.title)),
+        );
+    f.render_widget(input_widget, chunks[0]);
+    let before_cursor: String = query.chars().take(cursor).collect();
+    let cursor_x = chunks[0].x + 1 + Line::from(before_cursor).width() as u16;
+    f.set_cursor_position((
+        cursor_x.min(chunks[0].right().saturating_sub(2)),
+        chunks[0].y + 1,
+    ));
+
+    let mut list_area = None;
+    if items.is_empty() {
+        let empty_msg = Paragraph::new("No matching pages")
+            .style(Style::default().fg(Color::Gray))
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .title("Pages")
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.border)),
+            );
+        f.render_widget(empty_msg, chunks[1]);
+    } else {
+        list_area = Some(render_list(
+            f,
+            chunks[1],
+            items,
+            selected_index,
+            &BTreeSet::new(),
+            &[],
+            offset,
+            theme,
+        ));
+    }
+
+    let help_widget = Paragraph::new(help_text)
+        .style(Style::default().fg(theme.help))
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Controls")
+                .border_style(Style::default().fg(theme.help)),
+        );
+    f.render_widget(help_widget, main_chunks[1]);
+    list_area
+}
+
+pub fn render_stats(
+    f: &mut Frame,
+    area: Rect,
+    stats: Option<&JournalStats>,
+    header: &str,
+    help_text: &str,
+    theme: &Theme,
+) {
+    let main_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(3)])
+        .split(area);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([Constraint::Length(3), Constraint::Min(1)])
+        .split(main_chunks[0]);
+
+    let title_widget = Paragraph::new("Statistics")
+        .style(
+            Style::default()
+                .fg(theme.title)
+                .add_modifier(Modifier::BOLD),
+        )
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .title(header)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.title)),
+        );
+    f.render_widget(title_widget, chunks[0]);
+
+    let number = Style::default()
+        .fg(theme.highlight)
+        .add_modifier(Modifier::BOLD);
+    let row = |label: &'static str, value: usize, unit: &'static str| {
+        Line::from(vec![
+            Span::raw(format!("{:<24}", label)),
+            Span::styled(value.to_string(), number),
+            Span::raw(unit),
+        ])
+    };
+    let lines = match stats {
+        Some(stats) => vec![
+            row("Entries", stats.entries, ""),
+            row("Days written", stats.days, ""),
+            row(
+                "Longest streak",
+                stats.longest_streak,
+                if stats.longest_streak == 1 {
+                    " day"
+                } else {
+                    " days"
+                },
+            ),
+            row("Total words", stats.words, ""),
+            row("Average words per entry", stats.average_words(), ""),
+        ],
+        None => vec![Line::from("Counting…")],
+    };
+    let body = Paragraph::new(lines)
+        .style(Style::default().fg(theme.text))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.border)),
+        );
+    f.render_widget(body, chunks[1]);
+
+    let help_widget = Paragraph::new(help_text)
+        .style(Style::default().fg(theme.help))
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Controls")
+                .border_style(Style::default().fg(theme.help)),
+        );
+    f.render_widget(help_widget, main_chunks[1]);
+}
+
+/// How a prompt shows what is typed into it. The two are independent: a
+/// password is masked whether or not its strength is shown.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PromptStyle<'a> {
+    /// Shows `•` for every character typed.
+    pub masked: bool,
+    /// Rates the input as a password under the input box.
+    pub strength_meter: bool,
+    /// Shown dimmed while nothing is typed: what Enter accepts as it is.
+    pub placeholder: Option<&'a str>,
+    /// Text shown in a box of its own under the input, such as the writing
+    /// prompt of the day.
+    pub note: Option<(&'a str, &'a str)>,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn render_prompt(
+    f: &mut Frame,
+    area: Rect,
+    prompt: &str,
+    style: PromptStyle,
+    input_buffer: &str,
+    input_cursor: usize,
+    help_text: &str,
+    theme: &Theme,
+) {
+    let popup_area = centered_rect(80, 80, area);
+    f.render_widget(Clear, popup_area);
+
+    let meter_height = if style.strength_meter { 1 } else { 0 };
+    let note_height = style.note.map_or(0, |(_, note)| {
+        let width = popup_area.width.saturating_sub(2).max(1) as usize;
+        Line::from(note).width().div_ceil(width).clamp(1, 4) as u16 + 2
+    });
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(meter_height),
+            Constraint::Length(note_height),
+            Constraint::Length(3),
+        ])
+        .split(popup_area);
+
+    let prompt_widget = Paragraph::new(prompt)
+        .style(
+            Style::default()
+                .fg(theme.title)
+                .add_modifier(Modifier::BOLD),
+        )
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.title)),
+        );
+    f.render_widget(prompt_widget, chunks[0]);
+
+    let display_text = if style.masked {
+        "•".repeat(input_buffer.chars().count())
+    } else {
+        input_buffer.to_string()
+    };
+
+    let input_text = match style.placeholder {
+        Some(placeholder) if input_buffer.is_empty() => {
+            Line::styled(placeholder, Style::default().fg(theme.help))
+        }
+        _ => Line::raw(display_text.as_str()),
+    };
+    let input_widget = Paragraph::new(input_text)
+        .style(Style::default().fg(theme.text))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Input")
+                .border_style(Style::default().fg(theme.border)),
+        );
+    f.render_widget(input_widget, chunks[1]);
+
+    let before_cursor: String = display_text.chars().take(input_cursor).collect();
+    let cursor_x = chunks[1].x + 1 + Line::from(before_cursor).width() as u16;
+    f.set_cursor_position((
+        cursor_x.min(chunks[1].right().saturating_sub(2)),
+        chunks[1].y + 1,
+    ));
+
+    if style.strength_meter && !input_buffer.is_empty() {
+        let strength = password::strength(input_buffer);
+        let color = match strength {
+            Strength::Weak => theme.error,
+            Strength::Fair => Color::Yellow,
+            Strength::Strong => Color::Green,
+        };
+        let meter = Line::from(vec![
+            Span::raw(" Strength: "),
+            Span::styled(
+                format!(
+                    "{}{} ",
+                    "■".repeat(strength.level()),
+                    "□".repeat(3 - strength.level())
+                ),
+                Style::default().fg(color),
+            ),
+            Span::styled(strength.label(), Style::default().fg(color)),
+        ]);
+        f.render_widget(Paragraph::new(meter), chunks[2]);
+    }
+
+    if let Some((title, note)) = style.note {
+        let note_widget = Paragraph::new(note)
+            .style(
+                Style::default()
+                    .fg(theme.text)
+                    .add_modifier(Modifier::ITALIC),
+            )
+            .wrap(Wrap { trim: true })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(title)
+                    .border_style(Style::default().fg(theme.border)),
+            );
+        f.render_widget(note_widget, chunks[3]);
+    }
+
+    let help_widget = Paragraph::new(help_text)
+        .style(Style::default().fg(theme.help))
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Controls")
+                .border_style(Style::default().fg(theme.help)),
+        );
+    f.render_widget(help_widget, chunks[4]);
+}
+
+/// What a draft found on unlocking was made from, and when.
+fn draft_title(recovery: Option<&Recovery>) -> String {
+    match recovery {
+        Some(Recovery { draft, saved, .. }) => format!(
+            "Unsaved {} '{}' in {} · drafted {}",
+            if saved.is_some() {
+                "edit of"
+            } else {
+                "new page"
+            },
+            draft.file,
+            folder_label(&draft.folder),
+            timestamp_label(&draft.drafted)
+        ),
+        None => "Unsaved Draft".to_string(),
+    }
+}
+
+/// Which page an editor recovery was meant for, and when it was kept.
+fn recovery_title(recovery: Option<&Recovery>) -> String {
+    match recovery {
+        Some(Recovery { draft, .. }) => format!(
+            "{} '{}' in {} · kept {}",
+            if draft.new_page {
+                "New page"
+            } else {
+                "Edit of"
+            },
+            draft.file,
+            folder_label(&draft.folder),
+            timestamp_label(&draft.drafted)
+        ),
+        None => "Editor Recovery".to_string(),
+    }
+}
+
+/// The draft offered after unlocking: its text, or with `compare` the lines
+/// it changed from the saved page, `-` for removed and `+` for added.
+pub fn render_draft(
+    f: &mut Frame,
+    area: Rect,
+    title: &str,
+    recovery: Option<&Recovery>,
+    header: &str,
+    help_text: &str,
+    theme: &Theme,
+) {
+    let main_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(3)])
+        .split(area);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([Constraint::Length(3), Constraint::Min(1)])
+        .split(main_chunks[0]);
+
+    let title_widget = Paragraph::new(title)
+        .style(
+            Style::default()
+                .fg(theme.title)
+                .add_modifier(Modifier::BOLD),
+        )
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .title(header)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.title)),
+        );
+    f.render_widget(title_widget, chunks[0]);
+
+    let (label, lines, scroll) = match recovery {
+        Some(Recovery {
+            draft,
+            saved: Some(saved),
+            compare: true,
+            scroll,
+        }) => {
+            let lines: Vec<Line> = line_diff(saved, &draft.content)
+                .into_iter()
+                .map(|(mark, line)| {
+                    let style = match mark {
+                        '-' => Style::default().fg(theme.error),
+                        '+' => Style::default().fg(theme.border),
+                        _ => Style::default(),
+                    };
+                    Line::styled(format!("{} {}", mark, line), style)
+                })
+                .collect();
+            ("Changes from the saved page", lines, *scroll)
+        }
+        Some(Recovery { draft, scroll, .. }) => (
+            "Draft",
+            draft.content.lines().map(Line::raw).collect(),
+            *scroll,
+        ),
+        None => ("Draft", vec![Line::from("Reading the draft…")], 0),
+    };
+    let body = Paragraph::new(lines)
+        .style(Style::default().fg(theme.text))
+        .scroll((scroll, 0))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(label)
+                .border_style(Style::default().fg(theme.border)),
+        );
+    f.render_widget(body, chunks[1]);
+
+    let help_widget = Paragraph::new(help_text)
+        .style(Style::default().fg(theme.help))
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Controls")
+                .border_style(Style::default().fg(theme.help)),
+        );
+    f.render_widget(help_widget, main_chunks[1]);
+}
+
+/// Lines of `old` and `new` marked `-` (only in `old`), `+` (only in `new`)
+/// or ` ` (in both), in order, from their longest common subsequence.
+fn line_diff<'a>(old: &'a str, new: &'a str) -> Vec<(char, &'a str)> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    // common[i][j]: length of the common subsequence of old[i..] and new[j..].
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut diff = Vec::with_capacity(old.len().max(new.len()));
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            diff.push((' ', old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            diff.push(('-', old[i]));
+            i += 1;
+        } else {
+            diff.push(('+', new[j]));
+            j += 1;
+        }
+    }
+    diff
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn render_viewer(
+    f: &mut Frame,
+    area: Rect,
+    viewer: &Viewer,
+    options: ViewOptions,
+    dictionary: Option<&HashSet<String>>,
+    (input, cursor): (&str, usize),
+    help_text: &str,
+    theme: &Theme,
+) {
+    let search_bar = viewer.searching || !viewer.query.is_empty();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(1),
+            Constraint::Length(search_bar.into()),
+            Constraint::Length(3),
+        ])
+        .split(area);
+
+    let page_width = chunks[0].width.saturating_sub(2) as usize;
+    let mut lines = viewer.lines(dictionary, options, theme.title);
+    let spots = viewer.highlight(&mut lines);
+    // Matches are found by line; scrolling to one needs its row once
+    // wrapped, which is taken to be where its column falls.
+    let spots = if options.wrap && !spots.is_empty() {
+        let mut starts = Vec::with_capacity(lines.len());
+        let mut row = 0;
+        for line in &lines {
+            starts.push(row);
+            row += Paragraph::new(line.clone())
+                .wrap(Wrap { trim: false })
+                .line_count(page_width as u16)
+                .max(1);
+        }
+        let width = page_width.max(1);
+        spots
+            .into_iter()
+            .map(|(line, column)| (starts[line] + column / width, column % width))
+            .collect()
+    } else {
+        spots
+    };
+    let match_count = spots.len();
+    viewer.match_spots.replace(spots);
+    let longest = lines.iter().map(Line::width).max().unwrap_or(0);
+    let mut page = Paragraph::new(lines)
+        .style(Style::default().fg(theme.text))
+        .alignment(Alignment::Left)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(if viewer.dates.is_empty() {
+                    format!("View Page · {}", stats::summary(&viewer.content))
+                } else {
+                    format!(
+                        "View Page · {} · {}",
+                        viewer.dates,
+                        stats::summary(&viewer.content)
+                    )
+                })
+                .border_style(Style::default().fg(theme.popup)),
+        );
+    if options.wrap {
+        page = page.wrap(Wrap { trim: false });
+    }
+
+    // line_count includes the top and bottom border.
+    let page_height = chunks[0].height.saturating_sub(2) as usize;
+    let total_lines = page.line_count(chunks[0].width).saturating_sub(2);
+    let max_scroll = total_lines.saturating_sub(page_height);
+    let max_hscroll = if options.wrap {
+        0
+    } else {
+        longest.saturating_sub(page_width)
+    };
+    viewer.page_height.set(page_height.max(1));
+    viewer.page_width.set(page_width.max(1));
+    viewer.max_scroll.set(max_scroll);
+    viewer.max_hscroll.set(max_hscroll);
+    let scroll = viewer.scroll.min(max_scroll);
+    let hscroll = viewer.hscroll.min(max_hscroll);
+
+    f.render_widget(page.scroll((scroll as u16, hscroll as u16)), chunks[0]);
+    if search_bar {
+        render_search_bar(f, chunks[1], viewer, (input, cursor), match_count, theme);
+    }
+    if max_scroll > 0 {
+        let mut scrollbar_state = ScrollbarState::new(max_scroll + 1).position(scroll);
+        f.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight),
+            chunks[0].inner(Margin {
+                vertical: 1,
+                horizontal: 0,
+            }),
+            &mut scrollbar_state,
+        );
+    }
+
+    let help_widget = Paragraph::new(help_text)
+        .style(Style::default().fg(theme.help))
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Controls")
+                .border_style(Style::default().fg(theme.help)),
+        );
+    f.render_widget(help_widget, chunks[2]);
+}
+
+/// The viewer's search line: the query being typed, or the one searched
+/// for with how many matches it has.
+fn render_search_bar(
+    f: &mut Frame,
+    area: Rect,
+    viewer: &Viewer,
+    (input, cursor): (&str, usize),
+    match_count: usize,
+    theme: &Theme,
+) {
+    if viewer.searching {
+        f.render_widget(
+            Paragraph::new(format!(" /{}", input)).style(Style::default().fg(theme.text)),
+            area,
+        );
+        let before_cursor: String = input.chars().take(cursor).collect();
+        let cursor_x = area.x + 2 + Line::from(before_cursor).width() as u16;
+        f.set_cursor_position((cursor_x.min(area.right().saturating_sub(1)), area.y));
+        return;
+    }
+    let count = match match_count {
+        0 => "no matches".to_string(),
+        n => format!("{}/{}", viewer.current_match.min(n - 1) + 1, n),
+    };
+    let line = Line::from(vec![
+        Span::styled(
+            format!(" /{}  ", viewer.query),
+            Style::default().fg(theme.text),
+        ),
+        Span::styled(count, Style::default().fg(theme.highlight)),
+    ]);
+    f.render_widget(Paragraph::new(line), area);
+}
+
+pub fn render_calendar(
+    f: &mut Frame,
+    area: Rect,
+    cursor: NaiveDate,
+    entry_days: &HashSet<NaiveDate>,
+    header: &str,
+    help_text: &str,
+    theme: &Theme,
+) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(3)])
+        .split(area);
+
+    let first = cursor.with_day(1).unwrap_or(cursor);
+    let mut lines = vec![
+        Line::styled(
+            first.format("%B %Y").to_string(),
+            Style::default()
+                .fg(theme.title)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Line::default(),
+        Line::styled(
+            " Mo  Tu  We  Th  Fr  Sa  Su ",
+            Style::default().fg(Color::Gray),
+        ),
+    ];
+    let mut week = vec![Span::raw(
+        "    ".repeat(first.weekday().num_days_from_monday() as usize),
+    )];
+    for day in first
+        .iter_days()
+        .take_while(|day| day.month() == first.month())
+    {
+        let style = if day == cursor {
+            Style::default()
+                .bg(theme.highlight)
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD)
+        } else if entry_days.contains(&day) {
+            Style::default()
+                .fg(theme.border)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+        week.push(Span::styled(format!(" {:>2} ", day.day()), style));
+        if day.weekday().num_days_from_monday() == 6 {
+            lines.push(Line::from(std::mem::take(&mut week)));
+        }
+    }
+    if !week.is_empty() {
+        // Pad the last week so every row has the same width when centred.
+        let last = first
+            .iter_days()
+            .take_while(|day| day.month() == first.month())
+            .last()
+            .unwrap_or(first);
+        week.push(Span::raw(
+            "    ".repeat(6 - last.weekday().num_days_from_monday() as usize),
+        ));
+        lines.push(Line::from(week));
+    }
+
+    let calendar = Paragraph::new(lines).alignment(Alignment::Center).block(
+        Block::default()
+            .title(header)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.title)),
+    );
+    f.render_widget(calendar, chunks[0]);
+
+    let help_widget = Paragraph::new(help_text)
+        .style(Style::default().fg(theme.help))
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Controls")
+                .border_style(Style::default().fg(theme.help)),
+        );
+    f.render_widget(help_widget, chunks[1]);
+}
+
+pub fn render_editor(
+    f: &mut Frame,
+    area: Rect,
+    file: &str,
+    editor: &Editor,
+    help_text: &str,
+    theme: &Theme,
+) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(3)])
+        .split(area);
+
+    // Scroll just far enough to keep the cursor inside the borders.
+    let inner_height = chunks[0].height.saturating_sub(2) as usize;
+    let inner_width = chunks[0].width.saturating_sub(2) as usize;
+    let before_cursor: String = editor.lines[editor.row].chars().take(editor.col).collect();
+    let cursor_x = Line::from(before_cursor).width();
+    let scroll_y = (editor.row + 1).saturating_sub(inner_height);
+    let scroll_x = (cursor_x + 1).saturating_sub(inner_width);
+
+    let lines: Vec<Line> = editor
+        .lines
+        .iter()
+        .map(|line| Line::from(line.as_str()))
+        .collect();
+    let content = editor.content();
+    let mut title = if editor.is_modified() {
+        format!("Editing {} [modified] · {}", file, stats::summary(&content))
+    } else {
+        format!("Editing {} · {}", file, stats::summary(&content))
+    };
+    if editor.is_modal() {
+        title.push_str(match editor.mode {
+            Mode::Normal => " · NORMAL",
+            Mode::Insert => " · INSERT",
+        });
+    }
+    let page = Paragraph::new(lines)
+        .style(Style::default().fg(theme.text))
+        .scroll((scroll_y as u16, scroll_x as u16))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(Style::default().fg(theme.border)),
+        );
+    f.render_widget(page, chunks[0]);
+    f.set_cursor_position((
+        chunks[0].x + 1 + (cursor_x - scroll_x) as u16,
+        chunks[0].y + 1 + (editor.row - scroll_y) as u16,
+    ));
+
+    let help_widget = Paragraph::new(help_text)
+        .style(Style::default().fg(theme.help))
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Controls")
+                .border_style(Style::default().fg(theme.help)),
+        );
+    f.render_widget(help_widget, chunks[1]);
+}
+
+pub fn render_confirm(
+    f: &mut Frame,
+    area: Rect,
+    title: &str,
+    question: &str,
+    help_text: &str,
+    theme: &Theme,
+) {
+    let popup_area = centered_rect(60, 40, area);
+    f.render_widget(Clear, popup_area);
+
+    let mut text: Vec<Line> = question.lines().map(Line::from).collect();
+    text.push(Line::default());
+    text.push(Line::styled(help_text, Style::default().fg(theme.help)));
+    let block = Paragraph::new(text)
+        .style(Style::default().fg(theme.text))
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(Style::default().fg(theme.popup)),
+        )
+        .wrap(Wrap { trim: true });
+    f.render_widget(block, popup_area);
+}
+
+pub fn render_message(f: &mut Frame, message: &str, title: &str, theme: &Theme) {
+    let size = f.area();
+    let popup_area = centered_rect(80, 60, size);
+    f.render_widget(Clear, popup_area);
+    let border = if title == "Error" {
+        theme.error
+    } else {
+        theme.popup
+    };
+
+    let block = Paragraph::new(message)
+        .style(Style::default().fg(theme.text))
+        .alignment(Alignment::Left)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(Style::default().fg(border)),
+        )
+        .wrap(Wrap { trim: true });
+    f.render_widget(block, popup_area);
+}
+
+/// Small box over the screen while a slow decryption runs; `frame` turns
+/// the spinner.
+pub fn render_busy(f: &mut Frame, frame: usize, theme: &Theme) {
+    if too_small(f.area()) {
+        return;
+    }
+    const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+    let text = format!("{} Decrypting…", SPINNER[frame % SPINNER.len()]);
+    let size = f.area();
+    let width = (Line::from(text.as_str()).width() as u16 + 4).min(size.width);
+    let area = Rect::new(
+        size.x + size.width.saturating_sub(width) / 2,
+        size.y + size.height.saturating_sub(3) / 2,
+        width,
+        3.min(size.height),
+    );
+    f.render_widget(Clear, area);
+    let busy = Paragraph::new(text)
+        .style(Style::default().fg(theme.text))
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.popup)),
+        );
+    f.render_widget(busy, area);
+}
+
+/// Shown instead of the screen while the terminal is under the minimum
+/// size; the normal layout comes back on the next resize.
+fn render_too_small(f: &mut Frame, theme: &Theme) {
+    let notice = Paragraph::new(format!(
+        "Terminal too small ({}x{} needed)",
+        MIN_WIDTH, MIN_HEIGHT
+    ))
+    .style(Style::default().fg(theme.error))
+    .alignment(Alignment::Center)
+    .wrap(Wrap { trim: true });
+    f.render_widget(notice, f.area());
+}
+
+pub fn render_help(f: &mut Frame, sections: &[(&str, Vec<KeyHint>)], theme: &Theme) {
+    let size = f.area();
+    let popup_area = centered_rect(80, 90, size);
+    f.render_widget(Clear, popup_area);
+
+    let key_width = sections
+        .iter()
+        .flat_map(|(_, keys)| {
+            keys.iter()
+                .map(|(keys, _)| Line::from(keys.as_ref()).width())
+        })
+        .max()
+        .unwrap_or(0);
+    let mut lines = Vec::new();
+    for (title, keys) in sections {
+        if !lines.is_empty() {
+            lines.push(Line::default());
+        }
+        lines.push(Line::from(Span::styled(
+            *title,
+            Style::default()
+                .fg(theme.title)
+                .add_modifier(Modifier::BOLD),
+        )));
+        for (keys, action) in keys {
+            let pad = key_width - Line::from(keys.as_ref()).width();
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("  {}{}  ", keys, " ".repeat(pad)),
+                    Style::default().fg(theme.help),
+                ),
+                Span::raw(*action),
+            ]));
+        }
+    }
+
+    let help = Paragraph::new(lines)
+        .style(Style::default().fg(theme.text))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Help · any key to close")
+                .border_style(Style::default().fg(theme.popup)),
+        )
+        .wrap(Wrap { trim: false });
+    f.render_widget(help, popup_area);
+}
+
+pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(r);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(popup_layout[1])[1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{Terminal, backend::TestBackend};
+
+    #[test]
+    fn user_list_and_its_hints() {
+        let data = tempfile::tempdir().unwrap();
+        let config = tempfile::tempdir().unwrap();
+        let mut app = App::with_dirs(data.path().to_path_buf(), config.path().to_path_buf());
+        app.set_items(vec![
+            ("alice.clog".to_string(), String::new()),
+            (crate::app::ADD_USER_ITEM.to_string(), String::new()),
+        ]);
+        let mut terminal = Terminal::new(TestBackend::new(100, 14)).unwrap();
+        terminal.draw(|f| draw(f, &app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let lines: Vec<String> = (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect();
+        let expected = [
+            "",
+            "",
+            " ┌────────────────────────────────────────────────────────────────────────────────────────────────┐",
+            " │                                           Select User                                          │",
+            " └────────────────────────────────────────────────────────────────────────────────────────────────┘",
+            " ┌Options (1/2)───────────────────────────────────────────────────────────────────────────────────┐",
+            " │► alice.clog                                                                                    │",
+            " │  Add New User                                                                                  │",
+            " │                                                                                                │",
+            " └────────────────────────────────────────────────────────────────────────────────────────────────┘",
+            "",
+            "┌Controls──────────────────────────────────────────────────────────────────────────────────────────┐",
+            "│ ↑/↓ or j/k: Navigate | Enter: Select | s: Sorted by modified | R: Recoveries | ?: Help | q: Quit │",
+            "└──────────────────────────────────────────────────────────────────────────────────────────────────┘",
+        ];
+        assert_eq!(lines, expected);
+    }
+}
@@ -0,0 +1,329 @@
+//! Read-only page viewer.
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+use crate::markdown;
+
+/// Word lists tried when no `dictionary` is configured.
+const SYSTEM_DICTIONARIES: &[&str] = &["/usr/share/dict/words", "/usr/dict/words"];
+
+/// How pages are laid out in the viewer. Kept on `App`, so a toggle lasts
+/// for the rest of the session.
+#[derive(Clone, Copy, Debug)]
+pub struct ViewOptions {
+    pub wrap: bool,
+    pub line_numbers: bool,
+    /// Style Markdown instead of showing the page as written.
+    pub markdown: bool,
+}
+
+impl Default for ViewOptions {
+    fn default() -> Self {
+        Self {
+            wrap: true,
+            line_numbers: false,
+            markdown: false,
+        }
+    }
+}
+
+pub struct Viewer {
+    pub file: String,
+    pub content: String,
+    /// When the page was created and last edited, for the title.
+    pub dates: String,
+    pub spellcheck: bool,
+    /// First visible line, counted after wrapping.
+    pub scroll: usize,
+    /// Scroll limits measured by the last render, which is the only place
+    /// that knows the size of the wrapped page.
+    pub max_scroll: Cell<usize>,
+    pub page_height: Cell<usize>,
+    /// First visible column when lines aren't wrapped.
+    pub hscroll: usize,
+    pub max_hscroll: Cell<usize>,
+    pub page_width: Cell<usize>,
+    /// Text searched for with `/`; empty when there is no search.
+    pub query: String,
+    /// The query is still being typed.
+    pub searching: bool,
+    /// The match last moved to with `n`/`N`.
+    pub current_match: usize,
+    /// Where the last render drew each match: its row, counted after
+    /// wrapping, and its column.
+    pub match_spots: RefCell<Vec<(usize, usize)>>,
+}
+
+impl Viewer {
+    pub fn new(file: &str, content: &str) -> Self {
+        Self {
+            file: file.to_string(),
+            content: content.to_string(),
+            dates: String::new(),
+            spellcheck: false,
+            scroll: 0,
+            max_scroll: Cell::new(0),
+            page_height: Cell::new(1),
+            hscroll: 0,
+            max_hscroll: Cell::new(0),
+            page_width: Cell::new(1),
+            query: String::new(),
+            searching: false,
+            current_match: 0,
+            match_spots: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.scroll = self.scroll.min(self.max_scroll.get()).saturating_sub(lines);
+    }
+
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.scroll = (self.scroll + lines).min(self.max_scroll.get());
+    }
+
+    pub fn scroll_to_end(&mut self) {
+        self.scroll = self.max_scroll.get();
+    }
+
+    pub fn scroll_left(&mut self, columns: usize) {
+        self.hscroll = self
+            .hscroll
+            .min(self.max_hscroll.get())
+            .saturating_sub(columns);
+    }
+
+    pub fn scroll_right(&mut self, columns: usize) {
+        self.hscroll = (self.hscroll + columns).min(self.max_hscroll.get());
+    }
+
+    pub fn clear_search(&mut self) {
+        self.query.clear();
+        self.searching = false;
+        self.current_match = 0;
+        self.match_spots.borrow_mut().clear();
+    }
+
+    /// The first match from the top of the view down, or the first of all
+    /// when none is below.
+    pub fn first_match_in_view(&self) -> usize {
+        let scroll = self.scroll.min(self.max_scroll.get());
+        self.match_spots
+            .borrow()
+            .iter()
+            .position(|&(row, _)| row >= scroll)
+            .unwrap_or(0)
+    }
+
+    /// The match after the current one, or before it, wrapping around.
+    pub fn step_match(&self, forward: bool) -> usize {
+        let count = self.match_spots.borrow().len().max(1);
+        if forward {
+            (self.current_match + 1) % count
+        } else {
+            (self.current_match + count - 1) % count
+        }
+    }
+
+    /// Makes `index` the current match and scrolls it into view, a third of
+    /// the way down the page when it was off screen.
+    pub fn show_match(&mut self, index: usize) {
+        let Some(&(row, column)) = self.match_spots.borrow().get(index) else {
+            return;
+        };
+        self.current_match = index;
+        let height = self.page_height.get();
+        let scroll = self.scroll.min(self.max_scroll.get());
+        if row < scroll || row >= scroll + height {
+            self.scroll = row.saturating_sub(height / 3).min(self.max_scroll.get());
+        }
+        let width = self.page_width.get();
+        if column < self.hscroll || column >= self.hscroll + width {
+            self.hscroll = column.saturating_sub(width / 2).min(self.max_hscroll.get());
+        }
+    }
+
+    /// Highlights the search matches in `lines`, as made by `lines`, and
+    /// returns the line and column each starts at.
+    pub fn highlight(&self, lines: &mut [Line<'_>]) -> Vec<(usize, usize)> {
+        // The first two lines name the page rather than being part of it.
+        highlight_matches(&mut lines[2..], &self.query, self.current_match)
+            .into_iter()
+            .map(|(line, column)| (line + 2, column))
+            .collect()
+    }
+
+    /// The page as drawn: raw lines, spellchecked or numbered as asked, or
+    /// rendered Markdown with `heading` as the colour of headings.
+    pub fn lines(
+        &self,
+        dictionary: Option<&HashSet<String>>,
+        options: ViewOptions,
+        heading: Color,
+    ) -> Vec<Line<'_>> {
+        let mut lines = vec![
+            Line::from(format!("[READ-ONLY] Content of {}:", self.file)),
+            Line::default(),
+        ];
+        if options.markdown {
+            lines.extend(markdown::render(&self.content, heading));
+            return lines;
+        }
+        let number_style = Style::default().fg(Color::DarkGray);
+        for (i, line) in self.content.lines().enumerate() {
+            let mut rendered = match dictionary {
+                Some(words) if self.spellcheck => spellchecked_line(line, words),
+                _ => Line::from(line),
+            };
+            if options.line_numbers {
+                rendered
+                    .spans
+                    .insert(0, Span::styled(format!("{:>4} │ ", i + 1), number_style));
+            }
+            lines.push(rendered);
+        }
+        lines
+    }
+}
+
+/// Gives every case-insensitive occurrence of `query` in `lines` a
+/// background, a brighter one for match number `current`, and returns the
+/// line and column each match starts at. Matches are found span by span, so
+/// one running across a change of style, as in Markdown, isn't found.
+fn highlight_matches(lines: &mut [Line<'_>], query: &str, current: usize) -> Vec<(usize, usize)> {
+    let matched = Style::default().fg(Color::Black).bg(Color::Yellow);
+    let current_style = Style::default()
+        .fg(Color::Black)
+        .bg(Color::LightRed)
+        .add_modifier(Modifier::BOLD);
+    let mut spots = Vec::new();
+    for (i, line) in lines.iter_mut().enumerate() {
+        let mut column = 0;
+        let mut spans = Vec::with_capacity(line.spans.len());
+        for span in line.spans.drain(..) {
+            let ranges = find_matches(&span.content, query);
+            if ranges.is_empty() {
+                column += span.width();
+                spans.push(span);
+                continue;
+            }
+            let text = span.content.as_ref();
+            let mut end_of_last = 0;
+            for (start, end) in ranges {
+                if start > end_of_last {
+                    spans.push(Span::styled(
+                        text[end_of_last..start].to_string(),
+                        span.style,
+                    ));
+                }
+                let style = if spots.len() == current {
+                    current_style
+                } else {
+                    matched
+                };
+                spans.push(Span::styled(
+                    text[start..end].to_string(),
+                    span.style.patch(style),
+                ));
+                spots.push((i, column + Line::from(&text[..start]).width()));
+                end_of_last = end;
+            }
+            if end_of_last < text.len() {
+                spans.push(Span::styled(text[end_of_last..].to_string(), span.style));
+            }
+            column += span.width();
+        }
+        line.spans = spans;
+    }
+    spots
+}
+
+/// Byte ranges of the occurrences of `query` in `text`, ignoring case and
+/// not overlapping.
+fn find_matches(text: &str, query: &str) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    if query.is_empty() {
+        return ranges;
+    }
+    let mut from = 0;
+    while from < text.len() {
+        let rest = &text[from..];
+        let mut ends = rest.char_indices().map(|(i, c)| (i + c.len_utf8(), c));
+        let matched = query.chars().try_fold(0, |_, q| match ends.next() {
+            Some((end, c)) if c.to_lowercase().eq(q.to_lowercase()) => Some(end),
+            _ => None,
+        });
+        match matched {
+            Some(len) => {
+                ranges.push((from, from + len));
+                from += len;
+            }
+            None => from += rest.chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    ranges
+}
+
+/// Splits `line` into words and the text between them, underlining words
+/// the dictionary doesn't know.
+fn spellchecked_line<'a>(line: &'a str, words: &HashSet<String>) -> Line<'a> {
+    let misspelled = Style::default()
+        .fg(Color::Red)
+        .add_modifier(Modifier::UNDERLINED);
+    let mut spans = Vec::new();
+    let mut start = 0;
+    let mut in_word = false;
+    for (i, c) in line.char_indices().chain([(line.len(), ' ')]) {
+        let is_word_char = c.is_alphabetic() || (in_word && c == '\'');
+        if is_word_char == in_word {
+            continue;
+        }
+        let segment = &line[start..i];
+        if in_word && !is_known(segment, words) {
+            spans.push(Span::styled(segment, misspelled));
+        } else if !segment.is_empty() {
+            spans.push(Span::raw(segment));
+        }
+        start = i;
+        in_word = is_word_char;
+    }
+    Line::from(spans)
+}
+
+fn is_known(word: &str, words: &HashSet<String>) -> bool {
+    let word = word.trim_end_matches('\'');
+    words.contains(&word.to_lowercase())
+        || word
+            .strip_suffix("'s")
+            .is_some_and(|stem| words.contains(&stem.to_lowercase()))
+}
+
+pub fn load_dictionary(configured: Option<&Path>) -> Result<HashSet<String>, AppError> {
+    let path = match configured {
+        Some(path) => path.to_path_buf(),
+        None => SYSTEM_DICTIONARIES
+            .iter()
+            .map(PathBuf::from)
+            .find(|path| path.is_file())
+            .ok_or_else(|| {
+                AppError::Config(
+                    "No dictionary found. Set `dictionary` in config.toml to a word list."
+                        .to_string(),
+                )
+            })?,
+    };
+    let raw = fs::read_to_string(&path)?;
+    Ok(raw
+        .lines()
+        .map(|word| word.trim().to_lowercase())
+        .filter(|word| !word.is_empty())
+        .collect())
+}